@@ -1,4 +1,4 @@
-import { ref, onMounted, onUnmounted } from 'vue';
+import { ref, onUnmounted } from 'vue';
 import { TaskAPI } from '../utils/taskAPI';
 import type { Task } from '../types/task';
 
@@ -112,10 +112,8 @@ export function useNotifications() {
     notifiedTasks.value.clear();
   };
 
-  // 组件挂载时启动检查
-  onMounted(() => {
-    startPeriodicCheck();
-  });
+  // 到期和过期提醒由后端调度器负责（窗口隐藏到托盘时同样生效），
+  // 前端不再自动轮询，startPeriodicCheck 仅保留供手动调用
 
   // 组件卸载时清理
   onUnmounted(() => {
@@ -9,15 +9,87 @@ export interface Task {
   created_at: string;
   tags: string[]; // 任务标签列表
   sort_order: number; // 新增：排序顺序
+  recurrence?: RecurrenceRule | null; // 重复规则（为空表示不重复）
+  parent_id?: number | null; // 父任务ID（为空表示顶层任务）
+  subtask_count: number; // 直接子任务数量
+  completed_subtask_count: number; // 已完成的直接子任务数量
+  project_id?: number | null; // 所属项目ID（为空表示收集箱）
+  deleted_at?: string | null; // 移入回收站的时间（为空表示未删除）
+  is_all_day?: boolean; // 全天任务：due_date 为该日期的 UTC 零点，只按日期显示
 }
 
-export interface Tag {
+// 项目（任务清单）
+export interface Project {
   id?: number;
   name: string;
+  color: string; // 项目颜色（十六进制）
+  icon: string;
+  is_archived: boolean;
+  sort_order: number;
+  created_at: string;
+}
+
+export interface CreateProjectRequest {
+  name: string;
+  color?: string;
+  icon?: string;
+}
+
+export interface UpdateProjectRequest {
+  id: number;
+  name?: string;
+  color?: string;
+  icon?: string;
+  is_archived?: boolean;
+  sort_order?: number;
+}
+
+export interface MoveTasksToProjectRequest {
+  task_ids: number[];
+  project_id?: number | null; // 为空表示移回收集箱
+}
+
+export interface TaskOrderItem {
+  id: number;
+  sort_order: number;
+}
+
+export interface UpdateTaskSortOrderRequest {
+  task_orders: TaskOrderItem[];
+  project_id?: number | null; // 指定时只调整该项目内任务的顺序
+}
+
+export type RecurrenceFrequency = 'daily' | 'weekly' | 'monthly' | 'yearly';
+
+export interface RecurrenceRule {
+  frequency: RecurrenceFrequency;
+  interval: number; // 间隔，如每 2 周
+  by_weekday?: number[]; // 指定星期几（0 = 周日 ... 6 = 周六）
+  until?: string | null; // 截止日期
+  count?: number | null; // 剩余次数（含当前这一次）
+}
+
+export interface Tag {
+  id?: number;
+  name: string; // 完整路径，如 work/clientA/billing
+  parent_id?: number | null; // 上级标签，顶级标签为空
   color: string; // 标签颜色（十六进制）
   created_at: string;
 }
 
+// 标签使用情况
+export interface TagStats {
+  tag: Tag;
+  auto_created: boolean; // 是否为编辑任务时自动创建的标签
+  open_count: number; // 关联的未完成任务数
+  completed_count: number; // 关联的已完成任务数
+}
+
+export interface MergeTagsRequest {
+  source_ids: number[]; // 被合并的标签，合并后删除
+  target_id: number; // 保留的标签
+}
+
 export enum TaskPriority {
   Low = 0,
   Medium = 1,
@@ -30,16 +102,174 @@ export interface CreateTaskRequest {
   due_date?: string;
   priority: TaskPriority;
   tags?: string[]; // 新增：创建任务时的标签
+  recurrence?: RecurrenceRule; // 重复规则
+  parent_id?: number; // 父任务ID，创建子任务时使用
+  project_id?: number; // 所属项目ID，子任务始终跟随父任务所在的项目
+  is_all_day?: boolean; // 全天任务，due_date 只取日期部分（如 2024-05-01T00:00:00Z）
 }
 
+// 更新任务时，可清除的字段不传表示保持不变，传 null 表示清除
 export interface UpdateTaskRequest {
   id: number;
   title?: string;
-  description?: string;
-  due_date?: string;
+  description?: string | null; // null 表示清空描述
+  due_date?: string | null; // null 表示移除截止时间
   priority?: TaskPriority;
   is_completed?: boolean;
-  tags?: string[]; // 新增：更新任务时的标签
+  tags?: string[] | null; // null 或空列表表示移除所有标签
+  recurrence?: RecurrenceRule | null; // null 表示取消重复
+  is_all_day?: boolean; // 全天任务，due_date 只取日期部分
+}
+
+// 任务筛选条件，所有条件之间为“且”的关系，未设置的条件不参与筛选
+export interface TaskFilter {
+  tags_any?: string[]; // 包含任意一个标签
+  tags_all?: string[]; // 包含全部标签
+  tags_none?: string[]; // 不包含这些标签
+  priorities?: TaskPriority[];
+  project_ids?: number[]; // 属于任意一个项目
+  due_before?: string | null;
+  due_after?: string | null;
+  due_range?: RelativeDueRange | null; // 相对时间范围，查询时按当前时间计算
+  is_completed?: boolean | null;
+  text?: string | null; // 全文搜索关键词（语法同 searchTasks）
+  created_before?: string | null;
+  created_after?: string | null;
+}
+
+// 相对截止时间范围，适合保存在智能列表中（如“本周到期”）
+export type RelativeDueRange = 'overdue' | 'today' | 'tomorrow' | 'this_week' | 'next_7_days' | 'this_month';
+
+// 截止时间窗口：已过期、N 分钟内到期、截止时间在 [start, end) 内
+export type DueWindow =
+  | { kind: 'overdue' }
+  | { kind: 'due_within'; minutes: number }
+  | { kind: 'between'; start: string; end: string };
+
+export type TaskSortField = 'sort_order' | 'due_date' | 'priority' | 'created_at' | 'title';
+
+export interface TaskSortKey {
+  field: TaskSortField;
+  descending?: boolean;
+}
+
+export interface TaskQuery {
+  filter?: TaskFilter;
+  sort?: TaskSortKey[]; // 为空时按 sort_order 排序
+  limit?: number | null;
+  offset?: number | null;
+}
+
+export interface TaskQueryResult {
+  tasks: Task[];
+  total: number; // 符合条件的任务总数（不受分页影响）
+}
+
+// 智能列表：保存的任务查询
+export interface SmartList {
+  id?: number;
+  name: string;
+  icon: string;
+  query: TaskQuery;
+  sort_order: number;
+  created_at: string;
+  task_count: number; // 当前符合条件的任务数量
+}
+
+export interface CreateSmartListRequest {
+  name: string;
+  icon?: string;
+  query: TaskQuery;
+}
+
+export interface UpdateSmartListRequest {
+  id: number;
+  name?: string;
+  icon?: string;
+  query?: TaskQuery;
+  sort_order?: number;
+}
+
+export interface SearchResult {
+  task: Task;
+  title_highlight: string; // 高亮后的标题 HTML（命中部分以 <mark> 包裹，其余文本已转义）
+  snippet: string; // 描述中命中位置附近的片段，格式同 title_highlight
+  rank: number; // 相关度（越小越相关）
+}
+
+export interface TaskHistoryEntry {
+  id: number;
+  task_id: number;
+  field: 'title' | 'description' | 'due_date' | 'priority' | 'is_completed' | 'recurrence' | 'tags';
+  old_value?: unknown; // 字段原值（与 Task 中对应字段的类型一致）
+  new_value?: unknown; // 字段新值
+  changed_at: string;
+}
+
+// 数据一致性检查发现的一类问题
+export interface ConsistencyIssue {
+  kind: string; // 问题类型，如 orphaned_task_tags
+  description: string;
+  count: number; // 涉及的记录数
+}
+
+export interface ConsistencyReport {
+  issues: ConsistencyIssue[]; // 为空表示数据一致
+  repaired: boolean;
+}
+
+export type EncryptionMode = 'none' | 'stored_key' | 'passphrase';
+
+export type KeyStorage = 'keyring' | 'file';
+
+export interface EncryptionStatus {
+  mode: EncryptionMode;
+  is_unlocked: boolean; // 使用密码加密时，输入密码前为 false
+  key_storage?: KeyStorage; // 自动生成的密钥保存位置
+}
+
+// 数据目录的来源，按优先级：命令行参数、环境变量、便携模式、移动后的目录、旧版本目录、平台数据目录
+export type DataDirSource = 'command_line' | 'environment' | 'portable' | 'moved' | 'legacy' | 'platform';
+
+export interface DataDirectoryInfo {
+  path: string;
+  source: DataDirSource;
+  is_movable: boolean; // 由命令行参数、环境变量或便携模式指定时不能移动
+}
+
+// 配置：每个配置使用单独的数据库文件
+export interface Profile {
+  id: number;
+  name: string;
+  created_at: string;
+  is_active: boolean; // 是否为当前使用的配置
+}
+
+export interface CreateProfileRequest {
+  name: string;
+}
+
+export interface RenameProfileRequest {
+  id: number;
+  name: string;
+}
+
+export interface AppLockStatus {
+  is_enabled: boolean; // 是否设置了应用锁密码
+  is_locked: boolean; // 锁定时数据命令会返回"应用已锁定"错误
+  auto_lock_minutes: number; // 空闲多少分钟后自动锁定（0 表示不自动锁定）
+}
+
+export interface UndoStatus {
+  can_undo: boolean;
+  can_redo: boolean;
+  undo_description?: string; // 下一次撤销的操作描述
+  redo_description?: string; // 下一次重做的操作描述
+}
+
+export interface MoveSubtaskRequest {
+  id: number;
+  parent_id?: number | null; // 新的父任务ID，为空表示移动到顶层
 }
 
 export interface CreateTagRequest {
@@ -51,4 +281,20 @@ export interface UpdateTagRequest {
   id: number;
   name?: string;
   color?: string;
-}
\ No newline at end of file
+}
+// 命令失败时返回的错误
+export type ErrorCode = 'not_found' | 'validation' | 'conflict' | 'locked' | 'storage' | 'io';
+
+export interface AppError {
+  code: ErrorCode; // 错误码，用于区分错误类型并显示本地化提示
+  message: string; // 中文说明
+  details: {
+    entity?: string; // 不存在的对象类型：task、project、smart_list、tag
+    id?: number;
+    field?: string; // 校验失败的字段名
+  };
+}
+
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+}
@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import type { Task, CreateTaskRequest, UpdateTaskRequest, Tag, CreateTagRequest, UpdateTagRequest } from '../types/task';
+import type { Task, CreateTaskRequest, UpdateTaskRequest, Tag, TagStats, MergeTagsRequest, CreateTagRequest, UpdateTagRequest, MoveSubtaskRequest, SearchResult, TaskQuery, TaskQueryResult, DueWindow, SmartList, CreateSmartListRequest, UpdateSmartListRequest, Project, CreateProjectRequest, UpdateProjectRequest, MoveTasksToProjectRequest, UpdateTaskSortOrderRequest, UndoStatus, TaskHistoryEntry, ConsistencyReport, EncryptionStatus, AppLockStatus, DataDirectoryInfo, Profile, CreateProfileRequest, RenameProfileRequest } from '../types/task';
 
 export class TaskAPI {
   static async createTask(request: CreateTaskRequest): Promise<Task> {
@@ -30,6 +30,10 @@ export class TaskAPI {
     return await invoke('toggle_task_completion', { id });
   }
 
+  static async getTaskHistory(id: number): Promise<TaskHistoryEntry[]> {
+    return await invoke('get_task_history', { id });
+  }
+
   static async exportTasksToJson(): Promise<string> {
     return await invoke('export_tasks_to_json');
   }
@@ -42,6 +46,115 @@ export class TaskAPI {
     return await invoke('clear_all_tasks');
   }
 
+  static async queryTasks(query: TaskQuery): Promise<TaskQueryResult> {
+    return await invoke('query_tasks', { query });
+  }
+
+  static async searchTasks(query: string, limit?: number): Promise<SearchResult[]> {
+    return await invoke('search_tasks', { query, limit });
+  }
+
+  static async updateTasksSortOrder(request: UpdateTaskSortOrderRequest): Promise<void> {
+    return await invoke('update_tasks_sort_order', { request });
+  }
+
+  // === 项目相关方法 ===
+
+  static async createProject(request: CreateProjectRequest): Promise<Project> {
+    return await invoke('create_project', { request });
+  }
+
+  static async getProjects(includeArchived = false): Promise<Project[]> {
+    return await invoke('get_projects', { includeArchived });
+  }
+
+  static async updateProject(request: UpdateProjectRequest): Promise<Project> {
+    return await invoke('update_project', { request });
+  }
+
+  static async deleteProject(id: number): Promise<void> {
+    return await invoke('delete_project', { id });
+  }
+
+  static async getProjectTasks(projectId: number | null): Promise<Task[]> {
+    return await invoke('get_project_tasks', { projectId });
+  }
+
+  static async moveTasksToProject(request: MoveTasksToProjectRequest): Promise<void> {
+    return await invoke('move_tasks_to_project', { request });
+  }
+
+  // === 智能列表相关方法 ===
+
+  static async createSmartList(request: CreateSmartListRequest): Promise<SmartList> {
+    return await invoke('create_smart_list', { request });
+  }
+
+  static async getSmartLists(): Promise<SmartList[]> {
+    return await invoke('get_smart_lists');
+  }
+
+  static async updateSmartList(request: UpdateSmartListRequest): Promise<SmartList> {
+    return await invoke('update_smart_list', { request });
+  }
+
+  static async deleteSmartList(id: number): Promise<void> {
+    return await invoke('delete_smart_list', { id });
+  }
+
+  static async getSmartListTasks(id: number): Promise<TaskQueryResult> {
+    return await invoke('get_smart_list_tasks', { id });
+  }
+
+  // === 回收站相关方法 ===
+
+  static async getTrashedTasks(): Promise<Task[]> {
+    return await invoke('get_trashed_tasks');
+  }
+
+  static async restoreTask(id: number): Promise<Task> {
+    return await invoke('restore_task', { id });
+  }
+
+  static async emptyTrash(): Promise<number> {
+    return await invoke('empty_trash');
+  }
+
+  // === 撤销/重做相关方法 ===
+
+  // 返回被撤销的操作描述，没有可撤销的操作时返回 null
+  static async undo(): Promise<string | null> {
+    return await invoke('undo');
+  }
+
+  static async redo(): Promise<string | null> {
+    return await invoke('redo');
+  }
+
+  static async getUndoStatus(): Promise<UndoStatus> {
+    return await invoke('get_undo_status');
+  }
+
+  // === 数据维护相关方法 ===
+
+  static async checkDatabaseConsistency(repair = false): Promise<ConsistencyReport> {
+    return await invoke('check_database_consistency', { repair });
+  }
+
+  // === 子任务相关方法 ===
+
+  static async createSubtask(parentId: number, request: CreateTaskRequest): Promise<Task> {
+    return await invoke('create_subtask', { parentId, request });
+  }
+
+  static async getSubtasks(parentId: number): Promise<Task[]> {
+    return await invoke('get_subtasks', { parentId });
+  }
+
+  static async moveSubtask(request: MoveSubtaskRequest): Promise<Task> {
+    return await invoke('move_subtask', { request });
+  }
+
   // === 标签相关方法 ===
 
   static async createTag(request: CreateTagRequest): Promise<Tag> {
@@ -60,6 +173,18 @@ export class TaskAPI {
     return await invoke('delete_tag', { id });
   }
 
+  static async getTagStats(): Promise<TagStats[]> {
+    return await invoke('get_tag_stats');
+  }
+
+  static async mergeTags(request: MergeTagsRequest): Promise<Tag> {
+    return await invoke('merge_tags', { request });
+  }
+
+  static async deleteUnusedTags(): Promise<number> {
+    return await invoke('delete_unused_tags');
+  }
+
   // === 设置相关方法 ===
 
   static async getAppSettings(): Promise<any> {
@@ -70,6 +195,79 @@ export class TaskAPI {
     return await invoke('update_app_settings', { request });
   }
 
+  // === 加密相关方法 ===
+
+  static async getEncryptionStatus(): Promise<EncryptionStatus> {
+    return await invoke('get_encryption_status');
+  }
+
+  static async unlockDatabase(passphrase: string): Promise<void> {
+    return await invoke('unlock_database', { passphrase });
+  }
+
+  // 不提供密码时使用自动生成的密钥（保存在系统钥匙串中）
+  static async encryptDatabase(passphrase?: string): Promise<EncryptionStatus> {
+    return await invoke('encrypt_database', { passphrase });
+  }
+
+  static async changeDatabaseKey(newPassphrase?: string): Promise<EncryptionStatus> {
+    return await invoke('change_database_key', { newPassphrase });
+  }
+
+  // === 数据目录相关方法 ===
+
+  static async getDataDirectory(): Promise<DataDirectoryInfo> {
+    return await invoke('get_data_directory');
+  }
+
+  // 使用密码加密的数据库需要再次输入密码
+  static async moveDataDirectory(target: string, passphrase?: string): Promise<DataDirectoryInfo> {
+    return await invoke('move_data_directory', { target, passphrase });
+  }
+
+  // === 配置相关方法 ===
+
+  static async getProfiles(): Promise<Profile[]> {
+    return await invoke('get_profiles');
+  }
+
+  static async createProfile(request: CreateProfileRequest): Promise<Profile> {
+    return await invoke('create_profile', { request });
+  }
+
+  static async renameProfile(request: RenameProfileRequest): Promise<Profile> {
+    return await invoke('rename_profile', { request });
+  }
+
+  // 不能删除当前使用的配置；要删除的配置设置了应用锁或使用密码加密时需要提供该配置的密码
+  static async deleteProfile(id: number, passphrase?: string): Promise<void> {
+    return await invoke('delete_profile', { id, passphrase });
+  }
+
+  // 切换后后端会发送 profile-changed 事件；新配置使用密码加密时需要先调用 unlockDatabase
+  static async switchProfile(id: number): Promise<Profile> {
+    return await invoke('switch_profile', { id });
+  }
+
+  // === 应用锁相关方法 ===
+
+  static async getAppLockStatus(): Promise<AppLockStatus> {
+    return await invoke('get_app_lock_status');
+  }
+
+  static async unlockApp(passphrase: string): Promise<void> {
+    return await invoke('unlock_app', { passphrase });
+  }
+
+  static async lockApp(): Promise<void> {
+    return await invoke('lock_app');
+  }
+
+  // 不提供新密码表示关闭应用锁
+  static async setAppLockPassphrase(currentPassphrase?: string, newPassphrase?: string): Promise<void> {
+    return await invoke('set_app_lock_passphrase', { currentPassphrase, newPassphrase });
+  }
+
   // === 窗口管理方法 ===
 
   static async handleCloseRequest(): Promise<string> {
@@ -98,4 +296,8 @@ export class TaskAPI {
     return await invoke('get_upcoming_tasks', { minutesAhead });
   }
 
+  static async getTasksDue(window: DueWindow): Promise<Task[]> {
+    return await invoke('get_tasks_due', { window });
+  }
+
 }
\ No newline at end of file
@@ -0,0 +1,132 @@
+//! 任务列表查询的基准测试
+//!
+//! 需求文档要求 10,000 个任务时列表的加载时间在 100ms 以内。运行方式：
+//!
+//! ```text
+//! cargo bench --bench task_list
+//! ```
+//!
+//! 在临时目录中生成未加密的测试数据库，任一查询的中位耗时超过上限时以非零状态退出。
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use app_lib::database::Database;
+use app_lib::error::AppError;
+use app_lib::models::{CreateTaskRequest, Patch, TaskFilter, TaskPriority, TaskQuery, UpdateTaskRequest};
+use chrono::Utc;
+
+const TASK_COUNT: usize = 10_000;
+const ITERATIONS: usize = 10;
+const LATENCY_LIMIT: Duration = Duration::from_millis(100);
+
+/// 一个被测查询，返回查到的任务数量
+type Case<'a> = Box<dyn Fn() -> Result<usize, AppError> + 'a>;
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("mytodo-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("无法创建临时目录");
+
+    let passed = run(&dir.join("tasks.db"));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    if !passed {
+        std::process::exit(1);
+    }
+}
+
+fn run(path: &Path) -> bool {
+    let db = Database::open_unencrypted(path).expect("无法打开数据库");
+
+    let started = Instant::now();
+    db.record_undo_step("生成基准测试数据", populate).expect("生成测试数据失败");
+    println!("生成 {} 个任务耗时 {:.2?}\n", TASK_COUNT, started.elapsed());
+
+    let tag_query = TaskQuery {
+        filter: TaskFilter { tags_any: vec!["work".to_string()], ..Default::default() },
+        ..Default::default()
+    };
+    let page_query = TaskQuery { limit: Some(50), ..Default::default() };
+
+    let cases: Vec<(&str, Case)> = vec![
+        ("get_all_tasks", Box::new(|| Ok(db.get_all_tasks()?.len()))),
+        ("get_today_tasks", Box::new(|| Ok(db.get_today_tasks()?.len()))),
+        ("get_completed_tasks", Box::new(|| Ok(db.get_completed_tasks()?.len()))),
+        ("get_project_tasks", Box::new(|| Ok(db.get_project_tasks(None)?.len()))),
+        ("get_overdue_tasks", Box::new(|| Ok(db.get_overdue_tasks()?.len()))),
+        ("query_tasks (标签 work)", Box::new(|| Ok(db.query_tasks(&tag_query)?.tasks.len()))),
+        ("query_tasks (分页 50)", Box::new(|| Ok(db.query_tasks(&page_query)?.tasks.len()))),
+    ];
+
+    let mut passed = true;
+    for (name, case) in cases {
+        let mut timings = Vec::with_capacity(ITERATIONS);
+        let mut rows = 0;
+        for _ in 0..ITERATIONS {
+            let started = Instant::now();
+            rows = case().expect("查询失败");
+            timings.push(started.elapsed());
+        }
+        timings.sort();
+
+        let median = timings[ITERATIONS / 2];
+        let within_limit = median <= LATENCY_LIMIT;
+        passed &= within_limit;
+        println!(
+            "{:<24} {:>6} 行  中位 {:>10.2?}  最慢 {:>10.2?}  {}",
+            name,
+            rows,
+            median,
+            timings[ITERATIONS - 1],
+            if within_limit { "ok" } else { "超出上限" },
+        );
+    }
+
+    passed
+}
+
+/// 生成测试数据：带层级标签、子任务、不同截止时间，四分之一已完成
+fn populate(db: &Database) -> Result<(), AppError> {
+    let now = Utc::now();
+    let mut parent_id = None;
+
+    for i in 0..TASK_COUNT {
+        let mut tags = vec![format!("work/client{}", i % 20)];
+        if i % 3 == 0 {
+            tags.push("home".to_string());
+        }
+        if i % 7 == 0 {
+            tags.push(format!("topic/{}", i % 11));
+        }
+
+        let task = db.create_task(CreateTaskRequest {
+            title: format!("任务 {}", i),
+            description: Some(format!("第 {} 个基准测试任务的描述", i)),
+            due_date: (i % 4 != 0).then(|| now + chrono::Duration::hours(i as i64 % 720 - 360)),
+            priority: TaskPriority::from((i % 3) as i32),
+            tags: Some(tags),
+            recurrence: None,
+            // 每十个任务中有一个是上一个任务的子任务
+            parent_id: if i % 10 == 9 { parent_id } else { None },
+            project_id: None,
+            is_all_day: false,
+        })?;
+        parent_id = task.id;
+
+        if i % 4 == 1 {
+            db.update_task(UpdateTaskRequest {
+                id: task.id.unwrap(),
+                title: None,
+                description: Patch::Unchanged,
+                due_date: Patch::Unchanged,
+                priority: None,
+                is_completed: Some(true),
+                tags: Patch::Unchanged,
+                recurrence: Patch::Unchanged,
+                is_all_day: None,
+            })?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,101 @@
+//! 应用锁
+//!
+//! 设置密码后应用启动时处于锁定状态，需要输入密码解锁才能访问任务数据。
+//! 密码只保存 Argon2 哈希；空闲超过设置的时间后自动重新锁定。
+
+use std::time::{Duration, Instant};
+use argon2::Argon2;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::password_hash::rand_core::OsRng;
+use crate::error::AppError;
+
+/// 应用锁状态，保存在 `AppState` 中
+pub struct AppLock {
+    enabled: bool,
+    locked: bool,
+    auto_lock: Option<Duration>,
+    last_activity: Instant,
+}
+
+impl AppLock {
+    /// 创建应用锁，启用时初始为锁定状态
+    pub fn new(enabled: bool, auto_lock_minutes: i32) -> Self {
+        AppLock {
+            enabled,
+            locked: enabled,
+            auto_lock: Self::auto_lock_duration(auto_lock_minutes),
+            last_activity: Instant::now(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 是否处于锁定状态，空闲超时的会在这里转为锁定
+    pub fn is_locked(&mut self) -> bool {
+        if self.enabled && !self.locked {
+            if let Some(auto_lock) = self.auto_lock {
+                if self.last_activity.elapsed() >= auto_lock {
+                    self.locked = true;
+                }
+            }
+        }
+        self.locked
+    }
+
+    /// 记录一次用户操作，重新开始空闲计时
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    /// 立即锁定，未设置密码时无效
+    pub fn lock(&mut self) {
+        if self.enabled {
+            self.locked = true;
+        }
+    }
+
+    pub fn unlock(&mut self) {
+        self.locked = false;
+        self.touch();
+    }
+
+    /// 设置或清除密码后更新启用状态（当前会话保持解锁）
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.locked = false;
+        }
+    }
+
+    pub fn set_auto_lock_minutes(&mut self, auto_lock_minutes: i32) {
+        self.auto_lock = Self::auto_lock_duration(auto_lock_minutes);
+        self.touch();
+    }
+
+    fn auto_lock_duration(minutes: i32) -> Option<Duration> {
+        if minutes > 0 {
+            Some(Duration::from_secs(minutes as u64 * 60))
+        } else {
+            None
+        }
+    }
+}
+
+/// 计算密码的 Argon2 哈希（PHC 字符串格式，包含随机盐）
+pub fn hash_passphrase(passphrase: &str) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map_err(|e| AppError::storage(format!("计算密码哈希失败: {}", e)))?;
+
+    Ok(hash.to_string())
+}
+
+/// 校验密码是否与保存的哈希匹配
+pub fn verify_passphrase(passphrase: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(passphrase.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
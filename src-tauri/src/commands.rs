@@ -1,71 +1,254 @@
+use std::path::Path;
+use std::sync::MutexGuard;
 use tauri::State;
-use tauri::Manager;
-use crate::{AppState, models::{Task, CreateTaskRequest, UpdateTaskRequest, Tag, CreateTagRequest, UpdateTagRequest, AppSettings, UpdateSettingsRequest, UpdateTaskSortOrderRequest}};
+use tauri::{Emitter, Manager};
+use crate::app_lock::{self, AppLock};
+use crate::data_dir;
+use crate::profiles::ProfileStore;
+use crate::database::Database;
+use crate::error::AppError;
+use crate::{AppState, models::{Task, CreateTaskRequest, UpdateTaskRequest, Tag, TagStats, MergeTagsRequest, CreateTagRequest, UpdateTagRequest, AppSettings, UpdateSettingsRequest, UpdateTaskSortOrderRequest, MoveSubtaskRequest, SearchResult, TaskQuery, TaskQueryResult, DueWindow, SmartList, CreateSmartListRequest, UpdateSmartListRequest, Project, CreateProjectRequest, UpdateProjectRequest, MoveTasksToProjectRequest, UndoStatus, TaskHistoryEntry, ConsistencyReport, EncryptionStatus, AppLockStatus, DataDirectoryInfo, Profile, CreateProfileRequest, RenameProfileRequest}};
+
+/// 应用锁定时数据命令返回的错误
+const LOCKED_ERROR: &str = "应用已锁定，请先解锁";
+
+/// 获取数据库，应用锁定或数据库尚未解锁时返回锁定错误
+///
+/// 所有读写任务数据的命令都通过这里获取数据库，同时重新开始空闲自动锁定的计时。
+fn unlocked_db(state: &AppState) -> Result<MutexGuard<'_, Database>, AppError> {
+    let db = state.db.lock().map_err(|e| AppError::storage(format!("数据库锁定失败: {}", e)))?;
+    let mut app_lock = state.app_lock.lock().map_err(|e| AppError::storage(format!("读取应用锁状态失败: {}", e)))?;
+    
+    if !db.is_unlocked() || app_lock.is_locked() {
+        return Err(AppError::locked(LOCKED_ERROR));
+    }
+    
+    app_lock.touch();
+    Ok(db)
+}
 
 #[tauri::command]
 pub fn create_task(
     state: State<AppState>,
     request: CreateTaskRequest,
-) -> Result<Task, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.create_task(request).map_err(|e| format!("创建任务失败: {}", e))
+) -> Result<Task, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("创建任务", |db| db.create_task(request)).map_err(|e| e.context("创建任务失败"))
 }
 
 #[tauri::command]
-pub fn get_all_tasks(state: State<AppState>) -> Result<Vec<Task>, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.get_all_tasks().map_err(|e| format!("获取任务列表失败: {}", e))
+pub fn get_all_tasks(state: State<AppState>) -> Result<Vec<Task>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_all_tasks().map_err(|e| e.context("获取任务列表失败"))
 }
 
 #[tauri::command]
-pub fn get_today_tasks(state: State<AppState>) -> Result<Vec<Task>, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.get_today_tasks().map_err(|e| format!("获取今日任务失败: {}", e))
+pub fn get_today_tasks(state: State<AppState>) -> Result<Vec<Task>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_today_tasks().map_err(|e| e.context("获取今日任务失败"))
 }
 
 #[tauri::command]
-pub fn get_completed_tasks(state: State<AppState>) -> Result<Vec<Task>, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.get_completed_tasks().map_err(|e| format!("获取已完成任务失败: {}", e))
+pub fn get_completed_tasks(state: State<AppState>) -> Result<Vec<Task>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_completed_tasks().map_err(|e| e.context("获取已完成任务失败"))
 }
 
 #[tauri::command]
 pub fn update_task(
     state: State<AppState>,
     request: UpdateTaskRequest,
-) -> Result<Task, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.update_task(request).map_err(|e| format!("更新任务失败: {}", e))
+) -> Result<Task, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("更新任务", |db| db.update_task(request)).map_err(|e| e.context("更新任务失败"))
+}
+
+#[tauri::command]
+pub fn delete_task(state: State<AppState>, id: i64) -> Result<(), AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("删除任务", |db| db.delete_task(id)).map_err(|e| e.context("删除任务失败"))
+}
+
+#[tauri::command]
+pub fn toggle_task_completion(state: State<AppState>, id: i64) -> Result<Task, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("切换任务状态", |db| db.toggle_task_completion(id)).map_err(|e| e.context("切换任务状态失败"))
+}
+
+#[tauri::command]
+pub fn get_task_history(state: State<AppState>, id: i64) -> Result<Vec<TaskHistoryEntry>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_task_history(id).map_err(|e| e.context("获取任务历史失败"))
+}
+
+#[tauri::command]
+pub fn export_tasks_to_json(state: State<AppState>) -> Result<String, AppError> {
+    let db = unlocked_db(&state)?;
+    db.export_tasks_to_json().map_err(|e| e.context("导出任务失败"))
+}
+
+#[tauri::command]
+pub fn import_tasks_from_json(state: State<AppState>, json_data: String) -> Result<usize, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("导入任务", |db| db.import_tasks_from_json(&json_data)).map_err(|e| e.context("导入任务失败"))
+}
+
+#[tauri::command]
+pub fn clear_all_tasks(state: State<AppState>) -> Result<(), AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("清空任务", |db| db.clear_all_tasks()).map_err(|e| e.context("清空任务失败"))
+}
+
+#[tauri::command]
+pub fn query_tasks(
+    state: State<AppState>,
+    query: TaskQuery,
+) -> Result<TaskQueryResult, AppError> {
+    let db = unlocked_db(&state)?;
+    db.query_tasks(&query).map_err(|e| e.context("查询任务失败"))
+}
+
+#[tauri::command]
+pub fn search_tasks(
+    state: State<AppState>,
+    query: String,
+    limit: Option<u32>,
+) -> Result<Vec<SearchResult>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.search_tasks(&query, limit.unwrap_or(50)).map_err(|e| e.context("搜索任务失败"))
+}
+
+// === 项目相关命令 ===
+
+#[tauri::command]
+pub fn create_project(
+    state: State<AppState>,
+    request: CreateProjectRequest,
+) -> Result<Project, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("创建项目", |db| db.create_project(request)).map_err(|e| e.context("创建项目失败"))
+}
+
+#[tauri::command]
+pub fn get_projects(state: State<AppState>, include_archived: Option<bool>) -> Result<Vec<Project>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_projects(include_archived.unwrap_or(false)).map_err(|e| e.context("获取项目列表失败"))
+}
+
+#[tauri::command]
+pub fn update_project(
+    state: State<AppState>,
+    request: UpdateProjectRequest,
+) -> Result<Project, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("更新项目", |db| db.update_project(request)).map_err(|e| e.context("更新项目失败"))
+}
+
+#[tauri::command]
+pub fn delete_project(state: State<AppState>, id: i64) -> Result<(), AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("删除项目", |db| db.delete_project(id)).map_err(|e| e.context("删除项目失败"))
+}
+
+#[tauri::command]
+pub fn get_project_tasks(state: State<AppState>, project_id: Option<i64>) -> Result<Vec<Task>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_project_tasks(project_id).map_err(|e| e.context("获取项目任务失败"))
 }
 
 #[tauri::command]
-pub fn delete_task(state: State<AppState>, id: i64) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.delete_task(id).map_err(|e| format!("删除任务失败: {}", e))
+pub fn move_tasks_to_project(
+    state: State<AppState>,
+    request: MoveTasksToProjectRequest,
+) -> Result<(), AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("移动任务", |db| db.move_tasks_to_project(&request.task_ids, request.project_id)).map_err(|e| e.context("移动任务失败"))
+}
+
+// === 智能列表相关命令 ===
+
+#[tauri::command]
+pub fn create_smart_list(
+    state: State<AppState>,
+    request: CreateSmartListRequest,
+) -> Result<SmartList, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("创建智能列表", |db| db.create_smart_list(request)).map_err(|e| e.context("创建智能列表失败"))
+}
+
+#[tauri::command]
+pub fn get_smart_lists(state: State<AppState>) -> Result<Vec<SmartList>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_smart_lists().map_err(|e| e.context("获取智能列表失败"))
+}
+
+#[tauri::command]
+pub fn update_smart_list(
+    state: State<AppState>,
+    request: UpdateSmartListRequest,
+) -> Result<SmartList, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("更新智能列表", |db| db.update_smart_list(request)).map_err(|e| e.context("更新智能列表失败"))
+}
+
+#[tauri::command]
+pub fn delete_smart_list(state: State<AppState>, id: i64) -> Result<(), AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("删除智能列表", |db| db.delete_smart_list(id)).map_err(|e| e.context("删除智能列表失败"))
+}
+
+#[tauri::command]
+pub fn get_smart_list_tasks(state: State<AppState>, id: i64) -> Result<TaskQueryResult, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_smart_list_tasks(id).map_err(|e| e.context("获取智能列表任务失败"))
+}
+
+// === 回收站相关命令 ===
+
+#[tauri::command]
+pub fn get_trashed_tasks(state: State<AppState>) -> Result<Vec<Task>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_trashed_tasks().map_err(|e| e.context("获取回收站任务失败"))
+}
+
+#[tauri::command]
+pub fn restore_task(state: State<AppState>, id: i64) -> Result<Task, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("恢复任务", |db| db.restore_task(id)).map_err(|e| e.context("恢复任务失败"))
 }
 
 #[tauri::command]
-pub fn toggle_task_completion(state: State<AppState>, id: i64) -> Result<Task, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.toggle_task_completion(id).map_err(|e| format!("切换任务状态失败: {}", e))
+pub fn empty_trash(state: State<AppState>) -> Result<usize, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("清空回收站", |db| db.empty_trash()).map_err(|e| e.context("清空回收站失败"))
 }
 
+// === 子任务相关命令 ===
+
 #[tauri::command]
-pub fn export_tasks_to_json(state: State<AppState>) -> Result<String, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.export_tasks_to_json().map_err(|e| format!("导出任务失败: {}", e))
+pub fn create_subtask(
+    state: State<AppState>,
+    parent_id: i64,
+    request: CreateTaskRequest,
+) -> Result<Task, AppError> {
+    let db = unlocked_db(&state)?;
+    let request = CreateTaskRequest { parent_id: Some(parent_id), ..request };
+    db.record_undo_step("创建子任务", |db| db.create_task(request)).map_err(|e| e.context("创建子任务失败"))
 }
 
 #[tauri::command]
-pub fn import_tasks_from_json(state: State<AppState>, json_data: String) -> Result<usize, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.import_tasks_from_json(&json_data).map_err(|e| format!("导入任务失败: {}", e))
+pub fn get_subtasks(state: State<AppState>, parent_id: i64) -> Result<Vec<Task>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_subtasks(parent_id).map_err(|e| e.context("获取子任务失败"))
 }
 
 #[tauri::command]
-pub fn clear_all_tasks(state: State<AppState>) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.clear_all_tasks().map_err(|e| format!("清空任务失败: {}", e))
+pub fn move_subtask(
+    state: State<AppState>,
+    request: MoveSubtaskRequest,
+) -> Result<Task, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("移动子任务", |db| db.move_subtask(request.id, request.parent_id)).map_err(|e| e.context("移动子任务失败"))
 }
 
 // === 标签相关命令 ===
@@ -74,47 +257,346 @@ pub fn clear_all_tasks(state: State<AppState>) -> Result<(), String> {
 pub fn create_tag(
     state: State<AppState>,
     request: CreateTagRequest,
-) -> Result<Tag, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.create_tag(request).map_err(|e| format!("创建标签失败: {}", e))
+) -> Result<Tag, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("创建标签", |db| db.create_tag(request)).map_err(|e| e.context("创建标签失败"))
 }
 
 #[tauri::command]
-pub fn get_all_tags(state: State<AppState>) -> Result<Vec<Tag>, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.get_all_tags().map_err(|e| format!("获取标签列表失败: {}", e))
+pub fn get_all_tags(state: State<AppState>) -> Result<Vec<Tag>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_all_tags().map_err(|e| e.context("获取标签列表失败"))
 }
 
 #[tauri::command]
 pub fn update_tag(
     state: State<AppState>,
     request: UpdateTagRequest,
-) -> Result<Tag, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.update_tag(request).map_err(|e| format!("更新标签失败: {}", e))
+) -> Result<Tag, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("更新标签", |db| db.update_tag(request)).map_err(|e| e.context("更新标签失败"))
+}
+
+#[tauri::command]
+pub fn delete_tag(state: State<AppState>, id: i64) -> Result<(), AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("删除标签", |db| db.delete_tag(id)).map_err(|e| e.context("删除标签失败"))
+}
+
+#[tauri::command]
+pub fn get_tag_stats(state: State<AppState>) -> Result<Vec<TagStats>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_tag_stats().map_err(|e| e.context("获取标签统计失败"))
+}
+
+#[tauri::command]
+pub fn merge_tags(
+    state: State<AppState>,
+    request: MergeTagsRequest,
+) -> Result<Tag, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("合并标签", |db| db.merge_tags(&request.source_ids, request.target_id)).map_err(|e| e.context("合并标签失败"))
+}
+
+#[tauri::command]
+pub fn delete_unused_tags(state: State<AppState>) -> Result<usize, AppError> {
+    let db = unlocked_db(&state)?;
+    db.record_undo_step("清理未使用的标签", |db| db.delete_unused_tags()).map_err(|e| e.context("清理未使用的标签失败"))
+}
+
+// === 撤销/重做相关命令 ===
+
+#[tauri::command]
+pub fn undo(state: State<AppState>) -> Result<Option<String>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.undo().map_err(|e| e.context("撤销失败"))
+}
+
+#[tauri::command]
+pub fn redo(state: State<AppState>) -> Result<Option<String>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.redo().map_err(|e| e.context("重做失败"))
 }
 
 #[tauri::command]
-pub fn delete_tag(state: State<AppState>, id: i64) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.delete_tag(id).map_err(|e| format!("删除标签失败: {}", e))
+pub fn get_undo_status(state: State<AppState>) -> Result<UndoStatus, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_undo_status().map_err(|e| e.context("获取撤销状态失败"))
+}
+
+// === 数据维护命令 ===
+
+/// 检查数据一致性，`repair` 为 true 时修复发现的问题
+#[tauri::command]
+pub fn check_database_consistency(state: State<AppState>, repair: Option<bool>) -> Result<ConsistencyReport, AppError> {
+    let db = unlocked_db(&state)?;
+    db.check_consistency(repair.unwrap_or(false)).map_err(|e| e.context("检查数据一致性失败"))
+}
+
+// === 加密相关命令 ===
+
+#[tauri::command]
+pub fn get_encryption_status(state: State<AppState>) -> Result<EncryptionStatus, AppError> {
+    let db = state.db.lock().map_err(|e| AppError::storage(format!("数据库锁定失败: {}", e)))?;
+    Ok(db.get_encryption_status())
+}
+
+#[tauri::command]
+pub fn unlock_database(state: State<AppState>, passphrase: String) -> Result<(), AppError> {
+    let mut db = state.db.lock().map_err(|e| AppError::storage(format!("数据库锁定失败: {}", e)))?;
+    db.unlock(&passphrase).map_err(|e| e.context("解锁数据库失败"))?;
+    
+    // 解锁后才能读取应用锁设置，刚输入过密码，本次保持解锁状态
+    let settings = db.get_app_settings().map_err(|e| e.context("获取应用设置失败"))?;
+    let mut app_lock = state.app_lock.lock().map_err(|e| AppError::storage(format!("读取应用锁状态失败: {}", e)))?;
+    app_lock.set_enabled(settings.app_lock_enabled);
+    app_lock.set_auto_lock_minutes(settings.auto_lock_minutes);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn encrypt_database(state: State<AppState>, passphrase: Option<String>) -> Result<EncryptionStatus, AppError> {
+    let mut db = unlocked_db(&state)?;
+    db.encrypt(passphrase).map_err(|e| e.context("加密数据库失败"))?;
+    Ok(db.get_encryption_status())
+}
+
+#[tauri::command]
+pub fn change_database_key(state: State<AppState>, new_passphrase: Option<String>) -> Result<EncryptionStatus, AppError> {
+    let mut db = unlocked_db(&state)?;
+    db.change_key(new_passphrase).map_err(|e| e.context("更换数据库密钥失败"))?;
+    Ok(db.get_encryption_status())
+}
+
+// === 数据目录相关命令 ===
+
+#[tauri::command]
+pub fn get_data_directory(state: State<AppState>) -> Result<DataDirectoryInfo, AppError> {
+    let data_dir = state.data_dir.lock().map_err(|e| AppError::storage(format!("读取数据目录失败: {}", e)))?;
+    Ok(data_dir.info())
+}
+
+#[tauri::command]
+pub fn move_data_directory(
+    state: State<AppState>,
+    target: String,
+    passphrase: Option<String>,
+) -> Result<DataDirectoryInfo, AppError> {
+    let mut db = unlocked_db(&state)?;
+    let mut data_dir = state.data_dir.lock().map_err(|e| AppError::storage(format!("读取数据目录失败: {}", e)))?;
+    let mut profiles = lock_profiles(&state)?;
+    data_dir::move_database(&mut db, &mut data_dir, &mut profiles, Path::new(&target), passphrase.as_deref())
+        .map_err(|e| e.context("移动数据目录失败"))?;
+    Ok(data_dir.info())
+}
+
+// === 配置相关命令 ===
+
+/// 锁定并获取配置列表
+fn lock_profiles(state: &AppState) -> Result<MutexGuard<'_, ProfileStore>, AppError> {
+    state.profiles.lock().map_err(|e| AppError::storage(format!("读取配置列表失败: {}", e)))
+}
+
+/// 检查应用锁，配置相关的命令不读写任务数据
+///
+/// 当前配置的数据库等待输入密码时，仍然可以切换到其他配置。
+fn ensure_app_unlocked(state: &AppState) -> Result<(), AppError> {
+    let mut app_lock = state.app_lock.lock().map_err(|e| AppError::storage(format!("读取应用锁状态失败: {}", e)))?;
+    if app_lock.is_locked() {
+        return Err(AppError::locked(LOCKED_ERROR));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_profiles(state: State<AppState>) -> Result<Vec<Profile>, AppError> {
+    ensure_app_unlocked(&state)?;
+    Ok(lock_profiles(&state)?.list())
+}
+
+#[tauri::command]
+pub fn create_profile(state: State<AppState>, request: CreateProfileRequest) -> Result<Profile, AppError> {
+    ensure_app_unlocked(&state)?;
+    lock_profiles(&state)?.create(request).map_err(|e| e.context("创建配置失败"))
+}
+
+#[tauri::command]
+pub fn rename_profile(
+    app: tauri::AppHandle,
+    state: State<AppState>,
+    request: RenameProfileRequest,
+) -> Result<Profile, AppError> {
+    ensure_app_unlocked(&state)?;
+    let profile = lock_profiles(&state)?.rename(request).map_err(|e| e.context("重命名配置失败"))?;
+    if profile.is_active {
+        crate::refresh_tray(&app, &profile.name);
+    }
+    Ok(profile)
+}
+
+/// 删除其他配置：当前配置需要已解锁，要删除的配置设置了应用锁或使用密码加密时需要提供该配置的密码
+#[tauri::command]
+pub fn delete_profile(state: State<AppState>, id: i64, passphrase: Option<String>) -> Result<(), AppError> {
+    let db = unlocked_db(&state)?;
+    let mut profiles = lock_profiles(&state)?;
+    
+    let profile = profiles.find(id)?;
+    let database_path = profiles.database_path(id);
+    // 从未切换过的配置还没有数据库文件
+    let has_database = std::fs::metadata(&database_path).map(|m| m.len() > 0).unwrap_or(false);
+    if !profile.is_active && has_database {
+        let mut database = Database::open(&database_path, &ProfileStore::key_name(id))
+            .map_err(|e| e.context("打开配置的数据库失败"))?;
+        database.verify_removal(passphrase.as_deref()).map_err(|e| e.context("删除配置失败"))?;
+    }
+    
+    profiles.delete(id).map_err(|e| e.context("删除配置失败"))?;
+    drop(db);
+    Ok(())
+}
+
+/// 切换到其他配置：打开该配置的数据库替换当前数据库，并按新配置的设置重新初始化应用锁
+///
+/// 切换后通过 `profile-changed` 事件通知前端重新加载数据；新配置使用密码加密时需要先解锁。
+#[tauri::command]
+pub fn switch_profile(app: tauri::AppHandle, state: State<AppState>, id: i64) -> Result<Profile, AppError> {
+    ensure_app_unlocked(&state)?;
+    let mut db = state.db.lock().map_err(|e| AppError::storage(format!("数据库锁定失败: {}", e)))?;
+    let mut profiles = lock_profiles(&state)?;
+    
+    let profile = profiles.find(id)?;
+    if profile.is_active {
+        return Ok(profile);
+    }
+    
+    let database = Database::open(&profiles.database_path(id), &ProfileStore::key_name(id))
+        .map_err(|e| e.context("打开配置的数据库失败"))?;
+    
+    // 与启动时一样：设置了应用锁密码时以锁定状态进入，使用密码加密的数据库在解锁后再读取设置
+    let app_lock = if database.is_unlocked() {
+        let settings = database.get_app_settings().map_err(|e| e.context("获取应用设置失败"))?;
+        AppLock::new(settings.app_lock_enabled, settings.auto_lock_minutes)
+    } else {
+        AppLock::new(false, 0)
+    };
+    
+    let profile = profiles.set_active(id).map_err(|e| e.context("切换配置失败"))?;
+    *db = database;
+    *state.app_lock.lock().map_err(|e| AppError::storage(format!("读取应用锁状态失败: {}", e)))? = app_lock;
+    drop(profiles);
+    drop(db);
+    
+    crate::refresh_tray(&app, &profile.name);
+    if let Err(e) = app.emit("profile-changed", &profile) {
+        log::error!("发送切换配置事件失败: {}", e);
+    }
+    Ok(profile)
+}
+
+// === 应用锁相关命令 ===
+
+#[tauri::command]
+pub fn get_app_lock_status(state: State<AppState>) -> Result<AppLockStatus, AppError> {
+    let db = state.db.lock().map_err(|e| AppError::storage(format!("数据库锁定失败: {}", e)))?;
+    let mut app_lock = state.app_lock.lock().map_err(|e| AppError::storage(format!("读取应用锁状态失败: {}", e)))?;
+    
+    // 使用密码加密的数据库解锁前读不到设置，同样视为锁定
+    if !db.is_unlocked() {
+        return Ok(AppLockStatus { is_enabled: app_lock.is_enabled(), is_locked: true, auto_lock_minutes: 0 });
+    }
+    
+    let settings = db.get_app_settings().map_err(|e| e.context("获取应用设置失败"))?;
+    Ok(AppLockStatus {
+        is_enabled: app_lock.is_enabled(),
+        is_locked: app_lock.is_locked(),
+        auto_lock_minutes: settings.auto_lock_minutes,
+    })
+}
+
+#[tauri::command]
+pub fn unlock_app(state: State<AppState>, passphrase: String) -> Result<(), AppError> {
+    let db = state.db.lock().map_err(|e| AppError::storage(format!("数据库锁定失败: {}", e)))?;
+    if !db.is_unlocked() {
+        return Err(AppError::locked("解锁失败: 请先解锁数据库"));
+    }
+    
+    let hash = db.get_app_lock_hash().map_err(|e| e.context("解锁失败"))?;
+    let mut app_lock = state.app_lock.lock().map_err(|e| AppError::storage(format!("读取应用锁状态失败: {}", e)))?;
+    
+    match hash {
+        Some(hash) if !app_lock::verify_passphrase(&passphrase, &hash) => Err(AppError::validation(Some("passphrase"), "解锁失败: 密码错误")),
+        _ => {
+            app_lock.unlock();
+            Ok(())
+        }
+    }
+}
+
+#[tauri::command]
+pub fn lock_app(state: State<AppState>) -> Result<(), AppError> {
+    let mut app_lock = state.app_lock.lock().map_err(|e| AppError::storage(format!("读取应用锁状态失败: {}", e)))?;
+    if !app_lock.is_enabled() {
+        return Err(AppError::conflict("锁定失败: 尚未设置应用锁密码"));
+    }
+    
+    app_lock.lock();
+    Ok(())
+}
+
+/// 设置、修改或清除应用锁密码
+///
+/// 已设置密码时必须提供正确的当前密码；`new_passphrase` 为空表示关闭应用锁。
+#[tauri::command]
+pub fn set_app_lock_passphrase(
+    state: State<AppState>,
+    current_passphrase: Option<String>,
+    new_passphrase: Option<String>,
+) -> Result<(), AppError> {
+    let db = unlocked_db(&state)?;
+    
+    let current_hash = db.get_app_lock_hash().map_err(|e| e.context("设置应用锁密码失败"))?;
+    if let Some(current_hash) = current_hash {
+        let verified = current_passphrase
+            .as_deref()
+            .map(|passphrase| app_lock::verify_passphrase(passphrase, &current_hash))
+            .unwrap_or(false);
+        if !verified {
+            return Err(AppError::validation(Some("current_passphrase"), "设置应用锁密码失败: 当前密码错误"));
+        }
+    }
+    
+    let new_hash = match new_passphrase {
+        Some(passphrase) if passphrase.is_empty() => return Err(AppError::validation(Some("new_passphrase"), "设置应用锁密码失败: 密码不能为空")),
+        Some(passphrase) => Some(app_lock::hash_passphrase(&passphrase).map_err(|e| e.context("设置应用锁密码失败"))?),
+        None => None,
+    };
+    
+    db.set_app_lock_hash(new_hash.as_deref()).map_err(|e| e.context("设置应用锁密码失败"))?;
+    
+    let mut app_lock = state.app_lock.lock().map_err(|e| AppError::storage(format!("读取应用锁状态失败: {}", e)))?;
+    app_lock.set_enabled(new_hash.is_some());
+    Ok(())
 }
 
 // === 设置相关命令 ===
 
 #[tauri::command]
-pub fn get_app_settings(state: State<AppState>) -> Result<AppSettings, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.get_app_settings().map_err(|e| format!("获取应用设置失败: {}", e))
+pub fn get_app_settings(state: State<AppState>) -> Result<AppSettings, AppError> {
+    let db = state.db.lock().map_err(|e| AppError::storage(format!("数据库锁定失败: {}", e)))?;
+    db.get_app_settings().map_err(|e| e.context("获取应用设置失败"))
 }
 
 #[tauri::command]
 pub fn update_app_settings(
     state: State<AppState>,
     request: UpdateSettingsRequest,
-) -> Result<AppSettings, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.update_app_settings(request).map_err(|e| format!("更新应用设置失败: {}", e))
+) -> Result<AppSettings, AppError> {
+    let db = unlocked_db(&state)?;
+    let settings = db.update_app_settings(request).map_err(|e| e.context("更新应用设置失败"))?;
+    
+    let mut app_lock = state.app_lock.lock().map_err(|e| AppError::storage(format!("读取应用锁状态失败: {}", e)))?;
+    app_lock.set_auto_lock_minutes(settings.auto_lock_minutes);
+    Ok(settings)
 }
 
 // === 窗口管理命令 ===
@@ -123,9 +605,9 @@ pub fn update_app_settings(
 pub fn handle_close_request(
     state: State<AppState>,
     window: tauri::Window
-) -> Result<String, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    let settings = db.get_app_settings().map_err(|e| format!("获取设置失败: {}", e))?;
+) -> Result<String, AppError> {
+    let db = state.db.lock().map_err(|e| AppError::storage(format!("数据库锁定失败: {}", e)))?;
+    let settings = db.get_app_settings().map_err(|e| e.context("获取设置失败"))?;
     
     match settings.close_behavior {
         crate::models::CloseBehavior::Exit => {
@@ -146,14 +628,14 @@ pub fn handle_close_request(
 }
 
 #[tauri::command]
-pub fn force_exit_app(window: tauri::Window) -> Result<(), String> {
+pub fn force_exit_app(window: tauri::Window) -> Result<(), AppError> {
     window.app_handle().exit(0);
     Ok(())
 }
 
 #[tauri::command]
-pub fn minimize_to_tray(window: tauri::Window) -> Result<(), String> {
-    window.hide().map_err(|e| format!("隐藏窗口失败: {}", e))?;
+pub fn minimize_to_tray(window: tauri::Window) -> Result<(), AppError> {
+    window.hide().map_err(|e| AppError::io(format!("隐藏窗口失败: {}", e)))?;
     Ok(())
 }
 
@@ -164,7 +646,7 @@ pub fn send_notification(
     app: tauri::AppHandle,
     title: String,
     body: String,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     use tauri_plugin_notification::NotificationExt;
     
     app.notification()
@@ -173,35 +655,41 @@ pub fn send_notification(
         .body(body)
         .icon("icons/icon.ico") // 尝试设置图标
         .show()
-        .map_err(|e| format!("发送通知失败: {}", e))?;
+        .map_err(|e| AppError::io(format!("发送通知失败: {}", e)))?;
     
     Ok(())
 }
 
 #[tauri::command]
-pub fn check_overdue_tasks(state: State<AppState>) -> Result<Vec<Task>, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.get_overdue_tasks().map_err(|e| format!("获取过期任务失败: {}", e))
+pub fn check_overdue_tasks(state: State<AppState>) -> Result<Vec<Task>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_overdue_tasks().map_err(|e| e.context("获取过期任务失败"))
 }
 
 #[tauri::command]
 pub fn get_upcoming_tasks(
     state: State<AppState>,
     minutes_ahead: i32,
-) -> Result<Vec<Task>, String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
-    db.get_upcoming_tasks(minutes_ahead).map_err(|e| format!("获取即将到期任务失败: {}", e))
+) -> Result<Vec<Task>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_upcoming_tasks(minutes_ahead).map_err(|e| e.context("获取即将到期任务失败"))
+}
+
+#[tauri::command]
+pub fn get_tasks_due(state: State<AppState>, window: DueWindow) -> Result<Vec<Task>, AppError> {
+    let db = unlocked_db(&state)?;
+    db.get_tasks_due(window).map_err(|e| e.context("获取到期任务失败"))
 }
 
 #[tauri::command]
 pub fn update_tasks_sort_order(
     state: State<AppState>,
     request: UpdateTaskSortOrderRequest,
-) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
+) -> Result<(), AppError> {
+    let db = unlocked_db(&state)?;
     let task_orders: Vec<(i64, i64)> = request.task_orders
         .into_iter()
         .map(|item| (item.id, item.sort_order))
         .collect();
-    db.update_tasks_sort_order(task_orders).map_err(|e| format!("更新任务排序失败: {}", e))
+    db.record_undo_step("更新任务排序", |db| db.update_tasks_sort_order(task_orders, request.project_id)).map_err(|e| e.context("更新任务排序失败"))
 }
\ No newline at end of file
@@ -0,0 +1,265 @@
+//! 数据目录
+//!
+//! 数据库文件所在的目录按以下顺序确定：
+//! 1. 命令行参数 `--data-dir <目录>`
+//! 2. 环境变量 `MYTODO_DATA_DIR`
+//! 3. 可执行文件旁有 `portable` 标记文件时，使用可执行文件旁的 `data` 目录（便携模式）
+//! 4. 平台数据目录（如 Linux 的 `~/.local/share/MyTodo`）。通过 `move_database` 移动过时使用记录的新目录；
+//!    旧版本保存在 文档/TodoAppData 下的数据在移动之前继续在原位置使用
+//!
+//! 所有配置的数据库文件都保存在同一个数据目录中，见 `profiles` 模块。
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::database::Database;
+use crate::error::AppError;
+use crate::models::{DataDirSource, DataDirectoryInfo};
+use crate::profiles::ProfileStore;
+
+/// 指定数据目录的命令行参数
+pub const DATA_DIR_ARG: &str = "--data-dir";
+/// 指定数据目录的环境变量
+pub const DATA_DIR_ENV: &str = "MYTODO_DATA_DIR";
+/// 默认配置的数据库文件名
+pub const DATABASE_FILE_NAME: &str = "tasks.db";
+
+const APP_DIR_NAME: &str = "MyTodo";
+const LEGACY_DIR_NAME: &str = "TodoAppData";
+const PORTABLE_MARKER: &str = "portable";
+const PORTABLE_DATA_DIR: &str = "data";
+const LOCATION_FILE_NAME: &str = "data-dir";
+
+/// 数据目录及其来源
+#[derive(Debug, Clone)]
+pub struct DataDir {
+    pub path: PathBuf,
+    pub source: DataDirSource,
+}
+
+impl DataDir {
+    /// 是否可以移动
+    ///
+    /// 由命令行参数、环境变量或便携模式指定的目录在下次启动时仍会被使用，移动后数据会“消失”。
+    pub fn is_movable(&self) -> bool {
+        matches!(self.source, DataDirSource::Moved | DataDirSource::Legacy | DataDirSource::Platform)
+    }
+
+    pub fn info(&self) -> DataDirectoryInfo {
+        DataDirectoryInfo {
+            path: self.path.to_string_lossy().to_string(),
+            source: self.source,
+            is_movable: self.is_movable(),
+        }
+    }
+}
+
+/// 按优先级确定数据目录
+pub fn resolve() -> Result<DataDir, AppError> {
+    if let Some(path) = data_dir_arg(std::env::args_os().skip(1)) {
+        return Ok(DataDir { path: absolute(path)?, source: DataDirSource::CommandLine });
+    }
+
+    if let Some(path) = std::env::var_os(DATA_DIR_ENV).filter(|value| !value.is_empty()) {
+        return Ok(DataDir { path: absolute(PathBuf::from(path))?, source: DataDirSource::Environment });
+    }
+
+    let exe_dir = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf));
+    if let Some(exe_dir) = exe_dir {
+        if exe_dir.join(PORTABLE_MARKER).is_file() {
+            return Ok(DataDir { path: exe_dir.join(PORTABLE_DATA_DIR), source: DataDirSource::Portable });
+        }
+    }
+
+    if let Some(path) = saved_location()? {
+        return Ok(DataDir { path, source: DataDirSource::Moved });
+    }
+
+    let platform_dir = dirs::data_dir().map(|dir| dir.join(APP_DIR_NAME));
+
+    // 旧版本把数据保存在文档目录下，平台数据目录还没有数据库时继续使用旧目录
+    let has_platform_database = platform_dir.as_ref().is_some_and(|dir| dir.join(DATABASE_FILE_NAME).exists());
+    if !has_platform_database {
+        if let Some(legacy_dir) = dirs::document_dir().map(|dir| dir.join(LEGACY_DIR_NAME)) {
+            if legacy_dir.join(DATABASE_FILE_NAME).exists() {
+                return Ok(DataDir { path: legacy_dir, source: DataDirSource::Legacy });
+            }
+        }
+    }
+
+    platform_dir
+        .map(|path| DataDir { path, source: DataDirSource::Platform })
+        .ok_or_else(|| AppError::io(format!("无法获取用户数据目录，请使用 {} 参数或 {} 环境变量指定", DATA_DIR_ARG, DATA_DIR_ENV)))
+}
+
+/// 把数据目录中的数据库移动到新目录
+///
+/// 先复制其他配置的数据库和配置列表，再记录新目录并复制、切换当前数据库，最后删除原文件；
+/// 任何一步失败时删除已复制的文件并恢复原来的记录，原数据保持不变。
+pub fn move_database(
+    db: &mut Database,
+    data_dir: &mut DataDir,
+    profiles: &mut ProfileStore,
+    target: &Path,
+    passphrase: Option<&str>,
+) -> Result<(), AppError> {
+    if !data_dir.is_movable() {
+        return Err(AppError::conflict("数据目录由命令行参数、环境变量或便携模式指定，不能移动"));
+    }
+
+    let target = absolute(target.to_path_buf())?;
+    if target == data_dir.path {
+        return Err(AppError::validation(Some("target"), "新目录与当前数据目录相同"));
+    }
+
+    let active_path = db.path().to_path_buf();
+    let active_file_name = active_path.file_name()
+        .ok_or_else(|| AppError::storage("数据库文件路径无效"))?
+        .to_os_string();
+
+    // 未打开的配置数据库（可能还没有创建）和配置列表直接复制
+    let other_files: Vec<String> = profiles.file_names().into_iter()
+        .filter(|name| active_file_name != name.as_str() && data_dir.path.join(name).exists())
+        .collect();
+    for name in &other_files {
+        if target.join(name).exists() {
+            return Err(AppError::conflict(format!("目标目录中已经存在文件: {}", name)));
+        }
+    }
+
+    fs::create_dir_all(&target)?;
+    let mut copied = Vec::new();
+    for name in &other_files {
+        if let Err(e) = fs::copy(data_dir.path.join(name), target.join(name)) {
+            remove_files(&copied);
+            return Err(e.into());
+        }
+        copied.push(target.join(name));
+    }
+
+    let previous_location = saved_location()?;
+    let relocated = write_location(Some(&target))
+        .and_then(|_| db.relocate(&target.join(&active_file_name), passphrase));
+    if let Err(e) = relocated {
+        let _ = write_location(previous_location.as_deref());
+        remove_files(&copied);
+        return Err(e);
+    }
+
+    // 已经切换到新文件，删除原文件失败不影响使用
+    let mut originals = vec![active_path];
+    originals.extend(other_files.iter().map(|name| data_dir.path.join(name)));
+    for path in originals {
+        if let Err(e) = fs::remove_file(&path) {
+            log::warn!("删除原数据文件 {} 失败: {}", path.display(), e);
+        }
+    }
+
+    *data_dir = DataDir { path: target, source: DataDirSource::Moved };
+    profiles.set_dir(&data_dir.path);
+    Ok(())
+}
+
+/// 删除移动失败时已复制的文件
+fn remove_files(paths: &[PathBuf]) {
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// 从命令行参数中读取数据目录，支持 `--data-dir <目录>` 和 `--data-dir=<目录>`
+fn data_dir_arg(args: impl IntoIterator<Item = OsString>) -> Option<PathBuf> {
+    let prefix = format!("{}=", DATA_DIR_ARG);
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == DATA_DIR_ARG {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(value) = arg.to_str().and_then(|arg| arg.strip_prefix(prefix.as_str())) {
+            return Some(PathBuf::from(value));
+        }
+    }
+
+    None
+}
+
+/// 相对路径按当前工作目录转换为绝对路径
+fn absolute(path: PathBuf) -> Result<PathBuf, AppError> {
+    if path.as_os_str().is_empty() {
+        return Err(AppError::validation(Some("target"), "数据目录不能为空"));
+    }
+    if path.is_absolute() {
+        return Ok(path);
+    }
+
+    Ok(std::env::current_dir()?.join(path))
+}
+
+/// 记录移动后数据目录的文件：配置目录/MyTodo/data-dir
+///
+/// 与密钥文件一样放在配置目录，不随数据目录移动。
+fn location_file_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR_NAME).join(LOCATION_FILE_NAME))
+}
+
+/// 读取移动后记录的数据目录
+fn saved_location() -> Result<Option<PathBuf>, AppError> {
+    let Some(location_file) = location_file_path() else {
+        return Ok(None);
+    };
+
+    match fs::read_to_string(location_file) {
+        Ok(content) if !content.trim().is_empty() => Ok(Some(PathBuf::from(content.trim()))),
+        Ok(_) => Ok(None),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 记录数据目录，为空时删除记录
+fn write_location(path: Option<&Path>) -> Result<(), AppError> {
+    let location_file = location_file_path()
+        .ok_or_else(|| AppError::io("无法获取用户配置目录"))?;
+
+    match path {
+        Some(path) => {
+            if let Some(parent_dir) = location_file.parent() {
+                fs::create_dir_all(parent_dir)?;
+            }
+            fs::write(&location_file, path.to_string_lossy().as_bytes())?;
+        }
+        None => {
+            if location_file.exists() {
+                fs::remove_file(&location_file)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Option<PathBuf> {
+        data_dir_arg(args.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn reads_data_dir_argument() {
+        assert_eq!(parse(&["--data-dir", "/srv/todo"]), Some(PathBuf::from("/srv/todo")));
+        assert_eq!(parse(&["--minimized", "--data-dir=/srv/todo"]), Some(PathBuf::from("/srv/todo")));
+        assert_eq!(parse(&["--data-dir"]), None);
+        assert_eq!(parse(&["--data-directory=/srv/todo"]), None);
+        assert_eq!(parse(&[]), None);
+    }
+
+    #[test]
+    fn relative_paths_are_resolved_against_working_directory() {
+        let current_dir = std::env::current_dir().unwrap();
+        assert_eq!(absolute(PathBuf::from("data")).unwrap(), current_dir.join("data"));
+        assert!(absolute(PathBuf::new()).is_err());
+    }
+}
@@ -1,193 +1,483 @@
-use rusqlite::{Connection, Result as SqliteResult, params};
-use chrono::{Utc, TimeZone};
-use std::path::PathBuf;
-use dirs;
-use crate::models::{Task, TaskPriority, CreateTaskRequest, UpdateTaskRequest, Tag, CreateTagRequest, UpdateTagRequest, AppSettings, CloseBehavior, StartupBehavior, UpdateSettingsRequest};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult, params};
+use rusqlite::types::Value;
+use rusqlite::DatabaseName;
+use chrono::{DateTime, Datelike, NaiveDate, Utc, TimeZone};
+use serde::Serialize;
+use crate::recurrence;
+use crate::search;
+use crate::app_lock;
+use crate::encryption::{self, DatabaseKey};
+use crate::migrations;
+use crate::timezone::{self, UserTimeZone};
+use crate::error::AppError;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::models::{Task, TaskPriority, CreateTaskRequest, UpdateTaskRequest, Patch, Tag, TagStats, CreateTagRequest, UpdateTagRequest, AppSettings, CloseBehavior, StartupBehavior, UpdateSettingsRequest, RecurrenceRule, SearchResult, TaskFilter, TaskQuery, TaskQueryResult, TaskSortField, RelativeDueRange, DueWindow, SmartList, CreateSmartListRequest, UpdateSmartListRequest, Project, CreateProjectRequest, UpdateProjectRequest, ExportData, UndoStatus, TaskHistoryEntry, ConsistencyIssue, ConsistencyReport, EncryptionMode, EncryptionStatus, KeyStorage};
+
+/// 参与撤销/重做的表，以及日志中记录的任务定位键（用于撤销后同步搜索索引）
+const JOURNALED_TABLES: &[(&str, &str)] = &[
+    ("tasks", "rowid"),
+    ("task_tags", "task_id"),
+    ("tags", "rowid"),
+    ("projects", "rowid"),
+    ("smart_lists", "rowid"),
+    // 永久删除任务时一起删除，撤销时需要和任务一起恢复
+    ("task_history", "task_id"),
+    ("task_notifications", "task_id"),
+];
+
+/// 内存数据库的路径，数据只保存在连接中，关闭后即丢失
+pub const IN_MEMORY_PATH: &str = ":memory:";
+
+/// 最多保留的撤销步骤数
+const MAX_UNDO_STEPS: i64 = 100;
+
+/// 自动创建的标签使用的颜色
+const DEFAULT_TAG_COLOR: &str = "#3b82f6";
+
+/// 标签与其所有子孙标签（含自身）的对应关系，用于按标签树汇总
+const TAG_TREE_CTE: &str = "tag_tree(ancestor_id, tag_id) AS (
+    SELECT id, id FROM tags
+    UNION ALL
+    SELECT tag_tree.ancestor_id, tags.id FROM tags JOIN tag_tree ON tags.parent_id = tag_tree.tag_id
+)";
+
+/// 数据一致性检查项
+///
+/// 启用外键约束之前写入的数据中可能存在指向已删除记录的引用，`condition` 匹配这些记录，
+/// 修复时执行 `repair`：附属记录直接删除，任务上的引用置空，不删除任务本身。
+struct ConsistencyCheck {
+    kind: &'static str,
+    description: &'static str,
+    table: &'static str,
+    condition: &'static str,
+    repair: &'static str,
+}
+
+const CONSISTENCY_CHECKS: &[ConsistencyCheck] = &[
+    ConsistencyCheck {
+        kind: "orphaned_task_tags",
+        description: "标签关联指向不存在的任务",
+        table: "task_tags",
+        condition: "task_id NOT IN (SELECT id FROM tasks)",
+        repair: "DELETE FROM task_tags WHERE task_id NOT IN (SELECT id FROM tasks)",
+    },
+    ConsistencyCheck {
+        kind: "dangling_task_tags",
+        description: "任务关联了不存在的标签",
+        table: "task_tags",
+        condition: "tag_id NOT IN (SELECT id FROM tags)",
+        repair: "DELETE FROM task_tags WHERE tag_id NOT IN (SELECT id FROM tags)",
+    },
+    ConsistencyCheck {
+        kind: "orphaned_subtasks",
+        description: "子任务的父任务不存在",
+        table: "tasks",
+        condition: "parent_id IS NOT NULL AND parent_id NOT IN (SELECT id FROM tasks)",
+        repair: "UPDATE tasks SET parent_id = NULL WHERE parent_id IS NOT NULL AND parent_id NOT IN (SELECT id FROM tasks)",
+    },
+    ConsistencyCheck {
+        kind: "dangling_project",
+        description: "任务所属的项目不存在",
+        table: "tasks",
+        condition: "project_id IS NOT NULL AND project_id NOT IN (SELECT id FROM projects)",
+        repair: "UPDATE tasks SET project_id = NULL WHERE project_id IS NOT NULL AND project_id NOT IN (SELECT id FROM projects)",
+    },
+    ConsistencyCheck {
+        kind: "dangling_next_occurrence",
+        description: "重复任务的下一次任务不存在",
+        table: "tasks",
+        condition: "next_occurrence_id IS NOT NULL AND next_occurrence_id NOT IN (SELECT id FROM tasks)",
+        repair: "UPDATE tasks SET next_occurrence_id = NULL WHERE next_occurrence_id IS NOT NULL AND next_occurrence_id NOT IN (SELECT id FROM tasks)",
+    },
+    ConsistencyCheck {
+        kind: "orphaned_notifications",
+        description: "提醒记录指向不存在的任务",
+        table: "task_notifications",
+        condition: "task_id NOT IN (SELECT id FROM tasks)",
+        repair: "DELETE FROM task_notifications WHERE task_id NOT IN (SELECT id FROM tasks)",
+    },
+    ConsistencyCheck {
+        kind: "orphaned_history",
+        description: "变更历史指向不存在的任务",
+        table: "task_history",
+        condition: "task_id NOT IN (SELECT id FROM tasks)",
+        repair: "DELETE FROM task_history WHERE task_id NOT IN (SELECT id FROM tasks)",
+    },
+    ConsistencyCheck {
+        kind: "orphaned_search_index",
+        description: "搜索索引包含不存在的任务",
+        table: "tasks_fts",
+        condition: "rowid NOT IN (SELECT id FROM tasks)",
+        repair: "DELETE FROM tasks_fts WHERE rowid NOT IN (SELECT id FROM tasks)",
+    },
+];
 
 pub struct Database {
     connection: Connection,
+    journal_depth: Cell<u32>, // 当前嵌套的撤销步骤层数，只有最外层负责提交
+    path: PathBuf,
+    encryption: EncryptionMode,
+    key_name: String, // 自动生成的密钥在钥匙串或密钥文件中的名称，每个配置各不相同
+    key_storage: Option<KeyStorage>,
+    is_unlocked: bool, // 使用密码加密时，输入密码前数据库不可用
 }
 
 impl Database {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let db_path = Self::get_database_path()?;
-        
+    /// 打开指定路径的数据库，文件不存在时新建
+    ///
+    /// 新建的数据库使用随机密钥加密并把密钥以 `key_name` 为名保存到系统钥匙串；使用密码加密的数据库需要调用 `unlock` 解锁后才能使用。
+    pub fn open(db_path: &Path, key_name: &str) -> Result<Self, AppError> {
         // 确保目录存在
         if let Some(parent_dir) = db_path.parent() {
             std::fs::create_dir_all(parent_dir)?;
         }
         
-        let connection = Connection::open(&db_path)?;
-        let db = Database { connection };
+        let is_new = std::fs::metadata(db_path).map(|m| m.len() == 0).unwrap_or(true);
+        let is_encrypted = encryption::is_encrypted_file(db_path)?;
+        
+        let connection = Self::open_connection(db_path)?;
+        let mut db = Database {
+            connection,
+            journal_depth: Cell::new(0),
+            path: db_path.to_path_buf(),
+            encryption: EncryptionMode::None,
+            key_name: key_name.to_string(),
+            key_storage: None,
+            is_unlocked: false,
+        };
         
-        // 初始化数据库表
-        db.init_tables()?;
+        if is_new {
+            // 新建的数据库默认使用随机密钥加密
+            let key = DatabaseKey::generate();
+            db.key_storage = Some(encryption::store_key(key_name, &key)?);
+            db.encryption = EncryptionMode::StoredKey;
+            db.apply_key(&key)?;
+        } else if is_encrypted {
+            match encryption::load_stored_key(key_name)? {
+                Some((key, storage)) => {
+                    db.key_storage = Some(storage);
+                    db.encryption = EncryptionMode::StoredKey;
+                    db.apply_key(&key)?;
+                }
+                None => {
+                    // 使用用户密码加密，等待解锁
+                    db.encryption = EncryptionMode::Passphrase;
+                    return Ok(db);
+                }
+            }
+        }
+        
+        db.open_tables()?;
         
         Ok(db)
     }
     
-    /// 获取数据库文件路径：%UserProfile%\Documents\TodoAppData\tasks.db
-    fn get_database_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let documents_dir = dirs::document_dir()
-            .ok_or("无法获取用户文档目录")?;
-        
-        let app_data_dir = documents_dir.join("TodoAppData");
-        let db_path = app_data_dir.join("tasks.db");
+    /// 打开指定路径的未加密数据库，路径为 `:memory:` 时打开内存数据库
+    ///
+    /// 不读写系统钥匙串中的密钥，供测试和基准测试等独立运行的工具使用，应用本身始终通过 `open` 打开。
+    pub fn open_unencrypted(path: &Path) -> Result<Self, AppError> {
+        let mut db = Database {
+            connection: Self::open_connection(path)?,
+            journal_depth: Cell::new(0),
+            path: path.to_path_buf(),
+            encryption: EncryptionMode::None,
+            key_name: encryption::DEFAULT_KEY_NAME.to_string(),
+            key_storage: None,
+            is_unlocked: false,
+        };
+        db.open_tables()?;
         
-        Ok(db_path)
+        Ok(db)
     }
     
-    /// 初始化数据库表结构
-    fn init_tables(&self) -> SqliteResult<()> {
-        // 创建任务表
-        self.connection.execute(
-            "CREATE TABLE IF NOT EXISTS tasks (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                title TEXT NOT NULL,
-                description TEXT DEFAULT '',
-                due_date DATETIME,
-                priority INTEGER NOT NULL DEFAULT 1 CHECK(priority IN (0,1,2)),
-                is_completed BOOLEAN NOT NULL DEFAULT 0,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            )",
-            [],
-        )?;
+    /// 打开一个空的内存数据库
+    pub fn open_in_memory() -> Result<Self, AppError> {
+        Self::open_unencrypted(Path::new(IN_MEMORY_PATH))
+    }
+    
+    /// 是否为内存数据库
+    pub fn is_in_memory(&self) -> bool {
+        self.path == Path::new(IN_MEMORY_PATH)
+    }
+    
+    /// 数据库文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// 把数据库文件复制到新位置并改用新文件，原文件由调用方在确认后删除
+    ///
+    /// 使用密码加密的数据库需要再次提供密码才能打开新文件；新文件校验失败时删除它并继续使用原文件。
+    pub fn relocate(&mut self, new_path: &Path, passphrase: Option<&str>) -> Result<(), AppError> {
+        if self.is_in_memory() {
+            return Err(AppError::conflict("内存数据库没有数据文件"));
+        }
+        if !self.is_unlocked {
+            return Err(AppError::locked("数据库尚未解锁"));
+        }
+        if new_path.exists() {
+            return Err(AppError::conflict(format!("目标位置已经存在数据库文件: {}", new_path.display())));
+        }
         
-        // 创建标签表
-        self.connection.execute(
-            "CREATE TABLE IF NOT EXISTS tags (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                color TEXT NOT NULL DEFAULT '#3b82f6',
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            )",
-            [],
-        )?;
+        let key = match self.encryption {
+            EncryptionMode::None => None,
+            EncryptionMode::StoredKey => {
+                let (key, _) = encryption::load_stored_key(&self.key_name)?
+                    .ok_or_else(|| AppError::storage("找不到保存的数据库密钥"))?;
+                Some(key)
+            }
+            EncryptionMode::Passphrase => match passphrase {
+                Some(passphrase) => Some(DatabaseKey::Passphrase(passphrase.to_string())),
+                None => return Err(AppError::validation(Some("passphrase"), "移动使用密码加密的数据库需要输入密码")),
+            },
+        };
         
-        // 创建任务-标签关联表（多对多关系）
-        self.connection.execute(
-            "CREATE TABLE IF NOT EXISTS task_tags (
-                task_id INTEGER NOT NULL,
-                tag_name TEXT NOT NULL,
-                PRIMARY KEY (task_id, tag_name),
-                FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
-                FOREIGN KEY (tag_name) REFERENCES tags(name) ON DELETE CASCADE
-            )",
-            [],
-        )?;
+        if let Some(parent_dir) = new_path.parent() {
+            std::fs::create_dir_all(parent_dir)?;
+        }
         
-        // 创建索引以提高查询性能
-        self.connection.execute(
-            "CREATE INDEX IF NOT EXISTS idx_tasks_priority ON tasks(priority)",
-            [],
-        )?;
+        // 先复制到临时文件并写入磁盘，再改名为正式文件，避免留下不完整的数据库
+        let copying_path = new_path.with_extension("db.moving");
+        std::fs::copy(&self.path, &copying_path)?;
+        let copied = std::fs::File::open(&copying_path).and_then(|file| file.sync_all())
+            .and_then(|_| std::fs::rename(&copying_path, new_path));
+        if let Err(e) = copied {
+            let _ = std::fs::remove_file(&copying_path);
+            return Err(e.into());
+        }
         
-        self.connection.execute(
-            "CREATE INDEX IF NOT EXISTS idx_tasks_due_date ON tasks(due_date)",
-            [],
-        )?;
+        let connection = Self::open_connection(new_path).and_then(|connection| {
+            if let Some(key) = &key {
+                connection.pragma_update(None, "key", key.pragma_value())?;
+            }
+            let check: String = connection
+                .query_row("PRAGMA quick_check", [], |row| row.get(0))
+                .map_err(|_| AppError::validation(Some("passphrase"), "密码错误或数据库文件已损坏"))?;
+            if check != "ok" {
+                return Err(AppError::storage(format!("复制的数据库校验失败: {}", check)));
+            }
+            Ok(connection)
+        });
+        let connection = match connection {
+            Ok(connection) => connection,
+            Err(e) => {
+                let _ = std::fs::remove_file(new_path);
+                return Err(e);
+            }
+        };
         
-        self.connection.execute(
-            "CREATE INDEX IF NOT EXISTS idx_tasks_is_completed ON tasks(is_completed)",
-            [],
-        )?;
+        self.connection = connection;
+        self.path = new_path.to_path_buf();
         
-        self.connection.execute(
-            "CREATE INDEX IF NOT EXISTS idx_task_tags_task_id ON task_tags(task_id)",
-            [],
-        )?;
+        Ok(())
+    }
+    
+    /// 打开数据库连接并启用外键约束
+    ///
+    /// SQLite 的外键约束按连接设置，每次打开连接都需要重新启用，否则 ON DELETE CASCADE 等约束不会生效。
+    fn open_connection(path: &Path) -> Result<Connection, AppError> {
+        let connection = Connection::open(path)?;
+        connection.pragma_update(None, "foreign_keys", true)?;
+        Ok(connection)
+    }
+    
+    /// 数据库解锁后初始化表结构并执行启动任务
+    fn open_tables(&mut self) -> Result<(), AppError> {
+        // 初始化数据库表
+        self.init_tables()?;
         
-        self.connection.execute(
-            "CREATE INDEX IF NOT EXISTS idx_task_tags_tag_name ON task_tags(tag_name)",
-            [],
-        )?;
+        // 清理回收站中超过保留期限的任务
+        self.purge_expired_trash()?;
         
-        // 创建设置表
-        self.connection.execute(
-            "CREATE TABLE IF NOT EXISTS settings (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
-                close_behavior TEXT NOT NULL DEFAULT 'ask' CHECK(close_behavior IN ('exit','minimize','ask')),
-                notifications_enabled BOOLEAN NOT NULL DEFAULT 1,
-                notification_time_before INTEGER NOT NULL DEFAULT 15,
-                startup_behavior TEXT NOT NULL DEFAULT 'normal' CHECK(startup_behavior IN ('normal','minimized')),
-                theme TEXT NOT NULL DEFAULT 'auto'
-            )",
-            [],
-        )?;
+        self.is_unlocked = true;
+        Ok(())
+    }
+    
+    /// 初始化数据库表结构：执行结构迁移，再检查搜索索引并生成撤销/重做触发器
+    fn init_tables(&self) -> Result<(), AppError> {
+        migrations::run(&self.connection, &self.path)?;
+        
+        // 检查全文搜索索引是否完整
+        self.init_search_index()?;
+        
+        // 生成撤销/重做触发器（需要在所有迁移完成后创建）
+        self.init_undo_journal()?;
         
-        // 插入默认设置（如果不存在）
+        Ok(())
+    }
+    
+    /// 生成撤销/重做触发器
+    ///
+    /// 采用 SQLite 官方推荐的撤销/重做实现：触发器在每次插入、更新、删除时
+    /// 把对应的逆操作 SQL 写入 undo_log，一个用户操作对应 undo_steps 中的一段日志。
+    /// 触发器只在 undo_state.recording = 1 时记录，启动清理、迁移等内部操作不会进入历史。
+    fn init_undo_journal(&self) -> SqliteResult<()> {
+        // 上次异常退出时可能停留在记录状态
         self.connection.execute(
-            "INSERT OR IGNORE INTO settings (id, close_behavior, notifications_enabled, notification_time_before, startup_behavior, theme) 
-             VALUES (1, 'ask', 1, 15, 'normal', 'auto')",
+            "INSERT OR REPLACE INTO undo_state (id, recording) VALUES (1, 0)",
             [],
         )?;
         
-        // 数据库迁移：添加sort_order字段
-        self.migrate_add_sort_order()?;
+        // 表结构可能因迁移而变化，每次启动重新生成触发器
+        for &(table, key) in JOURNALED_TABLES {
+            let mut stmt = self.connection.prepare(&format!("PRAGMA table_info({})", table))?;
+            let columns: Vec<String> = stmt
+                .query_map([], |row| row.get::<_, String>(1))?
+                .collect::<SqliteResult<_>>()?;
+            
+            let update_assignments = columns.iter()
+                .map(|column| format!("'\"{column}\"=' || quote(OLD.\"{column}\")"))
+                .collect::<Vec<_>>()
+                .join(" || ',' || ");
+            let insert_columns = columns.iter()
+                .map(|column| format!("\"{}\"", column))
+                .collect::<Vec<_>>()
+                .join(",");
+            let insert_values = columns.iter()
+                .map(|column| format!("quote(OLD.\"{}\")", column))
+                .collect::<Vec<_>>()
+                .join(" || ',' || ");
+            
+            self.connection.execute_batch(&format!(
+                "DROP TRIGGER IF EXISTS undo_{table}_insert;
+                 DROP TRIGGER IF EXISTS undo_{table}_update;
+                 DROP TRIGGER IF EXISTS undo_{table}_delete;
+                 
+                 CREATE TRIGGER undo_{table}_insert AFTER INSERT ON {table}
+                 WHEN (SELECT recording FROM undo_state WHERE id = 1) = 1
+                 BEGIN
+                     INSERT INTO undo_log (table_name, row_key, sql)
+                     VALUES ('{table}', NEW.{key}, 'DELETE FROM {table} WHERE rowid=' || NEW.rowid);
+                 END;
+                 
+                 CREATE TRIGGER undo_{table}_update AFTER UPDATE ON {table}
+                 WHEN (SELECT recording FROM undo_state WHERE id = 1) = 1
+                 BEGIN
+                     INSERT INTO undo_log (table_name, row_key, sql)
+                     VALUES ('{table}', OLD.{key}, 'UPDATE {table} SET ' || {update_assignments} || ' WHERE rowid=' || OLD.rowid);
+                 END;
+                 
+                 CREATE TRIGGER undo_{table}_delete BEFORE DELETE ON {table}
+                 WHEN (SELECT recording FROM undo_state WHERE id = 1) = 1
+                 BEGIN
+                     INSERT INTO undo_log (table_name, row_key, sql)
+                     VALUES ('{table}', OLD.{key}, 'INSERT INTO {table} (rowid,{insert_columns}) VALUES (' || OLD.rowid || ',' || {insert_values} || ')');
+                 END;"
+            ))?;
+        }
         
         Ok(())
     }
     
-    /// 迁移：添加sort_order字段到tasks表
-    fn migrate_add_sort_order(&self) -> SqliteResult<()> {
-        // 检查sort_order字段是否已存在
-        let mut stmt = self.connection.prepare("PRAGMA table_info(tasks)").unwrap();
-        let column_iter = stmt.query_map([], |row| {
-            Ok(row.get::<_, String>(1)?) // column name
-        })?;
+    /// 检查全文搜索索引，与任务表不一致时重建
+    fn init_search_index(&self) -> SqliteResult<()> {
+        let task_count: i64 = self.connection.query_row("SELECT COUNT(*) FROM tasks", [], |row| row.get(0))?;
+        let indexed_count: i64 = self.connection.query_row("SELECT COUNT(*) FROM tasks_fts", [], |row| row.get(0))?;
         
-        let mut has_sort_order = false;
-        for column_result in column_iter {
-            if let Ok(column_name) = column_result {
-                if column_name == "sort_order" {
-                    has_sort_order = true;
-                    break;
-                }
-            }
+        if task_count != indexed_count {
+            self.rebuild_search_index()?;
         }
         
-        // 如果没有sort_order字段，则添加它
-        if !has_sort_order {
-            self.connection.execute(
-                "ALTER TABLE tasks ADD COLUMN sort_order INTEGER DEFAULT 0",
-                []
-            )?;
-            
-            // 为现有任务设置sort_order值（基于created_at排序）
-            self.connection.execute(
-                "UPDATE tasks SET sort_order = (
-                    SELECT COUNT(*) FROM tasks t2 WHERE t2.created_at <= tasks.created_at
-                )",
-                []
-            )?;
+        Ok(())
+    }
+    
+    /// 重建全文搜索索引
+    fn rebuild_search_index(&self) -> SqliteResult<()> {
+        self.connection.execute("DELETE FROM tasks_fts", [])?;
+        
+        let mut stmt = self.connection.prepare("SELECT id FROM tasks")?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, i64>(0))?
+            .collect::<SqliteResult<Vec<i64>>>()?;
+        
+        for id in ids {
+            self.index_task(id)?;
         }
         
         Ok(())
     }
     
+    /// 更新单个任务的搜索索引（标题、描述和标签）
+    fn index_task(&self, task_id: i64) -> SqliteResult<()> {
+        self.connection.execute("DELETE FROM tasks_fts WHERE rowid = ?1", [task_id])?;
+        
+        let (title, description): (String, String) = self.connection.query_row(
+            "SELECT title, COALESCE(description, '') FROM tasks WHERE id = ?1",
+            [task_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        
+        let mut stmt = self.connection.prepare("SELECT tags.name FROM task_tags JOIN tags ON tags.id = task_tags.tag_id WHERE task_tags.task_id = ?1 ORDER BY tags.name")?;
+        let tags = stmt
+            .query_map([task_id], |row| row.get::<_, String>(0))?
+            .collect::<SqliteResult<Vec<String>>>()?;
+        
+        self.connection.execute(
+            "INSERT INTO tasks_fts (rowid, title, description, tags) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                task_id,
+                search::segment(&title),
+                search::segment(&description),
+                search::segment(&tags.join(" "))
+            ],
+        )?;
+        
+        Ok(())
+    }
+    
     /// 创建新任务
-    pub fn create_task(&self, request: CreateTaskRequest) -> Result<Task, Box<dyn std::error::Error>> {
+    pub fn create_task(&self, request: CreateTaskRequest) -> Result<Task, AppError> {
+        request.validate()?;
+        
         let now = Utc::now();
-        let due_date_timestamp = request.due_date.map(|dt| dt.timestamp());
+        // 全天任务只保留日期
+        let due_date = if request.is_all_day {
+            request.due_date.map(|dt| timezone::all_day_due_date(dt.date_naive()))
+        } else {
+            request.due_date
+        };
+        let due_date_timestamp = due_date.map(|dt| dt.timestamp());
         let priority_value: i32 = request.priority.into();
         let description = request.description.clone().unwrap_or_default();
+        let recurrence_json = request.recurrence.as_ref().map(serde_json::to_string).transpose()?;
+        
+        // 子任务的父任务必须存在，且子任务跟随父任务所在的项目
+        let project_id = match request.parent_id {
+            Some(parent_id) => {
+                self.get_task_by_id(parent_id)
+                    .map_err(|_| AppError::NotFound { entity: "task", id: Some(parent_id), message: format!("父任务不存在: {}", parent_id) })?
+                    .project_id
+            }
+            None => request.project_id,
+        };
         
-        // 获取下一个sort_order值
+        if let Some(project_id) = project_id {
+            self.get_project_by_id(project_id)
+                .map_err(|_| AppError::not_found("project", project_id))?;
+        }
+        
+        // 获取下一个sort_order值（每个项目独立排序）
         let next_sort_order: i64 = self.connection
-            .query_row("SELECT COALESCE(MAX(sort_order), 0) + 1 FROM tasks", [], |row| {
+            .query_row("SELECT COALESCE(MAX(sort_order), 0) + 1 FROM tasks WHERE project_id IS ?1", [project_id], |row| {
                 Ok(row.get(0)?)
             })?;
         
         self.connection.execute(
-            "INSERT INTO tasks (title, description, due_date, priority, created_at, sort_order) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO tasks (title, description, due_date, priority, created_at, sort_order, recurrence, parent_id, project_id, all_day) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             params![
                 request.title,
                 description,
                 due_date_timestamp,
                 priority_value,
                 now.timestamp(),
-                next_sort_order
+                next_sort_order,
+                recurrence_json,
+                request.parent_id,
+                project_id,
+                request.is_all_day
             ],
         )?;
         
@@ -199,203 +489,155 @@ impl Database {
             self.set_task_tags(task_id, &tags)?;
         }
         
+        // 新增未完成的子任务后，已完成的父任务需要重新打开
+        if let Some(parent_id) = request.parent_id {
+            self.reopen_ancestors(parent_id)?;
+        }
+        
+        self.index_task(task_id)?;
+        
         Ok(Task {
             id: Some(task_id),
             title: request.title,
             description,
-            due_date: request.due_date,
+            due_date,
             priority: request.priority,
             is_completed: false,
             created_at: now,
             tags,
             sort_order: next_sort_order,
+            recurrence: request.recurrence,
+            parent_id: request.parent_id,
+            subtask_count: 0,
+            completed_subtask_count: 0,
+            project_id,
+            deleted_at: None,
+            is_all_day: request.is_all_day,
         })
     }
     
     /// 获取所有任务
-    pub fn get_all_tasks(&self) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
-        let mut stmt = self.connection.prepare(
-            "SELECT id, title, description, due_date, priority, is_completed, created_at, COALESCE(sort_order, 0) as sort_order
-             FROM tasks 
-             ORDER BY sort_order ASC, created_at DESC"
-        )?;
-        
-        let task_iter = stmt.query_map([], |row| {
-            let due_date_timestamp: Option<i64> = row.get(3)?;
-            let due_date = due_date_timestamp.map(|ts| Utc.timestamp_opt(ts, 0).unwrap());
-            
-            let created_at_timestamp: i64 = row.get(6)?;
-            let created_at = Utc.timestamp_opt(created_at_timestamp, 0).unwrap();
-            
-            Ok(Task {
-                id: Some(row.get(0)?),
-                title: row.get(1)?,
-                description: row.get(2)?,
-                due_date,
-                priority: TaskPriority::from(row.get::<_, i32>(4)?),
-                is_completed: row.get(5)?,
-                created_at,
-                tags: Vec::new(), // 先创建空标签列表
-                sort_order: row.get(7)?,
-            })
-        })?;
-        
-        let mut tasks = Vec::new();
-        for task_result in task_iter {
-            let mut task = task_result?;
-            // 获取任务的标签
-            if let Some(task_id) = task.id {
-                task.tags = self.get_task_tags(task_id).unwrap_or_default();
-            }
-            tasks.push(task);
-        }
-        
-        Ok(tasks)
+    pub fn get_all_tasks(&self) -> Result<Vec<Task>, AppError> {
+        self.load_tasks("deleted_at IS NULL ORDER BY sort_order ASC, created_at DESC", [])
     }
     
     /// 更新任务排序
-    pub fn update_tasks_sort_order(&self, task_orders: Vec<(i64, i64)>) -> Result<(), Box<dyn std::error::Error>> {
-        let mut stmt = self.connection.prepare(
-            "UPDATE tasks SET sort_order = ?2 WHERE id = ?1"
-        )?;
+    ///
+    /// 指定 `project_id` 时只更新属于该项目的任务，其他项目中的任务会被忽略。
+    pub fn update_tasks_sort_order(&self, task_orders: Vec<(i64, i64)>, project_id: Option<i64>) -> Result<(), AppError> {
+        let mut stmt = match project_id {
+            Some(_) => self.connection.prepare(
+                "UPDATE tasks SET sort_order = ?2 WHERE id = ?1 AND project_id = ?3"
+            )?,
+            None => self.connection.prepare(
+                "UPDATE tasks SET sort_order = ?2 WHERE id = ?1"
+            )?,
+        };
         
         for (task_id, sort_order) in task_orders {
-            stmt.execute(params![task_id, sort_order])?;
+            match project_id {
+                Some(project_id) => stmt.execute(params![task_id, sort_order, project_id])?,
+                None => stmt.execute(params![task_id, sort_order])?,
+            };
         }
         
         Ok(())
     }
     
     /// 获取今日任务
-    pub fn get_today_tasks(&self) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
-        let today_start = Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
-        let today_end = Utc::now().date_naive().and_hms_opt(23, 59, 59).unwrap().and_utc();
-        
-        let mut stmt = self.connection.prepare(
-            "SELECT id, title, description, due_date, priority, is_completed, created_at, COALESCE(sort_order, 0) as sort_order
-             FROM tasks 
-             WHERE due_date BETWEEN ?1 AND ?2
-             ORDER BY priority DESC, due_date ASC"
-        )?;
-        
-        let task_iter = stmt.query_map(
-            params![today_start.timestamp(), today_end.timestamp()],
-            |row| {
-                let due_date_timestamp: Option<i64> = row.get(3)?;
-                let due_date = due_date_timestamp.map(|ts| Utc.timestamp_opt(ts, 0).unwrap());
-                
-                let created_at_timestamp: i64 = row.get(6)?;
-                let created_at = Utc.timestamp_opt(created_at_timestamp, 0).unwrap();
-                
-                Ok(Task {
-                    id: Some(row.get(0)?),
-                    title: row.get(1)?,
-                    description: row.get(2)?,
-                    due_date,
-                    priority: TaskPriority::from(row.get::<_, i32>(4)?),
-                    is_completed: row.get(5)?,
-                    created_at,
-                    tags: Vec::new(), // 稍后会填充标签
-                    sort_order: row.get(7)?,
-                })
-            }
-        )?;
-        
-        let mut tasks = Vec::new();
-        for task_result in task_iter {
-            let mut task = task_result?;
-            // 获取任务的标签
-            if let Some(task_id) = task.id {
-                task.tags = self.get_task_tags(task_id).unwrap_or_default();
-            }
-            tasks.push(task);
-        }
-        
-        Ok(tasks)
+    ///
+    /// “今天”按用户时区划分，全天任务按日期匹配。
+    pub fn get_today_tasks(&self) -> Result<Vec<Task>, AppError> {
+        let (condition, values) = relative_due_bounds(RelativeDueRange::Today, &self.user_time_zone()?).condition();
+        
+        self.load_tasks(
+            &format!("deleted_at IS NULL AND {} ORDER BY priority DESC, due_date ASC", condition),
+            rusqlite::params_from_iter(values.iter()),
+        )
     }
     
     /// 获取已完成任务
-    pub fn get_completed_tasks(&self) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
-        let mut stmt = self.connection.prepare(
-            "SELECT id, title, description, due_date, priority, is_completed, created_at, COALESCE(sort_order, 0) as sort_order
-             FROM tasks 
-             WHERE is_completed = 1
-             ORDER BY created_at DESC"
-        )?;
-        
-        let task_iter = stmt.query_map([], |row| {
-            let due_date_timestamp: Option<i64> = row.get(3)?;
-            let due_date = due_date_timestamp.map(|ts| Utc.timestamp_opt(ts, 0).unwrap());
-            
-            let created_at_timestamp: i64 = row.get(6)?;
-            let created_at = Utc.timestamp_opt(created_at_timestamp, 0).unwrap();
-            
-            Ok(Task {
-                id: Some(row.get(0)?),
-                title: row.get(1)?,
-                description: row.get(2)?,
-                due_date,
-                priority: TaskPriority::from(row.get::<_, i32>(4)?),
-                is_completed: row.get(5)?,
-                created_at,
-                tags: Vec::new(), // 稍后会填充标签
-                sort_order: row.get(7)?,
-            })
-        })?;
-        
-        let mut tasks = Vec::new();
-        for task_result in task_iter {
-            let mut task = task_result?;
-            // 获取任务的标签
-            if let Some(task_id) = task.id {
-                task.tags = self.get_task_tags(task_id).unwrap_or_default();
-            }
-            tasks.push(task);
-        }
-        
-        Ok(tasks)
+    pub fn get_completed_tasks(&self) -> Result<Vec<Task>, AppError> {
+        self.load_tasks("is_completed = 1 AND deleted_at IS NULL ORDER BY created_at DESC", [])
     }
     
     /// 更新任务
-    pub fn update_task(&self, request: UpdateTaskRequest) -> Result<Task, Box<dyn std::error::Error>> {
+    pub fn update_task(&self, request: UpdateTaskRequest) -> Result<Task, AppError> {
+        request.validate()?;
+        
         // 首先获取现有任务
         let existing_task = self.get_task_by_id(request.id)?;
         
-        let title = request.title.unwrap_or(existing_task.title);
-        let description = request.description.unwrap_or(existing_task.description);
-        let due_date = request.due_date.or(existing_task.due_date);
+        let title = request.title.unwrap_or_else(|| existing_task.title.clone());
+        let description = request.description.apply(Some(existing_task.description.clone())).unwrap_or_default();
+        let is_all_day = request.is_all_day.unwrap_or(existing_task.is_all_day);
+        let due_date = match request.due_date {
+            // 新的全天截止时间只取日期部分
+            Patch::Set(due_date) if is_all_day => Some(timezone::all_day_due_date(due_date.date_naive())),
+            Patch::Set(due_date) => Some(due_date),
+            Patch::Clear => None,
+            // 只切换全天标记时，按用户时区换算现有的截止时间
+            Patch::Unchanged => match existing_task.due_date {
+                Some(due_date) if is_all_day && !existing_task.is_all_day => {
+                    Some(timezone::all_day_due_date(self.user_time_zone()?.date_of(due_date)))
+                }
+                Some(due_date) if !is_all_day && existing_task.is_all_day => {
+                    Some(self.user_time_zone()?.day_start(due_date.date_naive()))
+                }
+                due_date => due_date,
+            },
+        };
         let priority = request.priority.unwrap_or(existing_task.priority);
         let is_completed = request.is_completed.unwrap_or(existing_task.is_completed);
+        let recurrence = request.recurrence.apply(existing_task.recurrence.clone());
         
         let due_date_timestamp = due_date.map(|dt| dt.timestamp());
+        let recurrence_json = recurrence.as_ref().map(serde_json::to_string).transpose()?;
         let priority_copy = priority; // 创建副本
         let priority_value: i32 = priority_copy.into();
         
         self.connection.execute(
-            "UPDATE tasks SET title = ?1, description = ?2, due_date = ?3, priority = ?4, is_completed = ?5 
-             WHERE id = ?6",
+            "UPDATE tasks SET title = ?1, description = ?2, due_date = ?3, priority = ?4, is_completed = ?5, recurrence = ?6, all_day = ?7 
+             WHERE id = ?8",
             params![
                 title,
                 description,
                 due_date_timestamp,
                 priority_value,
                 is_completed,
+                recurrence_json,
+                is_all_day,
                 request.id
             ],
         )?;
         
-        // 处理标签
-        let tags = if let Some(ref tags) = request.tags {
-            if !tags.is_empty() {
-                self.set_task_tags(request.id, tags)?;
+        // 处理标签：没有提供时保持现有标签，清除或设置为空列表时移除所有标签
+        let tags = match request.tags {
+            Patch::Unchanged => existing_task.tags.clone(),
+            Patch::Clear => {
+                self.set_task_tags(request.id, &[])?;
+                Vec::new()
+            }
+            Patch::Set(tags) => {
+                self.set_task_tags(request.id, &tags)?;
+                tags
             }
-            tags.clone()
-        } else {
-            // 如果没有提供标签信息，保持现有标签
-            self.get_task_tags(request.id).unwrap_or_default()
         };
         
-        Ok(Task {
+        // 记录变更历史
+        let changed_at = Utc::now();
+        self.record_task_change(request.id, "title", &existing_task.title, &title, changed_at)?;
+        self.record_task_change(request.id, "description", &existing_task.description, &description, changed_at)?;
+        self.record_task_change(request.id, "due_date", &existing_task.due_date, &due_date, changed_at)?;
+        self.record_task_change(request.id, "is_all_day", &existing_task.is_all_day, &is_all_day, changed_at)?;
+        self.record_task_change(request.id, "priority", &existing_task.priority, &priority, changed_at)?;
+        self.record_task_change(request.id, "is_completed", &existing_task.is_completed, &is_completed, changed_at)?;
+        self.record_task_change(request.id, "recurrence", &existing_task.recurrence, &recurrence, changed_at)?;
+        self.record_task_change(request.id, "tags", &existing_task.tags, &self.get_task_tags(request.id)?, changed_at)?;
+        
+        self.index_task(request.id)?;
+        
+        let updated_task = Task {
             id: Some(request.id),
             title,
             description,
@@ -405,51 +647,146 @@ impl Database {
             created_at: existing_task.created_at,
             tags,
             sort_order: existing_task.sort_order,
-        })
+            recurrence,
+            parent_id: existing_task.parent_id,
+            subtask_count: existing_task.subtask_count,
+            completed_subtask_count: existing_task.completed_subtask_count,
+            project_id: existing_task.project_id,
+            deleted_at: existing_task.deleted_at,
+            is_all_day,
+        };
+        
+        // 完成状态变化时同步子任务和父任务
+        if is_completed != existing_task.is_completed {
+            self.cascade_completion(request.id, is_completed)?;
+        }
+        
+        // 通过编辑标记完成的重复任务同样生成下一次任务
+        if is_completed && !existing_task.is_completed {
+            if let Some(ref rule) = updated_task.recurrence {
+                self.spawn_next_occurrence(&updated_task, rule)?;
+            }
+        }
+        
+        Ok(updated_task)
     }
     
-    /// 根据ID获取任务
-    pub fn get_task_by_id(&self, id: i64) -> Result<Task, Box<dyn std::error::Error>> {
-        let mut stmt = self.connection.prepare(
-            "SELECT id, title, description, due_date, priority, is_completed, created_at, COALESCE(sort_order, 0) as sort_order
-             FROM tasks WHERE id = ?1"
-        )?;
+    /// 根据ID获取任务，回收站中的任务视为不存在
+    pub fn get_task_by_id(&self, id: i64) -> Result<Task, AppError> {
+        self.load_tasks("id = ?1 AND deleted_at IS NULL", [id])?
+            .pop()
+            .ok_or_else(|| AppError::not_found("task", id))
+    }
+    
+    /// 根据ID获取任务，包括回收站中的任务（恢复任务时使用）
+    fn get_task_including_trashed(&self, id: i64) -> Result<Task, AppError> {
+        self.load_tasks("id = ?1", [id])?
+            .pop()
+            .ok_or_else(|| AppError::not_found("task", id))
+    }
+    
+    /// 删除任务：连同所有子任务一起移入回收站
+    pub fn delete_task(&self, id: i64) -> Result<(), AppError> {
+        // 已经在回收站中的任务不能再次删除
+        self.get_task_by_id(id)?;
+        let now = Utc::now().timestamp();
+        
+        // 同一次删除的任务使用相同的删除时间，恢复时据此一起恢复
+        for task_id in self.get_subtree_ids(id)? {
+            self.connection.execute(
+                "UPDATE tasks SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+                params![now, task_id],
+            )?;
+        }
         
-        let task = stmt.query_row([id], |row| {
-            let due_date_timestamp: Option<i64> = row.get(3)?;
-            let due_date = due_date_timestamp.map(|ts| Utc.timestamp_opt(ts, 0).unwrap());
-            
-            let created_at_timestamp: i64 = row.get(6)?;
-            let created_at = Utc.timestamp_opt(created_at_timestamp, 0).unwrap();
+        Ok(())
+    }
+    
+    /// 永久删除任务及其数据（标签关联、提醒记录、搜索索引）
+    fn purge_task(&self, task_id: i64) -> Result<(), AppError> {
+        self.connection.execute("DELETE FROM task_tags WHERE task_id = ?1", [task_id])?;
+        self.connection.execute("DELETE FROM task_notifications WHERE task_id = ?1", [task_id])?;
+        self.connection.execute("DELETE FROM task_history WHERE task_id = ?1", [task_id])?;
+        self.connection.execute("DELETE FROM tasks_fts WHERE rowid = ?1", [task_id])?;
+        self.connection.execute("DELETE FROM tasks WHERE id = ?1", [task_id])?;
+        Ok(())
+    }
+    
+    // === 回收站相关方法 ===
+    
+    /// 获取回收站中的任务（最近删除的在前）
+    pub fn get_trashed_tasks(&self) -> Result<Vec<Task>, AppError> {
+        self.load_tasks("deleted_at IS NOT NULL ORDER BY deleted_at DESC, sort_order ASC", [])
+    }
+    
+    /// 从回收站恢复任务，同一次删除的子任务一起恢复
+    ///
+    /// 如果父任务仍在回收站中，恢复的任务会成为顶层任务。
+    pub fn restore_task(&self, id: i64) -> Result<Task, AppError> {
+        let task = self.get_task_including_trashed(id)?;
+        let deleted_at = match task.deleted_at {
+            Some(deleted_at) => deleted_at.timestamp(),
+            None => return Ok(task),
+        };
+        
+        for task_id in self.get_subtree_ids(id)? {
+            self.connection.execute(
+                "UPDATE tasks SET deleted_at = NULL WHERE id = ?1 AND deleted_at = ?2",
+                params![task_id, deleted_at],
+            )?;
+        }
+        
+        if let Some(parent_id) = task.parent_id {
+            let parent_deleted: bool = self.connection.query_row(
+                "SELECT deleted_at IS NOT NULL FROM tasks WHERE id = ?1",
+                [parent_id],
+                |row| row.get(0),
+            ).unwrap_or(true);
             
-            Ok(Task {
-                id: Some(row.get(0)?),
-                title: row.get(1)?,
-                description: row.get(2)?,
-                due_date,
-                priority: TaskPriority::from(row.get::<_, i32>(4)?),
-                is_completed: row.get(5)?,
-                created_at,
-                tags: Vec::new(), // 稍后会填充标签
-                sort_order: row.get(7)?,
-            })
-        })?;
+            if parent_deleted {
+                self.connection.execute("UPDATE tasks SET parent_id = NULL WHERE id = ?1", [id])?;
+            } else if !task.is_completed {
+                self.reopen_ancestors(parent_id)?;
+            }
+        }
         
-        // 获取任务的标签
-        let mut task_with_tags = task;
-        task_with_tags.tags = self.get_task_tags(id).unwrap_or_default();
+        self.get_task_by_id(id)
+    }
+    
+    /// 清空回收站，永久删除其中所有任务
+    pub fn empty_trash(&self) -> Result<usize, AppError> {
+        self.purge_trashed_before(None)
+    }
+    
+    /// 永久删除超过保留期限的回收站任务
+    fn purge_expired_trash(&self) -> Result<usize, AppError> {
+        let settings = self.get_app_settings()?;
+        if settings.trash_retention_days <= 0 {
+            return Ok(0);
+        }
         
-        Ok(task_with_tags)
+        let cutoff = Utc::now() - chrono::Duration::days(settings.trash_retention_days as i64);
+        self.purge_trashed_before(Some(cutoff.timestamp()))
     }
     
-    /// 删除任务
-    pub fn delete_task(&self, id: i64) -> Result<(), Box<dyn std::error::Error>> {
-        self.connection.execute("DELETE FROM tasks WHERE id = ?1", [id])?;
-        Ok(())
+    /// 永久删除删除时间早于 `cutoff` 的回收站任务（为空时删除全部）
+    fn purge_trashed_before(&self, cutoff: Option<i64>) -> Result<usize, AppError> {
+        let mut stmt = self.connection.prepare(
+            "SELECT id FROM tasks WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR deleted_at < ?1)"
+        )?;
+        let ids = stmt
+            .query_map([cutoff], |row| row.get::<_, i64>(0))?
+            .collect::<SqliteResult<Vec<i64>>>()?;
+        
+        for &task_id in &ids {
+            self.purge_task(task_id)?;
+        }
+        
+        Ok(ids.len())
     }
     
     /// 切换任务完成状态
-    pub fn toggle_task_completion(&self, id: i64) -> Result<Task, Box<dyn std::error::Error>> {
+    pub fn toggle_task_completion(&self, id: i64) -> Result<Task, AppError> {
         let existing_task = self.get_task_by_id(id)?;
         let new_completion_status = !existing_task.is_completed;
         
@@ -457,6 +794,17 @@ impl Database {
             "UPDATE tasks SET is_completed = ?1 WHERE id = ?2",
             params![new_completion_status, id],
         )?;
+        self.record_task_change(id, "is_completed", &existing_task.is_completed, &new_completion_status, Utc::now())?;
+        
+        // 同步子任务和父任务的完成状态
+        self.cascade_completion(id, new_completion_status)?;
+        
+        // 完成重复任务时生成下一次任务
+        if new_completion_status {
+            if let Some(ref rule) = existing_task.recurrence {
+                self.spawn_next_occurrence(&existing_task, rule)?;
+            }
+        }
         
         let mut updated_task = existing_task;
         updated_task.is_completed = new_completion_status;
@@ -469,19 +817,614 @@ impl Database {
         Ok(updated_task)
     }
     
-    /// 导出所有任务数据为 JSON 格式
-    pub fn export_tasks_to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
-        let tasks = self.get_all_tasks()?;
-        let json_data = serde_json::to_string_pretty(&tasks)?;
-        Ok(json_data)
+    /// 为已完成的重复任务生成下一次任务（每个任务只生成一次）
+    fn spawn_next_occurrence(&self, task: &Task, rule: &RecurrenceRule) -> Result<Option<Task>, AppError> {
+        let task_id = match task.id {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        
+        // 已经生成过且下一次任务仍然存在时不再重复生成
+        let next_exists: bool = self.connection.query_row(
+            "SELECT EXISTS(SELECT 1 FROM tasks WHERE id = (SELECT next_occurrence_id FROM tasks WHERE id = ?1))",
+            [task_id],
+            |row| row.get(0),
+        )?;
+        if next_exists {
+            return Ok(None);
+        }
+        
+        // 没有截止时间的重复任务以完成时间为基准
+        let base = task.due_date.unwrap_or_else(Utc::now);
+        let zone = if task.is_all_day { UserTimeZone::utc() } else { self.user_time_zone()? };
+        let next_due_date = match recurrence::next_occurrence(rule, base, &zone) {
+            Some(due_date) => due_date,
+            None => return Ok(None),
+        };
+        
+        let next_task = self.create_task(CreateTaskRequest {
+            title: task.title.clone(),
+            description: Some(task.description.clone()),
+            due_date: Some(next_due_date),
+            priority: task.priority,
+            tags: Some(task.tags.clone()),
+            recurrence: Some(recurrence::advance_rule(rule)),
+            parent_id: task.parent_id,
+            project_id: task.project_id,
+            is_all_day: task.is_all_day,
+        })?;
+        
+        self.connection.execute(
+            "UPDATE tasks SET next_occurrence_id = ?1 WHERE id = ?2",
+            params![next_task.id, task_id],
+        )?;
+        
+        Ok(Some(next_task))
     }
     
-    /// 从 JSON 数据导入任务
-    pub fn import_tasks_from_json(&self, json_data: &str) -> Result<usize, Box<dyn std::error::Error>> {
-        let tasks: Vec<Task> = serde_json::from_str(json_data)?;
-        let mut imported_count = 0;
-        
+    /// 按筛选条件查询任务，支持多字段排序和分页
+    pub fn query_tasks(&self, query: &TaskQuery) -> Result<TaskQueryResult, AppError> {
+        let total = self.count_tasks(&query.filter)?;
+        let (where_clause, mut values) = build_filter_clause(&query.filter, &self.user_time_zone()?);
+        
+        // 排序字段来自枚举，不会拼接用户输入
+        let mut order_by: Vec<String> = query.sort.iter().map(|key| {
+            let direction = if key.descending { "DESC" } else { "ASC" };
+            match key.field {
+                TaskSortField::SortOrder => format!("COALESCE(sort_order, 0) {}", direction),
+                // 没有截止时间的任务始终排在最后
+                TaskSortField::DueDate => format!("due_date IS NULL, due_date {}", direction),
+                TaskSortField::Priority => format!("priority {}", direction),
+                TaskSortField::CreatedAt => format!("created_at {}", direction),
+                TaskSortField::Title => format!("title COLLATE NOCASE {}", direction),
+            }
+        }).collect();
+        if order_by.is_empty() {
+            order_by.push("COALESCE(sort_order, 0) ASC".to_string());
+        }
+        order_by.push("id ASC".to_string());
+        
+        // SQLite 中 LIMIT -1 表示不限制数量
+        values.push(Value::Integer(query.limit.map(i64::from).unwrap_or(-1)));
+        values.push(Value::Integer(query.offset.map(i64::from).unwrap_or(0)));
+        
+        let clause = format!(
+            "{} ORDER BY {} LIMIT ? OFFSET ?",
+            where_clause,
+            order_by.join(", ")
+        );
+        let tasks = self.load_tasks(&clause, rusqlite::params_from_iter(values.iter()))?;
+        
+        Ok(TaskQueryResult { tasks, total })
+    }
+    
+    /// 统计符合筛选条件的任务数量
+    pub fn count_tasks(&self, filter: &TaskFilter) -> Result<i64, AppError> {
+        let (where_clause, values) = build_filter_clause(filter, &self.user_time_zone()?);
+        
+        let count = self.connection.query_row(
+            &format!("SELECT COUNT(*) FROM tasks WHERE {}", where_clause),
+            rusqlite::params_from_iter(values.iter()),
+            |row| row.get(0),
+        )?;
+        
+        Ok(count)
+    }
+    
+    /// 全文搜索任务，按相关度排序，返回带高亮的结果
+    pub fn search_tasks(&self, query: &str, limit: u32) -> Result<Vec<SearchResult>, AppError> {
+        let match_query = match search::build_match_query(query) {
+            Some(match_query) => match_query,
+            None => return Ok(Vec::new()),
+        };
+        
+        // 标题权重最高，其次是标签，最后是描述
+        let sql = format!(
+            "SELECT rowid,
+                    highlight(tasks_fts, 0, '{start}', '{end}'),
+                    snippet(tasks_fts, 1, '{start}', '{end}', '…', 16),
+                    bm25(tasks_fts, 10.0, 1.0, 5.0) as rank
+             FROM tasks_fts
+             WHERE tasks_fts MATCH ?1
+             AND rowid IN (SELECT id FROM tasks WHERE deleted_at IS NULL)
+             ORDER BY rank
+             LIMIT ?2",
+            start = search::MATCH_START,
+            end = search::MATCH_END,
+        );
+        let mut stmt = self.connection.prepare(&sql)?;
+        
+        let hit_iter = stmt.query_map(params![match_query, limit], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, f64>(3)?,
+            ))
+        })?;
+        
+        let mut results = Vec::new();
+        for hit in hit_iter {
+            let (task_id, title_highlight, snippet, rank) = hit?;
+            results.push(SearchResult {
+                task: self.get_task_by_id(task_id)?,
+                title_highlight: search::unsegment(&title_highlight),
+                snippet: search::unsegment(&snippet),
+                rank,
+            });
+        }
+        
+        Ok(results)
+    }
+    
+    // === 项目相关方法 ===
+    
+    /// 创建项目
+    pub fn create_project(&self, request: CreateProjectRequest) -> Result<Project, AppError> {
+        let now = Utc::now();
+        let color = request.color.unwrap_or_else(|| "#3b82f6".to_string());
+        let icon = request.icon.unwrap_or_default();
+        
+        let next_sort_order: i64 = self.connection.query_row(
+            "SELECT COALESCE(MAX(sort_order), 0) + 1 FROM projects",
+            [],
+            |row| row.get(0),
+        )?;
+        
+        self.connection.execute(
+            "INSERT INTO projects (name, color, icon, sort_order, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![request.name, color, icon, next_sort_order, now.timestamp()],
+        )?;
+        
+        Ok(Project {
+            id: Some(self.connection.last_insert_rowid()),
+            name: request.name,
+            color,
+            icon,
+            is_archived: false,
+            sort_order: next_sort_order,
+            created_at: now,
+        })
+    }
+    
+    /// 获取项目列表
+    pub fn get_projects(&self, include_archived: bool) -> Result<Vec<Project>, AppError> {
+        let mut stmt = self.connection.prepare(
+            "SELECT id, name, color, icon, is_archived, sort_order, created_at FROM projects
+             WHERE ?1 OR is_archived = 0
+             ORDER BY sort_order ASC, id ASC"
+        )?;
+        
+        let project_iter = stmt.query_map([include_archived], |row| {
+            let created_at_timestamp: i64 = row.get(6)?;
+            let created_at = Utc.timestamp_opt(created_at_timestamp, 0).unwrap();
+            
+            Ok(Project {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                color: row.get(2)?,
+                icon: row.get(3)?,
+                is_archived: row.get(4)?,
+                sort_order: row.get(5)?,
+                created_at,
+            })
+        })?;
+        
+        let mut projects = Vec::new();
+        for project in project_iter {
+            projects.push(project?);
+        }
+        
+        Ok(projects)
+    }
+    
+    /// 根据ID获取项目
+    pub fn get_project_by_id(&self, id: i64) -> Result<Project, AppError> {
+        let project = self.connection.query_row(
+            "SELECT id, name, color, icon, is_archived, sort_order, created_at FROM projects WHERE id = ?1",
+            [id],
+            |row| {
+                let created_at_timestamp: i64 = row.get(6)?;
+                let created_at = Utc.timestamp_opt(created_at_timestamp, 0).unwrap();
+                
+                Ok(Project {
+                    id: Some(row.get(0)?),
+                    name: row.get(1)?,
+                    color: row.get(2)?,
+                    icon: row.get(3)?,
+                    is_archived: row.get(4)?,
+                    sort_order: row.get(5)?,
+                    created_at,
+                })
+            },
+        ).optional()?.ok_or_else(|| AppError::not_found("project", id))?;
+        
+        Ok(project)
+    }
+    
+    /// 更新项目
+    pub fn update_project(&self, request: UpdateProjectRequest) -> Result<Project, AppError> {
+        let existing = self.get_project_by_id(request.id)?;
+        
+        let name = request.name.unwrap_or(existing.name);
+        let color = request.color.unwrap_or(existing.color);
+        let icon = request.icon.unwrap_or(existing.icon);
+        let is_archived = request.is_archived.unwrap_or(existing.is_archived);
+        let sort_order = request.sort_order.unwrap_or(existing.sort_order);
+        
+        self.connection.execute(
+            "UPDATE projects SET name = ?1, color = ?2, icon = ?3, is_archived = ?4, sort_order = ?5 WHERE id = ?6",
+            params![name, color, icon, is_archived, sort_order, request.id],
+        )?;
+        
+        Ok(Project {
+            id: Some(request.id),
+            name,
+            color,
+            icon,
+            is_archived,
+            sort_order,
+            created_at: existing.created_at,
+        })
+    }
+    
+    /// 删除项目，项目中的任务移回收集箱
+    pub fn delete_project(&self, id: i64) -> Result<(), AppError> {
+        self.connection.execute("UPDATE tasks SET project_id = NULL WHERE project_id = ?1", [id])?;
+        self.connection.execute("DELETE FROM projects WHERE id = ?1", [id])?;
+        Ok(())
+    }
+    
+    /// 获取项目中的任务（project_id 为空时获取收集箱中的任务）
+    pub fn get_project_tasks(&self, project_id: Option<i64>) -> Result<Vec<Task>, AppError> {
+        self.load_tasks(
+            "project_id IS ?1 AND deleted_at IS NULL ORDER BY sort_order ASC, created_at DESC",
+            [project_id],
+        )
+    }
+    
+    /// 将任务移动到其他项目（子任务随父任务一起移动），移动后排在目标项目末尾
+    pub fn move_tasks_to_project(&self, task_ids: &[i64], project_id: Option<i64>) -> Result<(), AppError> {
+        if let Some(project_id) = project_id {
+            self.get_project_by_id(project_id)
+                .map_err(|_| AppError::not_found("project", project_id))?;
+        }
+        
+        // 祖先任务也在移动列表中的任务会随祖先一起移动，保留原来的父子关系
+        let mut moved_with_ancestor = Vec::new();
+        for &task_id in task_ids {
+            moved_with_ancestor.extend(self.get_subtree_ids(task_id)?.into_iter().filter(|&id| id != task_id));
+        }
+        
+        for &task_id in task_ids {
+            if moved_with_ancestor.contains(&task_id) {
+                continue;
+            }
+            
+            // 子任务必须和父任务在同一项目中，移动子任务时先将其提升为顶层任务
+            self.connection.execute(
+                "UPDATE tasks SET parent_id = NULL WHERE id = ?1",
+                [task_id],
+            )?;
+            
+            let next_sort_order: i64 = self.connection.query_row(
+                "SELECT COALESCE(MAX(sort_order), 0) + 1 FROM tasks WHERE project_id IS ?1",
+                [project_id],
+                |row| row.get(0),
+            )?;
+            self.connection.execute(
+                "UPDATE tasks SET sort_order = ?1 WHERE id = ?2",
+                params![next_sort_order, task_id],
+            )?;
+            
+            self.set_subtree_project(task_id, project_id)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 设置任务及其所有子孙任务的项目
+    fn set_subtree_project(&self, id: i64, project_id: Option<i64>) -> Result<(), AppError> {
+        for task_id in self.get_subtree_ids(id)? {
+            self.connection.execute(
+                "UPDATE tasks SET project_id = ?1 WHERE id = ?2",
+                params![project_id, task_id],
+            )?;
+        }
+        Ok(())
+    }
+    
+    // === 智能列表相关方法 ===
+    
+    /// 创建智能列表
+    pub fn create_smart_list(&self, request: CreateSmartListRequest) -> Result<SmartList, AppError> {
+        let now = Utc::now();
+        let icon = request.icon.unwrap_or_default();
+        let query_json = serde_json::to_string(&request.query)?;
+        
+        let next_sort_order: i64 = self.connection.query_row(
+            "SELECT COALESCE(MAX(sort_order), 0) + 1 FROM smart_lists",
+            [],
+            |row| row.get(0),
+        )?;
+        
+        self.connection.execute(
+            "INSERT INTO smart_lists (name, icon, query, sort_order, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![request.name, icon, query_json, next_sort_order, now.timestamp()],
+        )?;
+        
+        let task_count = self.count_tasks(&request.query.filter)?;
+        
+        Ok(SmartList {
+            id: Some(self.connection.last_insert_rowid()),
+            name: request.name,
+            icon,
+            query: request.query,
+            sort_order: next_sort_order,
+            created_at: now,
+            task_count,
+        })
+    }
+    
+    /// 获取所有智能列表（包含实时计算的任务数量）
+    pub fn get_smart_lists(&self) -> Result<Vec<SmartList>, AppError> {
+        let mut stmt = self.connection.prepare(
+            "SELECT id FROM smart_lists ORDER BY sort_order ASC, id ASC"
+        )?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, i64>(0))?
+            .collect::<SqliteResult<Vec<i64>>>()?;
+        
+        let mut lists = Vec::new();
+        for id in ids {
+            lists.push(self.get_smart_list_by_id(id)?);
+        }
+        
+        Ok(lists)
+    }
+    
+    /// 根据ID获取智能列表
+    pub fn get_smart_list_by_id(&self, id: i64) -> Result<SmartList, AppError> {
+        let (name, icon, query_json, sort_order, created_at_timestamp): (String, String, String, i64, i64) = self.connection.query_row(
+            "SELECT name, icon, query, sort_order, created_at FROM smart_lists WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        ).optional()?.ok_or_else(|| AppError::not_found("smart_list", id))?;
+        
+        let query: TaskQuery = serde_json::from_str(&query_json)?;
+        let task_count = self.count_tasks(&query.filter)?;
+        
+        Ok(SmartList {
+            id: Some(id),
+            name,
+            icon,
+            query,
+            sort_order,
+            created_at: Utc.timestamp_opt(created_at_timestamp, 0).unwrap(),
+            task_count,
+        })
+    }
+    
+    /// 更新智能列表
+    pub fn update_smart_list(&self, request: UpdateSmartListRequest) -> Result<SmartList, AppError> {
+        let existing = self.get_smart_list_by_id(request.id)?;
+        
+        let name = request.name.unwrap_or(existing.name);
+        let icon = request.icon.unwrap_or(existing.icon);
+        let query = request.query.unwrap_or(existing.query);
+        let sort_order = request.sort_order.unwrap_or(existing.sort_order);
+        
+        self.connection.execute(
+            "UPDATE smart_lists SET name = ?1, icon = ?2, query = ?3, sort_order = ?4 WHERE id = ?5",
+            params![name, icon, serde_json::to_string(&query)?, sort_order, request.id],
+        )?;
+        
+        self.get_smart_list_by_id(request.id)
+    }
+    
+    /// 删除智能列表
+    pub fn delete_smart_list(&self, id: i64) -> Result<(), AppError> {
+        self.connection.execute("DELETE FROM smart_lists WHERE id = ?1", [id])?;
+        Ok(())
+    }
+    
+    /// 获取智能列表中的任务
+    pub fn get_smart_list_tasks(&self, id: i64) -> Result<TaskQueryResult, AppError> {
+        let smart_list = self.get_smart_list_by_id(id)?;
+        self.query_tasks(&smart_list.query)
+    }
+    
+    // === 子任务相关方法 ===
+    
+    /// 获取直接子任务列表
+    pub fn get_subtasks(&self, parent_id: i64) -> Result<Vec<Task>, AppError> {
+        self.load_tasks(
+            "parent_id = ?1 AND deleted_at IS NULL ORDER BY sort_order ASC, created_at ASC",
+            [parent_id],
+        )
+    }
+    
+    /// 移动任务到新的父任务下（parent_id 为空时移动到顶层）
+    pub fn move_subtask(&self, id: i64, parent_id: Option<i64>) -> Result<Task, AppError> {
+        let task = self.get_task_by_id(id)?;
+        
+        if let Some(new_parent_id) = parent_id {
+            self.get_task_by_id(new_parent_id)
+                .map_err(|_| AppError::NotFound { entity: "task", id: Some(new_parent_id), message: format!("父任务不存在: {}", new_parent_id) })?;
+            
+            // 不能移动到自身或自己的子孙任务下，否则会形成环
+            if self.get_subtree_ids(id)?.contains(&new_parent_id) {
+                return Err(AppError::validation(Some("parent_id"), "不能将任务移动到自身或其子任务下"));
+            }
+        }
+        
+        self.connection.execute(
+            "UPDATE tasks SET parent_id = ?1 WHERE id = ?2",
+            params![parent_id, id],
+        )?;
+        
+        // 子任务跟随新父任务所在的项目
+        if let Some(new_parent_id) = parent_id {
+            let project_id = self.get_task_by_id(new_parent_id)?.project_id;
+            self.set_subtree_project(id, project_id)?;
+        }
+        
+        // 未完成的任务移入已完成的父任务时，父任务重新打开
+        if let Some(new_parent_id) = parent_id {
+            if !task.is_completed {
+                self.reopen_ancestors(new_parent_id)?;
+            }
+        }
+        
+        self.get_task_by_id(id)
+    }
+    
+    /// 获取任务及其所有子孙任务的ID
+    fn get_subtree_ids(&self, id: i64) -> Result<Vec<i64>, AppError> {
+        let mut stmt = self.connection.prepare(
+            "WITH RECURSIVE subtree(id) AS (
+                SELECT ?1
+                UNION
+                SELECT tasks.id FROM tasks JOIN subtree ON tasks.parent_id = subtree.id
+             )
+             SELECT id FROM subtree"
+        )?;
+        
+        let ids = stmt
+            .query_map([id], |row| row.get::<_, i64>(0))?
+            .collect::<SqliteResult<Vec<i64>>>()?;
+        
+        Ok(ids)
+    }
+    
+    /// 完成状态级联规则：
+    /// - 完成父任务时，所有子孙任务一并完成
+    /// - 重新打开子任务时，所有祖先任务一并重新打开
+    fn cascade_completion(&self, id: i64, is_completed: bool) -> Result<(), AppError> {
+        if is_completed {
+            let changed_at = Utc::now();
+            for task_id in self.get_subtree_ids(id)? {
+                let updated = self.connection.execute(
+                    "UPDATE tasks SET is_completed = 1 WHERE id = ?1 AND is_completed = 0",
+                    [task_id],
+                )?;
+                if updated > 0 {
+                    self.record_task_change(task_id, "is_completed", &false, &true, changed_at)?;
+                }
+            }
+        } else if let Some(parent_id) = self.get_parent_id(id)? {
+            self.reopen_ancestors(parent_id)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 重新打开指定任务及其所有祖先任务
+    fn reopen_ancestors(&self, id: i64) -> Result<(), AppError> {
+        let mut stmt = self.connection.prepare(
+            "WITH RECURSIVE ancestors(id) AS (
+                SELECT ?1
+                UNION
+                SELECT tasks.parent_id FROM tasks JOIN ancestors ON tasks.id = ancestors.id
+                WHERE tasks.parent_id IS NOT NULL
+             )
+             SELECT id FROM tasks WHERE id IN (SELECT id FROM ancestors) AND is_completed = 1"
+        )?;
+        
+        let ids = stmt
+            .query_map([id], |row| row.get::<_, i64>(0))?
+            .collect::<SqliteResult<Vec<i64>>>()?;
+        
+        let changed_at = Utc::now();
+        for task_id in ids {
+            self.connection.execute(
+                "UPDATE tasks SET is_completed = 0 WHERE id = ?1",
+                [task_id],
+            )?;
+            self.record_task_change(task_id, "is_completed", &true, &false, changed_at)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 获取任务的父任务ID
+    fn get_parent_id(&self, id: i64) -> Result<Option<i64>, AppError> {
+        let parent_id = self.connection.query_row(
+            "SELECT parent_id FROM tasks WHERE id = ?1",
+            [id],
+            |row| row.get(0),
+        )?;
+        
+        Ok(parent_id)
+    }
+    
+    /// 导出所有任务数据为 JSON 格式（包含项目）
+    pub fn export_tasks_to_json(&self) -> Result<String, AppError> {
+        let export_data = ExportData {
+            version: 2,
+            projects: self.get_projects(true)?,
+            tasks: self.get_all_tasks()?,
+        };
+        let json_data = serde_json::to_string_pretty(&export_data)?;
+        Ok(json_data)
+    }
+    
+    /// 从 JSON 数据导入任务
+    ///
+    /// 兼容旧版本导出的任务数组格式。同名项目会被合并，不会重复创建。
+    pub fn import_tasks_from_json(&self, json_data: &str) -> Result<usize, AppError> {
+        let export_data = match serde_json::from_str::<ExportData>(json_data) {
+            Ok(export_data) => export_data,
+            Err(_) => ExportData {
+                version: 1,
+                projects: Vec::new(),
+                tasks: serde_json::from_str(json_data)?,
+            },
+        };
+        let tasks = export_data.tasks;
+        let mut imported_count = 0;
+        
+        // 导入项目，记录旧项目ID到新项目ID的映射
+        let existing_projects = self.get_projects(true)?;
+        let mut project_map = std::collections::HashMap::new();
+        for project in export_data.projects {
+            let old_id = match project.id {
+                Some(old_id) => old_id,
+                None => continue,
+            };
+            
+            let new_id = match existing_projects.iter().find(|p| p.name == project.name) {
+                Some(existing) => existing.id,
+                None => {
+                    let created = self.create_project(CreateProjectRequest {
+                        name: project.name,
+                        color: Some(project.color),
+                        icon: Some(project.icon),
+                    })?;
+                    if project.is_archived {
+                        self.connection.execute(
+                            "UPDATE projects SET is_archived = 1 WHERE id = ?1",
+                            [created.id],
+                        )?;
+                    }
+                    created.id
+                }
+            };
+            
+            if let Some(new_id) = new_id {
+                project_map.insert(old_id, new_id);
+            }
+        }
+        
+        // 导入后任务ID会变化，记录旧ID到新ID的映射以恢复子任务层级
+        let mut id_map = std::collections::HashMap::new();
+        let mut parent_links = Vec::new();
+        
         for task in tasks {
+            let old_id = task.id;
+            let old_parent_id = task.parent_id;
+            let is_completed = task.is_completed;
+            
             // 创建导入任务请求
             let import_request = CreateTaskRequest {
                 title: task.title,
@@ -489,173 +1432,951 @@ impl Database {
                 due_date: task.due_date,
                 priority: task.priority,
                 tags: Some(task.tags),
+                recurrence: task.recurrence,
+                parent_id: None,
+                project_id: task.project_id.and_then(|id| project_map.get(&id).copied()),
+                is_all_day: task.is_all_day,
             };
             
             // 导入任务（忽略错误，继续导入其他任务）
-            if self.create_task(import_request).is_ok() {
+            if let Ok(created) = self.create_task(import_request) {
                 imported_count += 1;
+                
+                // 直接写入完成状态，不生成重复任务的下一次任务
+                if is_completed {
+                    self.connection.execute("UPDATE tasks SET is_completed = 1 WHERE id = ?1", [created.id])?;
+                }
+                
+                if let (Some(old_id), Some(new_id)) = (old_id, created.id) {
+                    id_map.insert(old_id, new_id);
+                    if let Some(old_parent_id) = old_parent_id {
+                        parent_links.push((new_id, old_parent_id));
+                    }
+                }
+            }
+        }
+        
+        // 恢复父子关系（父任务未被导入时保持为顶层任务）
+        for (new_id, old_parent_id) in parent_links {
+            if let Some(new_parent_id) = id_map.get(&old_parent_id) {
+                self.connection.execute(
+                    "UPDATE tasks SET parent_id = ?1 WHERE id = ?2",
+                    params![new_parent_id, new_id],
+                )?;
             }
         }
         
         Ok(imported_count)
     }
     
-    /// 清空所有任务数据
-    pub fn clear_all_tasks(&self) -> Result<(), Box<dyn std::error::Error>> {
-        self.connection.execute("DELETE FROM tasks", [])?;
-        self.connection.execute("DELETE FROM task_tags", [])?;
+    /// 清空所有任务数据（移入回收站，可以恢复）
+    pub fn clear_all_tasks(&self) -> Result<(), AppError> {
+        self.connection.execute(
+            "UPDATE tasks SET deleted_at = ?1 WHERE deleted_at IS NULL",
+            [Utc::now().timestamp()],
+        )?;
         Ok(())
     }
     
     // === 标签相关方法 ===
     
-    /// 获取任务的标签列表
-    fn get_task_tags(&self, task_id: i64) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    /// 获取任务的标签列表
+    fn get_task_tags(&self, task_id: i64) -> Result<Vec<String>, AppError> {
+        let mut stmt = self.connection.prepare(
+            "SELECT tags.name FROM task_tags JOIN tags ON tags.id = task_tags.tag_id WHERE task_tags.task_id = ?1 ORDER BY tags.name"
+        )?;
+        
+        let tag_iter = stmt.query_map([task_id], |row| {
+            Ok(row.get::<_, String>(0)?)
+        })?;
+        
+        let mut tags = Vec::new();
+        for tag in tag_iter {
+            tags.push(tag?);
+        }
+        
+        Ok(tags)
+    }
+    
+    /// 按条件查询任务并批量加载标签
+    ///
+    /// `clause` 是 `WHERE` 之后的部分（条件、排序和分页），标签用一次查询加载，
+    /// 避免列表中每个任务单独查询标签。
+    fn load_tasks<P: rusqlite::Params>(&self, clause: &str, params: P) -> Result<Vec<Task>, AppError> {
+        let mut stmt = self.connection.prepare(&format!("SELECT {} FROM tasks WHERE {}", TASK_COLUMNS, clause))?;
+        let mut tasks = stmt
+            .query_map(params, task_from_row)?
+            .collect::<SqliteResult<Vec<Task>>>()?;
+        
+        self.attach_tags(&mut tasks)?;
+        
+        Ok(tasks)
+    }
+    
+    /// 用一次查询填充一组任务的标签
+    fn attach_tags(&self, tasks: &mut [Task]) -> Result<(), AppError> {
+        if tasks.is_empty() {
+            return Ok(());
+        }
+        
+        // 任务ID以 JSON 数组传入，不受 SQLite 参数数量上限的限制
+        let ids: Vec<i64> = tasks.iter().filter_map(|task| task.id).collect();
+        let mut stmt = self.connection.prepare(
+            "SELECT task_tags.task_id, tags.name FROM task_tags JOIN tags ON tags.id = task_tags.tag_id
+             WHERE task_tags.task_id IN (SELECT value FROM json_each(?1))
+             ORDER BY tags.name"
+        )?;
+        let rows = stmt.query_map([serde_json::to_string(&ids)?], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        
+        let mut tags_by_task: HashMap<i64, Vec<String>> = HashMap::new();
+        for row in rows {
+            let (task_id, name) = row?;
+            tags_by_task.entry(task_id).or_default().push(name);
+        }
+        
+        for task in tasks.iter_mut() {
+            if let Some(tags) = task.id.and_then(|id| tags_by_task.remove(&id)) {
+                task.tags = tags;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 获取使用指定标签或其子标签的任务ID
+    fn get_tag_task_ids(&self, tag_id: i64) -> Result<Vec<i64>, AppError> {
+        let mut stmt = self.connection.prepare(&format!(
+            "SELECT DISTINCT task_id FROM task_tags WHERE tag_id IN ({})",
+            tag_subtree_sql("id = ?1")
+        ))?;
+        let task_ids = stmt
+            .query_map([tag_id], |row| row.get::<_, i64>(0))?
+            .collect::<SqliteResult<Vec<i64>>>()?;
+        
+        Ok(task_ids)
+    }
+    
+    /// 按路径获取标签ID（如 `work/clientA/billing`），路径上缺少的标签会自动创建
+    fn ensure_tag_path(&self, path: &str) -> Result<i64, AppError> {
+        let now = Utc::now().timestamp();
+        let mut parent_id: Option<i64> = None;
+        let mut current_path = String::new();
+        
+        for segment in path.split('/') {
+            if !current_path.is_empty() {
+                current_path.push('/');
+            }
+            current_path.push_str(segment);
+            
+            self.connection.execute(
+                "INSERT OR IGNORE INTO tags (name, color, created_at, auto_created, parent_id) VALUES (?1, ?2, ?3, 1, ?4)",
+                params![current_path, DEFAULT_TAG_COLOR, now, parent_id],
+            )?;
+            parent_id = Some(self.connection.query_row(
+                "SELECT id FROM tags WHERE name = ?1",
+                [&current_path],
+                |row| row.get(0),
+            )?);
+        }
+        
+        parent_id.ok_or_else(|| AppError::validation(Some("name"), "标签名称不能为空"))
+    }
+    
+    /// 设置任务的标签，标签可以是 `a/b/c` 形式的路径
+    fn set_task_tags(&self, task_id: i64, tags: &[String]) -> Result<(), AppError> {
+        // 删除现有标签关联
+        self.connection.execute(
+            "DELETE FROM task_tags WHERE task_id = ?1",
+            [task_id]
+        )?;
+        
+        // 添加新的标签关联（标签及上级标签不存在时自动创建）
+        for tag_name in tags {
+            let tag_id = self.ensure_tag_path(tag_name)?;
+            self.connection.execute(
+                "INSERT OR IGNORE INTO task_tags (task_id, tag_id) VALUES (?1, ?2)",
+                params![task_id, tag_id]
+            )?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 创建标签，名称为路径时自动创建缺少的上级标签
+    pub fn create_tag(&self, request: CreateTagRequest) -> Result<Tag, AppError> {
+        request.validate()?;
+        
+        let now = Utc::now();
+        let parent_id = match request.name.rsplit_once('/') {
+            Some((parent_path, _)) => Some(self.ensure_tag_path(parent_path)?),
+            None => None,
+        };
+        
+        self.connection.execute(
+            "INSERT INTO tags (name, color, created_at, parent_id) VALUES (?1, ?2, ?3, ?4)",
+            params![request.name, request.color, now.timestamp(), parent_id]
+        )?;
+        
+        let tag_id = self.connection.last_insert_rowid();
+        
+        Ok(Tag {
+            id: Some(tag_id),
+            name: request.name,
+            color: request.color,
+            created_at: now,
+            parent_id,
+        })
+    }
+    
+    /// 获取所有标签（按路径排序，子标签紧跟在上级标签之后）
+    pub fn get_all_tags(&self) -> Result<Vec<Tag>, AppError> {
+        let mut stmt = self.connection.prepare(
+            "SELECT id, name, color, created_at, parent_id FROM tags ORDER BY name"
+        )?;
+        
+        let tag_iter = stmt.query_map([], tag_from_row)?;
+        
+        let mut tags = Vec::new();
+        for tag in tag_iter {
+            tags.push(tag?);
+        }
+        
+        Ok(tags)
+    }
+    
+    /// 更新标签
+    ///
+    /// 修改名称会同时修改所有子标签的路径；新名称的上级路径不同时移动到新的上级标签下。
+    pub fn update_tag(&self, request: UpdateTagRequest) -> Result<Tag, AppError> {
+        request.validate()?;
+        
+        if request.name.is_none() && request.color.is_none() {
+            return Err(AppError::validation(None, "没有提供要更新的字段"));
+        }
+        
+        let current = self.get_tag_by_id(request.id)?;
+        
+        if let Some(ref color) = request.color {
+            self.connection.execute(
+                "UPDATE tags SET color = ?1 WHERE id = ?2",
+                params![color, request.id],
+            )?;
+        }
+        
+        if let Some(ref name) = request.name {
+            if *name != current.name {
+                let old_tags = self.snapshot_task_tags(&self.get_tag_task_ids(request.id)?)?;
+                self.rename_tag(request.id, &current.name, name)?;
+                
+                // 任务通过标签ID关联，重命名后只需要记录变更并更新搜索索引中的标签名称
+                self.record_tag_changes(old_tags)?;
+            }
+        }
+        
+        // 用户修改过的标签不再视为自动创建，清理未使用标签时保留
+        self.connection.execute("UPDATE tags SET auto_created = 0 WHERE id = ?1", [request.id])?;
+        
+        // 获取更新后的标签
+        self.get_tag_by_id(request.id)
+    }
+    
+    /// 修改标签路径，子标签的路径随之修改
+    fn rename_tag(&self, tag_id: i64, old_name: &str, new_name: &str) -> Result<(), AppError> {
+        if new_name.starts_with(&format!("{}/", old_name)) {
+            return Err(AppError::validation(Some("name"), "不能将标签移动到自身的子标签下"));
+        }
+        
+        let parent_id = match new_name.rsplit_once('/') {
+            Some((parent_path, _)) => Some(self.ensure_tag_path(parent_path)?),
+            None => None,
+        };
+        
+        self.connection.execute(
+            "UPDATE tags SET name = ?1, parent_id = ?2 WHERE id = ?3",
+            params![new_name, parent_id, tag_id],
+        )?;
+        self.connection.execute(
+            "UPDATE tags SET name = ?1 || substr(name, length(?2) + 1)
+             WHERE substr(name, 1, length(?2) + 1) = ?2 || '/'",
+            params![new_name, old_name],
+        )?;
+        
+        Ok(())
+    }
+    
+    /// 根据ID获取标签
+    pub fn get_tag_by_id(&self, id: i64) -> Result<Tag, AppError> {
+        let mut stmt = self.connection.prepare(
+            "SELECT id, name, color, created_at, parent_id FROM tags WHERE id = ?1"
+        )?;
+        
+        let tag = stmt.query_row([id], tag_from_row)
+            .optional()?
+            .ok_or_else(|| AppError::not_found("tag", id))?;
+        
+        Ok(tag)
+    }
+    
+    /// 删除标签，子标签一并删除
+    pub fn delete_tag(&self, tag_id: i64) -> Result<(), AppError> {
+        let old_tags = self.snapshot_task_tags(&self.get_tag_task_ids(tag_id)?)?;
+        
+        // 子标签和任务关联通过外键级联删除
+        self.connection.execute("DELETE FROM tags WHERE id = ?1", [tag_id])?;
+        
+        self.record_tag_changes(old_tags)
+    }
+    
+    /// 记录修改标签之前各任务的标签列表
+    fn snapshot_task_tags(&self, task_ids: &[i64]) -> Result<Vec<(i64, Vec<String>)>, AppError> {
+        task_ids.iter()
+            .map(|&task_id| Ok((task_id, self.get_task_tags(task_id)?)))
+            .collect()
+    }
+    
+    /// 修改标签之后为受影响的任务记录标签变更，并更新搜索索引
+    fn record_tag_changes(&self, old_tags: Vec<(i64, Vec<String>)>) -> Result<(), AppError> {
+        let changed_at = Utc::now();
+        for (task_id, old_tags) in old_tags {
+            self.record_task_change(task_id, "tags", &old_tags, &self.get_task_tags(task_id)?, changed_at)?;
+            self.index_task(task_id)?;
+        }
+        Ok(())
+    }
+    
+    /// 获取每个标签关联的未完成/已完成任务数量（不含回收站中的任务）
+    ///
+    /// 数量包含子标签的任务，同时带有上级标签和子标签的任务只计算一次。
+    pub fn get_tag_stats(&self) -> Result<Vec<TagStats>, AppError> {
+        let mut stmt = self.connection.prepare(&format!(
+            "WITH RECURSIVE {}
+             SELECT tags.id, tags.name, tags.color, tags.created_at, tags.parent_id, tags.auto_created,
+                    COUNT(DISTINCT CASE WHEN tasks.is_completed = 0 THEN tasks.id END),
+                    COUNT(DISTINCT CASE WHEN tasks.is_completed = 1 THEN tasks.id END)
+             FROM tags
+             JOIN tag_tree ON tag_tree.ancestor_id = tags.id
+             LEFT JOIN task_tags ON task_tags.tag_id = tag_tree.tag_id
+             LEFT JOIN tasks ON tasks.id = task_tags.task_id AND tasks.deleted_at IS NULL
+             GROUP BY tags.id
+             ORDER BY tags.name",
+            TAG_TREE_CTE
+        ))?;
+        
+        let stats = stmt.query_map([], |row| {
+            Ok(TagStats {
+                tag: tag_from_row(row)?,
+                auto_created: row.get(5)?,
+                open_count: row.get(6)?,
+                completed_count: row.get(7)?,
+            })
+        })?.collect::<SqliteResult<Vec<_>>>()?;
+        
+        Ok(stats)
+    }
+    
+    /// 将多个标签合并到目标标签
+    ///
+    /// 源标签关联的任务改为关联目标标签（已有目标标签的任务不重复关联），然后删除源标签。
+    /// 源标签的子标签移动到目标标签下，目标标签下已有同名子标签时继续合并。
+    /// 通过 `record_undo_step` 调用时整体在一个事务中完成。
+    pub fn merge_tags(&self, source_ids: &[i64], target_id: i64) -> Result<Tag, AppError> {
+        if source_ids.is_empty() {
+            return Err(AppError::validation(Some("source_ids"), "没有选择要合并的标签"));
+        }
+        if source_ids.contains(&target_id) {
+            return Err(AppError::validation(Some("source_ids"), "不能将标签合并到自身"));
+        }
+        
+        let target = self.get_tag_by_id(target_id)?;
+        let mut affected_task_ids = Vec::new();
+        for &source_id in source_ids {
+            let source = self.get_tag_by_id(source_id)?;
+            if target.name.starts_with(&format!("{}/", source.name)) {
+                return Err(AppError::validation(Some("target_id"), "不能将标签合并到自身的子标签"));
+            }
+            affected_task_ids.extend(self.get_tag_task_ids(source_id)?);
+        }
+        affected_task_ids.sort_unstable();
+        affected_task_ids.dedup();
+        
+        let old_tags = self.snapshot_task_tags(&affected_task_ids)?;
+        
+        for &source_id in source_ids {
+            // 前面的源标签合并时可能已经合并了它的子标签
+            let exists: bool = self.connection.query_row(
+                "SELECT EXISTS(SELECT 1 FROM tags WHERE id = ?1)",
+                [source_id],
+                |row| row.get(0),
+            )?;
+            if exists {
+                self.merge_tag_into(source_id, target_id, &target.name)?;
+            }
+        }
+        
+        self.record_tag_changes(old_tags)?;
+        
+        Ok(target)
+    }
+    
+    /// 把一个标签合并到目标标签
+    fn merge_tag_into(&self, source_id: i64, target_id: i64, target_name: &str) -> Result<(), AppError> {
+        let children: Vec<(i64, String)> = {
+            let mut stmt = self.connection.prepare("SELECT id, name FROM tags WHERE parent_id = ?1")?;
+            let rows = stmt.query_map([source_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<SqliteResult<_>>()?
+        };
+        
+        for (child_id, child_name) in children {
+            let leaf = child_name.rsplit('/').next().unwrap_or(&child_name);
+            let new_name = format!("{}/{}", target_name, leaf);
+            match self.connection.query_row("SELECT id FROM tags WHERE name = ?1", [&new_name], |row| row.get::<_, i64>(0)).optional()? {
+                Some(existing_id) => self.merge_tag_into(child_id, existing_id, &new_name)?,
+                None => self.rename_tag(child_id, &child_name, &new_name)?,
+            }
+        }
+        
+        self.connection.execute(
+            "INSERT OR IGNORE INTO task_tags (task_id, tag_id) SELECT task_id, ?1 FROM task_tags WHERE tag_id = ?2",
+            params![target_id, source_id],
+        )?;
+        // 源标签的关联通过外键级联删除
+        self.connection.execute("DELETE FROM tags WHERE id = ?1", [source_id])?;
+        
+        Ok(())
+    }
+    
+    /// 删除没有任何任务使用的自动创建标签，返回删除的数量
+    ///
+    /// 只清理编辑任务时自动创建的标签，用户手动创建或修改过的标签即使未使用也会保留。
+    /// 子标签仍在使用或需要保留时，上级标签也会保留。
+    /// 回收站中的任务仍然算作在使用标签，恢复后标签不会丢失。
+    pub fn delete_unused_tags(&self) -> Result<usize, AppError> {
+        // 下级标签随上级级联删除，不计入 execute 返回的行数，按删除前后的数量计算
+        let count_tags = || -> Result<usize, AppError> {
+            Ok(self.connection.query_row("SELECT COUNT(*) FROM tags", [], |row| row.get(0))?)
+        };
+        let before = count_tags()?;
+        
+        self.connection.execute(
+            &format!(
+                "WITH RECURSIVE {}
+                 DELETE FROM tags WHERE auto_created = 1 AND id NOT IN (
+                     SELECT tag_tree.ancestor_id FROM tag_tree
+                     JOIN tags kept ON kept.id = tag_tree.tag_id
+                     WHERE kept.auto_created = 0 OR kept.id IN (SELECT tag_id FROM task_tags)
+                 )",
+                TAG_TREE_CTE
+            ),
+            [],
+        )?;
+        
+        Ok(before - count_tags()?)
+    }
+    
+    // === 加密相关方法 ===
+    
+    /// 获取数据库加密状态
+    pub fn get_encryption_status(&self) -> EncryptionStatus {
+        EncryptionStatus {
+            mode: self.encryption,
+            is_unlocked: self.is_unlocked,
+            key_storage: self.key_storage,
+        }
+    }
+    
+    /// 数据库是否已解锁可用
+    pub fn is_unlocked(&self) -> bool {
+        self.is_unlocked
+    }
+    
+    /// 使用用户密码解锁数据库
+    pub fn unlock(&mut self, passphrase: &str) -> Result<(), AppError> {
+        if self.is_unlocked {
+            return Ok(());
+        }
+        
+        // 密钥错误后连接不能再次设置密钥，每次尝试都重新打开
+        self.connection = Self::open_connection(&self.path)?;
+        self.apply_key(&DatabaseKey::Passphrase(passphrase.to_string()))?;
+        self.open_tables()
+    }
+    
+    /// 加密未加密的数据库（原地替换数据库文件）
+    ///
+    /// 提供密码时使用密码加密，否则生成随机密钥并保存到系统钥匙串。
+    pub fn encrypt(&mut self, passphrase: Option<String>) -> Result<(), AppError> {
+        if self.encryption != EncryptionMode::None {
+            return Err(AppError::conflict("数据库已经加密"));
+        }
+        if self.is_in_memory() {
+            return Err(AppError::conflict("内存数据库不能加密"));
+        }
+        
+        let key = Self::new_key(passphrase)?;
+        let encrypted_path = self.path.with_extension("db.encrypting");
+        if encrypted_path.exists() {
+            std::fs::remove_file(&encrypted_path)?;
+        }
+        
+        // 导出到加密的新文件
+        let user_version: i64 = self.connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        self.connection.execute(
+            "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+            params![encrypted_path.to_string_lossy(), key.pragma_value()],
+        )?;
+        let export_result = self.connection
+            .query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))
+            .and_then(|_| self.connection.pragma_update(Some(DatabaseName::Attached("encrypted")), "user_version", user_version));
+        self.connection.execute_batch("DETACH DATABASE encrypted")?;
+        if let Err(e) = export_result {
+            let _ = std::fs::remove_file(&encrypted_path);
+            return Err(e.into());
+        }
+        
+        // 先保存密钥再替换文件，避免文件已加密而密钥丢失
+        let key_storage = match key {
+            DatabaseKey::Generated(_) => Some(encryption::store_key(&self.key_name, &key)?),
+            DatabaseKey::Passphrase(_) => None,
+        };
+        
+        // 关闭原连接后替换文件
+        let old_connection = std::mem::replace(&mut self.connection, Connection::open_in_memory()?);
+        old_connection.close().map_err(|(_, e)| e)?;
+        std::fs::rename(&encrypted_path, &self.path)?;
+        
+        self.connection = Self::open_connection(&self.path)?;
+        self.apply_key(&key)?;
+        self.encryption = if key_storage.is_some() { EncryptionMode::StoredKey } else { EncryptionMode::Passphrase };
+        self.key_storage = key_storage;
+        
+        Ok(())
+    }
+    
+    /// 更换数据库密钥
+    ///
+    /// 提供密码时改用新密码，否则生成新的随机密钥并保存到系统钥匙串。
+    pub fn change_key(&mut self, new_passphrase: Option<String>) -> Result<(), AppError> {
+        if self.encryption == EncryptionMode::None {
+            return Err(AppError::conflict("数据库尚未加密"));
+        }
+        if !self.is_unlocked {
+            return Err(AppError::locked("数据库尚未解锁"));
+        }
+        
+        let key = Self::new_key(new_passphrase)?;
+        let previous_key = encryption::load_stored_key(&self.key_name)?;
+        
+        // 先保存新密钥，重新加密失败时恢复原来保存的密钥
+        let key_storage = match key {
+            DatabaseKey::Generated(_) => Some(encryption::store_key(&self.key_name, &key)?),
+            DatabaseKey::Passphrase(_) => None,
+        };
+        
+        if let Err(e) = self.connection.pragma_update(None, "rekey", key.pragma_value()) {
+            match previous_key {
+                Some((previous_key, _)) => { encryption::store_key(&self.key_name, &previous_key)?; }
+                None => encryption::delete_stored_key(&self.key_name)?,
+            }
+            return Err(e.into());
+        }
+        
+        if key_storage.is_none() {
+            encryption::delete_stored_key(&self.key_name)?;
+        }
+        
+        self.encryption = if key_storage.is_some() { EncryptionMode::StoredKey } else { EncryptionMode::Passphrase };
+        self.key_storage = key_storage;
+        
+        Ok(())
+    }
+    
+    /// 设置连接的密钥并校验是否正确
+    fn apply_key(&self, key: &DatabaseKey) -> Result<(), AppError> {
+        self.connection.pragma_update(None, "key", key.pragma_value())?;
+        
+        // 密钥错误时读取数据库会失败
+        self.connection
+            .query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))
+            .map_err(|_| AppError::validation(Some("passphrase"), "密码错误或数据库文件已损坏"))?;
+        
+        Ok(())
+    }
+    
+    /// 根据用户输入生成新密钥，没有提供密码时生成随机密钥
+    fn new_key(passphrase: Option<String>) -> Result<DatabaseKey, AppError> {
+        match passphrase {
+            Some(passphrase) if passphrase.is_empty() => Err(AppError::validation(Some("passphrase"), "密码不能为空")),
+            Some(passphrase) => Ok(DatabaseKey::Passphrase(passphrase)),
+            None => Ok(DatabaseKey::generate()),
+        }
+    }
+    
+    // === 变更历史相关方法 ===
+    
+    /// 记录任务字段的一次变更，值没有变化时不记录
+    fn record_task_change<T: Serialize>(
+        &self,
+        task_id: i64,
+        field: &str,
+        old_value: &T,
+        new_value: &T,
+        changed_at: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let old_json = serde_json::to_value(old_value)?;
+        let new_json = serde_json::to_value(new_value)?;
+        if old_json == new_json {
+            return Ok(());
+        }
+        
+        // null 表示字段为空，直接存为 NULL
+        let to_text = |value: serde_json::Value| -> Option<String> {
+            if value.is_null() { None } else { Some(value.to_string()) }
+        };
+        
+        self.connection.execute(
+            "INSERT INTO task_history (task_id, field, old_value, new_value, changed_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![task_id, field, to_text(old_json), to_text(new_json), changed_at.timestamp()],
+        )?;
+        
+        Ok(())
+    }
+    
+    /// 获取任务的变更历史（按时间先后排列）
+    pub fn get_task_history(&self, task_id: i64) -> Result<Vec<TaskHistoryEntry>, AppError> {
         let mut stmt = self.connection.prepare(
-            "SELECT tag_name FROM task_tags WHERE task_id = ?1"
+            "SELECT id, task_id, field, old_value, new_value, changed_at FROM task_history
+             WHERE task_id = ?1 ORDER BY changed_at ASC, id ASC"
         )?;
         
-        let tag_iter = stmt.query_map([task_id], |row| {
-            Ok(row.get::<_, String>(0)?)
+        let rows = stmt.query_map([task_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, i64>(5)?,
+            ))
         })?;
         
-        let mut tags = Vec::new();
-        for tag in tag_iter {
-            tags.push(tag?);
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id, task_id, field, old_value, new_value, changed_at) = row?;
+            entries.push(TaskHistoryEntry {
+                id,
+                task_id,
+                field,
+                old_value: old_value.map(|value| serde_json::from_str(&value)).transpose()?,
+                new_value: new_value.map(|value| serde_json::from_str(&value)).transpose()?,
+                changed_at: Utc.timestamp_opt(changed_at, 0).unwrap(),
+            });
         }
         
-        Ok(tags)
+        Ok(entries)
     }
     
-    /// 设置任务的标签
-    fn set_task_tags(&self, task_id: i64, tags: &[String]) -> Result<(), Box<dyn std::error::Error>> {
-        // 删除现有标签关联
-        self.connection.execute(
-            "DELETE FROM task_tags WHERE task_id = ?1",
-            [task_id]
+    // === 数据一致性检查 ===
+    
+    /// 检查数据库中指向已删除记录的引用，`repair` 为 true 时同时修复
+    ///
+    /// 修复在同一个事务中完成，不进入撤销历史。
+    pub fn check_consistency(&self, repair: bool) -> Result<ConsistencyReport, AppError> {
+        let mut issues = Vec::new();
+        for check in CONSISTENCY_CHECKS {
+            let count: i64 = self.connection.query_row(
+                &format!("SELECT COUNT(*) FROM {} WHERE {}", check.table, check.condition),
+                [],
+                |row| row.get(0),
+            )?;
+            if count > 0 {
+                issues.push(ConsistencyIssue {
+                    kind: check.kind.to_string(),
+                    description: check.description.to_string(),
+                    count,
+                });
+            }
+        }
+        
+        // 缺少搜索索引的任务需要重新分词，不能直接用 SQL 修复
+        let mut unindexed_stmt = self.connection.prepare(
+            "SELECT id FROM tasks WHERE id NOT IN (SELECT rowid FROM tasks_fts)"
         )?;
+        let unindexed_task_ids = unindexed_stmt
+            .query_map([], |row| row.get::<_, i64>(0))?
+            .collect::<SqliteResult<Vec<i64>>>()?;
+        if !unindexed_task_ids.is_empty() {
+            issues.push(ConsistencyIssue {
+                kind: "missing_search_index".to_string(),
+                description: "任务缺少搜索索引".to_string(),
+                count: unindexed_task_ids.len() as i64,
+            });
+        }
         
-        // 添加新的标签关联
-        for tag_name in tags {
-            // 确保标签存在（如果不存在则创建）
-            self.connection.execute(
-                "INSERT OR IGNORE INTO tags (name, color) VALUES (?1, ?2)",
-                params![tag_name, "#3b82f6"] // 默认蓝色
-            )?;
-            
-            // 创建任务-标签关联
-            self.connection.execute(
-                "INSERT INTO task_tags (task_id, tag_name) VALUES (?1, ?2)",
-                params![task_id, tag_name]
-            )?;
+        let repaired = repair && !issues.is_empty();
+        if repaired {
+            let transaction = self.connection.unchecked_transaction()?;
+            for check in CONSISTENCY_CHECKS {
+                if issues.iter().any(|issue| issue.kind == check.kind) {
+                    transaction.execute(check.repair, [])?;
+                }
+            }
+            for task_id in unindexed_task_ids {
+                self.index_task(task_id)?;
+            }
+            transaction.commit()?;
         }
         
-        Ok(())
+        Ok(ConsistencyReport { issues, repaired })
     }
     
-    /// 创建标签
-    pub fn create_tag(&self, request: CreateTagRequest) -> Result<Tag, Box<dyn std::error::Error>> {
-        let now = Utc::now();
+    // === 撤销/重做相关方法 ===
+    
+    /// 将一次用户操作记录为一个可撤销的步骤
+    ///
+    /// 操作在同一个事务中执行，失败时整体回滚且不产生历史记录。
+    /// 新的操作会清空重做历史，超出上限的最早步骤会被丢弃。
+    /// 嵌套调用时只有最外层生效。
+    pub fn record_undo_step<T>(
+        &self,
+        description: &str,
+        operation: impl FnOnce(&Self) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        if self.journal_depth.get() > 0 {
+            return operation(self);
+        }
         
-        self.connection.execute(
-            "INSERT INTO tags (name, color, created_at) VALUES (?1, ?2, ?3)",
-            params![request.name, request.color, now.timestamp()]
-        )?;
+        self.journal_depth.set(1);
+        self.connection.execute_batch("SAVEPOINT undo_step")?;
         
-        let tag_id = self.connection.last_insert_rowid();
+        let result = self.set_undo_recording(true)
+            .and_then(|_| self.current_undo_seq())
+            .and_then(|first_seq| {
+                let value = operation(self)?;
+                self.set_undo_recording(false)?;
+                self.finish_undo_step(description, first_seq)?;
+                Ok(value)
+            });
         
-        Ok(Tag {
-            id: Some(tag_id),
-            name: request.name,
-            color: request.color,
-            created_at: now,
+        self.journal_depth.set(0);
+        
+        match result {
+            Ok(value) => {
+                self.connection.execute_batch("RELEASE undo_step")?;
+                Ok(value)
+            }
+            Err(e) => {
+                self.connection.execute_batch("ROLLBACK TO undo_step; RELEASE undo_step")?;
+                Err(e)
+            }
+        }
+    }
+    
+    /// 撤销最近一次操作，返回被撤销操作的描述（没有可撤销的操作时返回 None）
+    pub fn undo(&self) -> Result<Option<String>, AppError> {
+        self.replay_undo_step("undo", "redo")
+    }
+    
+    /// 重做最近一次被撤销的操作，返回被重做操作的描述
+    pub fn redo(&self) -> Result<Option<String>, AppError> {
+        self.replay_undo_step("redo", "undo")
+    }
+    
+    /// 获取撤销/重做状态
+    pub fn get_undo_status(&self) -> Result<UndoStatus, AppError> {
+        let latest_description = |kind: &str| -> SqliteResult<Option<String>> {
+            let mut stmt = self.connection.prepare(
+                "SELECT description FROM undo_steps WHERE kind = ?1 ORDER BY id DESC LIMIT 1"
+            )?;
+            let mut rows = stmt.query_map([kind], |row| row.get::<_, String>(0))?;
+            rows.next().transpose()
+        };
+        
+        let undo_description = latest_description("undo")?;
+        let redo_description = latest_description("redo")?;
+        
+        Ok(UndoStatus {
+            can_undo: undo_description.is_some(),
+            can_redo: redo_description.is_some(),
+            undo_description,
+            redo_description,
         })
     }
     
-    /// 获取所有标签
-    pub fn get_all_tags(&self) -> Result<Vec<Tag>, Box<dyn std::error::Error>> {
-        let mut stmt = self.connection.prepare(
-            "SELECT id, name, color, created_at FROM tags ORDER BY name"
+    /// 清空撤销/重做历史
+    pub fn clear_undo_history(&self) -> Result<(), AppError> {
+        self.connection.execute("DELETE FROM undo_steps", [])?;
+        self.connection.execute("DELETE FROM undo_log", [])?;
+        Ok(())
+    }
+    
+    fn set_undo_recording(&self, recording: bool) -> Result<(), AppError> {
+        self.connection.execute("UPDATE undo_state SET recording = ?1 WHERE id = 1", [recording])?;
+        Ok(())
+    }
+    
+    /// 当前日志的最大序号（AUTOINCREMENT 保证新日志的序号一定更大）
+    fn current_undo_seq(&self) -> Result<i64, AppError> {
+        let seq = self.connection.query_row(
+            "SELECT COALESCE((SELECT seq FROM sqlite_sequence WHERE name = 'undo_log'), 0)",
+            [],
+            |row| row.get(0),
         )?;
+        Ok(seq)
+    }
+    
+    /// 将 first_seq 之后产生的日志登记为一个撤销步骤
+    fn finish_undo_step(&self, description: &str, first_seq: i64) -> Result<(), AppError> {
+        let last_seq = self.current_undo_seq()?;
+        if last_seq <= first_seq {
+            // 没有产生任何变更
+            return Ok(());
+        }
         
-        let tag_iter = stmt.query_map([], |row| {
-            let created_at_timestamp: i64 = row.get(3)?;
-            let created_at = Utc.timestamp_opt(created_at_timestamp, 0).unwrap();
-            
-            Ok(Tag {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                color: row.get(2)?,
-                created_at,
-            })
-        })?;
+        // 新操作使重做历史失效
+        self.delete_undo_steps("SELECT id, first_seq, last_seq FROM undo_steps WHERE kind = 'redo'")?;
         
-        let mut tags = Vec::new();
-        for tag in tag_iter {
-            tags.push(tag?);
-        }
+        self.connection.execute(
+            "INSERT INTO undo_steps (kind, description, first_seq, last_seq, created_at) VALUES ('undo', ?1, ?2, ?3, ?4)",
+            params![description, first_seq + 1, last_seq, Utc::now().timestamp()],
+        )?;
         
-        Ok(tags)
+        // 只保留最近的 MAX_UNDO_STEPS 个步骤
+        self.delete_undo_steps(&format!(
+            "SELECT id, first_seq, last_seq FROM undo_steps WHERE kind = 'undo'
+             ORDER BY id DESC LIMIT -1 OFFSET {}",
+            MAX_UNDO_STEPS
+        ))?;
+        
+        Ok(())
     }
     
-    /// 更新标签
-    pub fn update_tag(&self, request: UpdateTagRequest) -> Result<Tag, Box<dyn std::error::Error>> {
-        // 构建动态更新语句
-        let mut updates = Vec::new();
-        let mut params = Vec::new();
-        
-        if let Some(ref name) = request.name {
-            updates.push("name = ?");
-            params.push(name.as_str());
-        }
-        
-        if let Some(ref color) = request.color {
-            updates.push("color = ?");
-            params.push(color.as_str());
+    /// 删除查询结果中的步骤及其日志，查询需返回 (id, first_seq, last_seq)
+    fn delete_undo_steps(&self, query: &str) -> Result<(), AppError> {
+        let mut stmt = self.connection.prepare(query)?;
+        let steps = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?)))?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        
+        for (id, first_seq, last_seq) in steps {
+            self.connection.execute(
+                "DELETE FROM undo_log WHERE seq BETWEEN ?1 AND ?2",
+                params![first_seq, last_seq],
+            )?;
+            self.connection.execute("DELETE FROM undo_steps WHERE id = ?1", [id])?;
         }
         
-        if updates.is_empty() {
-            return Err("没有提供要更新的字段".into());
-        }
+        Ok(())
+    }
+    
+    /// 回放 `from_kind` 栈顶的步骤，回放过程中产生的逆操作登记到 `to_kind` 栈
+    fn replay_undo_step(&self, from_kind: &str, to_kind: &str) -> Result<Option<String>, AppError> {
+        let step = {
+            let mut stmt = self.connection.prepare(
+                "SELECT id, description, first_seq, last_seq FROM undo_steps
+                 WHERE kind = ?1 ORDER BY id DESC LIMIT 1"
+            )?;
+            let mut rows = stmt.query_map([from_kind], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?))
+            })?;
+            rows.next().transpose()?
+        };
         
-        let sql = format!("UPDATE tags SET {} WHERE id = ?", updates.join(", "));
-        let id_str = request.id.to_string();
-        params.push(&id_str);
+        let (step_id, description, first_seq, last_seq) = match step {
+            Some(step) => step,
+            None => return Ok(None),
+        };
         
-        self.connection.execute(&sql, rusqlite::params_from_iter(params))?;
+        // 逆序执行日志中的逆操作
+        let entries = {
+            let mut stmt = self.connection.prepare(
+                "SELECT table_name, row_key, sql FROM undo_log
+                 WHERE seq BETWEEN ?1 AND ?2 ORDER BY seq DESC"
+            )?;
+            let rows = stmt.query_map(params![first_seq, last_seq], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, String>(2)?))
+            })?;
+            rows.collect::<SqliteResult<Vec<_>>>()?
+        };
         
-        // 获取更新后的标签
-        let mut stmt = self.connection.prepare(
-            "SELECT id, name, color, created_at FROM tags WHERE id = ?1"
-        )?;
+        self.connection.execute_batch("SAVEPOINT undo_replay")?;
+        // 逆操作按日志逆序执行，父子任务的恢复顺序不一定满足外键约束，推迟到提交时检查
+        self.connection.execute_batch("PRAGMA defer_foreign_keys = ON")?;
         
-        let tag = stmt.query_row([request.id], |row| {
-            let created_at_timestamp: i64 = row.get(3)?;
-            let created_at = Utc.timestamp_opt(created_at_timestamp, 0).unwrap();
+        let result = (|| -> Result<(), AppError> {
+            self.set_undo_recording(true)?;
+            let replay_first_seq = self.current_undo_seq()?;
             
-            Ok(Tag {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                color: row.get(2)?,
-                created_at,
-            })
-        })?;
+            for (_, _, sql) in &entries {
+                self.connection.execute_batch(sql)?;
+            }
+            
+            self.set_undo_recording(false)?;
+            let replay_last_seq = self.current_undo_seq()?;
+            
+            self.connection.execute(
+                "DELETE FROM undo_log WHERE seq BETWEEN ?1 AND ?2",
+                params![first_seq, last_seq],
+            )?;
+            self.connection.execute("DELETE FROM undo_steps WHERE id = ?1", [step_id])?;
+            
+            if replay_last_seq > replay_first_seq {
+                self.connection.execute(
+                    "INSERT INTO undo_steps (kind, description, first_seq, last_seq, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![to_kind, description, replay_first_seq + 1, replay_last_seq, Utc::now().timestamp()],
+                )?;
+            }
+            
+            // 同步受影响任务的搜索索引
+            let mut affected_task_ids: Vec<i64> = entries.iter()
+                .filter(|(table, _, _)| table == "tasks" || table == "task_tags")
+                .filter_map(|(_, row_key, _)| *row_key)
+                .collect();
+            // 标签重命名后使用该标签的任务也需要更新索引
+            for (_, row_key, _) in entries.iter().filter(|(table, _, _)| table == "tags") {
+                if let Some(tag_id) = row_key {
+                    affected_task_ids.extend(self.get_tag_task_ids(*tag_id)?);
+                }
+            }
+            affected_task_ids.sort_unstable();
+            affected_task_ids.dedup();
+            
+            for task_id in affected_task_ids {
+                let exists: bool = self.connection.query_row(
+                    "SELECT EXISTS(SELECT 1 FROM tasks WHERE id = ?1)",
+                    [task_id],
+                    |row| row.get(0),
+                )?;
+                if exists {
+                    self.index_task(task_id)?;
+                } else {
+                    self.connection.execute("DELETE FROM tasks_fts WHERE rowid = ?1", [task_id])?;
+                }
+            }
+            
+            Ok(())
+        })();
         
-        Ok(tag)
-    }
-    
-    /// 删除标签
-    pub fn delete_tag(&self, tag_id: i64) -> Result<(), Box<dyn std::error::Error>> {
-        self.connection.execute("DELETE FROM tags WHERE id = ?1", [tag_id])?;
-        Ok(())
+        match result {
+            Ok(()) => {
+                self.connection.execute_batch("RELEASE undo_replay")?;
+                Ok(Some(description))
+            }
+            Err(e) => {
+                self.connection.execute_batch("ROLLBACK TO undo_replay; RELEASE undo_replay")?;
+                Err(e)
+            }
+        }
     }
     
     // === 设置相关方法 ===
     
     /// 获取应用设置
-    pub fn get_app_settings(&self) -> Result<AppSettings, Box<dyn std::error::Error>> {
+    pub fn get_app_settings(&self) -> Result<AppSettings, AppError> {
         let mut stmt = self.connection.prepare(
-            "SELECT close_behavior, notifications_enabled, notification_time_before, startup_behavior, theme 
+            "SELECT close_behavior, notifications_enabled, notification_time_before, startup_behavior, theme, trash_retention_days,
+                    app_lock_hash IS NOT NULL, auto_lock_minutes, timezone
              FROM settings WHERE id = 1"
         )?;
         
@@ -679,6 +2400,10 @@ impl Database {
                 notification_time_before: row.get(2)?,
                 startup_behavior,
                 theme: row.get(4)?,
+                trash_retention_days: row.get(5)?,
+                app_lock_enabled: row.get(6)?,
+                auto_lock_minutes: row.get(7)?,
+                timezone: row.get(8)?,
             })
         });
         
@@ -692,7 +2417,9 @@ impl Database {
     }
     
     /// 更新应用设置
-    pub fn update_app_settings(&self, request: UpdateSettingsRequest) -> Result<AppSettings, Box<dyn std::error::Error>> {
+    pub fn update_app_settings(&self, request: UpdateSettingsRequest) -> Result<AppSettings, AppError> {
+        request.validate()?;
+        
         // 先获取当前设置
         let current = self.get_app_settings()?;
         
@@ -702,6 +2429,9 @@ impl Database {
         let notification_time_before = request.notification_time_before.unwrap_or(current.notification_time_before);
         let startup_behavior = request.startup_behavior.unwrap_or(current.startup_behavior);
         let theme = request.theme.unwrap_or(current.theme);
+        let trash_retention_days = request.trash_retention_days.unwrap_or(current.trash_retention_days);
+        let auto_lock_minutes = request.auto_lock_minutes.unwrap_or(current.auto_lock_minutes);
+        let timezone = request.timezone.apply(current.timezone);
         
         // 转换枚举为字符串
         let close_behavior_str = match close_behavior {
@@ -717,13 +2447,16 @@ impl Database {
         
         // 更新数据库
         self.connection.execute(
-            "UPDATE settings SET close_behavior = ?1, notifications_enabled = ?2, notification_time_before = ?3, startup_behavior = ?4, theme = ?5 WHERE id = 1",
+            "UPDATE settings SET close_behavior = ?1, notifications_enabled = ?2, notification_time_before = ?3, startup_behavior = ?4, theme = ?5, trash_retention_days = ?6, auto_lock_minutes = ?7, timezone = ?8 WHERE id = 1",
             params![
                 close_behavior_str,
                 notifications_enabled,
                 notification_time_before,
                 startup_behavior_str,
-                theme
+                theme,
+                trash_retention_days,
+                auto_lock_minutes,
+                timezone
             ],
         )?;
         
@@ -733,118 +2466,391 @@ impl Database {
             notification_time_before,
             startup_behavior,
             theme,
+            trash_retention_days,
+            app_lock_enabled: current.app_lock_enabled,
+            auto_lock_minutes,
+            timezone,
         })
     }
     
-    /// 获取过期的任务
-    pub fn get_overdue_tasks(&self) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
-        let now = Utc::now().to_rfc3339();
-        let mut stmt = self.connection.prepare(
-            "SELECT id, title, description, due_date, priority, is_completed, created_at, COALESCE(sort_order, 0) as sort_order
-             FROM tasks 
-             WHERE is_completed = 0 AND due_date IS NOT NULL AND due_date < ?1
-             ORDER BY due_date ASC"
+    /// 计算日期边界使用的时区：设置中的时区，未设置时为系统时区
+    pub fn user_time_zone(&self) -> Result<UserTimeZone, AppError> {
+        let settings = self.get_app_settings()?;
+        Ok(UserTimeZone::from_setting(settings.timezone.as_deref()))
+    }
+    
+    /// 获取应用锁密码哈希，未设置应用锁时返回 None
+    pub fn get_app_lock_hash(&self) -> Result<Option<String>, AppError> {
+        let hash = self.connection.query_row(
+            "SELECT app_lock_hash FROM settings WHERE id = 1",
+            [],
+            |row| row.get(0),
         )?;
         
-        let task_iter = stmt.query_map([now], |row| {
-            let due_date_timestamp: Option<i64> = row.get(3)?;
-            let due_date = due_date_timestamp.map(|ts| Utc.timestamp_opt(ts, 0).unwrap());
-            
-            let created_at_timestamp: i64 = row.get(6)?;
-            let created_at = Utc.timestamp_opt(created_at_timestamp, 0).unwrap();
-            
-            // 获取任务标签
-            let task_id: i64 = row.get(0)?;
-            let mut tag_stmt = self.connection.prepare(
-                "SELECT tag_name FROM task_tags WHERE task_id = ?1"
-            ).unwrap();
-            
-            let tag_iter = tag_stmt.query_map([task_id], |row| {
-                Ok(row.get::<_, String>(0)?)
-            }).unwrap();
-            
-            let mut tags = Vec::new();
-            for tag in tag_iter {
-                tags.push(tag.unwrap());
-            }
-            
-            Ok(Task {
-                id: Some(row.get(0)?),
-                title: row.get(1)?,
-                description: row.get(2)?,
-                due_date,
-                priority: TaskPriority::from(row.get::<_, i32>(4)?),
-                is_completed: row.get(5)?,
-                created_at,
-                tags,
-                sort_order: row.get(7)?,
-            })
-        })?;
+        Ok(hash)
+    }
+    
+    /// 确认删除数据库前提供了所需的密码
+    ///
+    /// 使用密码加密的数据库需要数据库密码，设置了应用锁的数据库需要应用锁密码，都没有设置时不需要密码。
+    pub fn verify_removal(&mut self, passphrase: Option<&str>) -> Result<(), AppError> {
+        if !self.is_unlocked {
+            let passphrase = passphrase.ok_or_else(|| AppError::locked("数据库使用密码加密，请输入密码"))?;
+            return self.unlock(passphrase);
+        }
         
-        let mut tasks = Vec::new();
-        for task in task_iter {
-            tasks.push(task?);
+        match (self.get_app_lock_hash()?, passphrase) {
+            (None, _) => Ok(()),
+            (Some(_), None) => Err(AppError::locked("已设置应用锁密码，请输入密码")),
+            (Some(hash), Some(passphrase)) if app_lock::verify_passphrase(passphrase, &hash) => Ok(()),
+            (Some(_), Some(_)) => Err(AppError::validation(Some("passphrase"), "密码错误")),
         }
+    }
+    
+    /// 设置或清除应用锁密码哈希
+    pub fn set_app_lock_hash(&self, hash: Option<&str>) -> Result<(), AppError> {
+        self.connection.execute(
+            "UPDATE settings SET app_lock_hash = ?1 WHERE id = 1",
+            [hash],
+        )?;
         
-        Ok(tasks)
+        Ok(())
     }
     
-    /// 获取即将到期的任务（在指定分钟数内）
-    pub fn get_upcoming_tasks(&self, minutes_ahead: i32) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
-        let now = Utc::now();
-        let future = now + chrono::Duration::minutes(minutes_ahead as i64);
+    // === 提醒相关方法 ===
+    
+    /// 获取需要发送提醒的任务：未完成、截止时间在 `now + minutes_before` 之前，且该截止时间尚未提醒过
+    ///
+    /// 全天任务在截止日期（用户时区）开始前 `minutes_before` 分钟提醒。
+    pub fn get_pending_notifications(&self, minutes_before: i32) -> Result<Vec<Task>, AppError> {
+        // 到期时刻不晚于 threshold，即早于 threshold 的下一秒
+        let threshold = Utc::now() + chrono::Duration::minutes(minutes_before as i64);
+        let bounds = DueBounds::between(None, Some(threshold + chrono::Duration::seconds(1)), &self.user_time_zone()?);
+        let (condition, values) = bounds.condition();
+        
+        self.load_tasks(
+            &format!(
+                "is_completed = 0 AND deleted_at IS NULL AND {}
+                 AND NOT EXISTS (
+                    SELECT 1 FROM task_notifications n
+                    WHERE n.task_id = tasks.id AND n.due_date = tasks.due_date
+                 )
+                 ORDER BY due_date ASC",
+                condition
+            ),
+            rusqlite::params_from_iter(values.iter()),
+        )
+    }
+    
+    /// 获取下一次需要发送提醒的时间（最早的未提醒截止时间减去提前量）
+    pub fn get_next_notification_time(&self, minutes_before: i32) -> Result<Option<chrono::DateTime<Utc>>, AppError> {
+        let next_due = |all_day: bool| -> Result<Option<DateTime<Utc>>, AppError> {
+            let next_due: Option<i64> = self.connection.query_row(
+                "SELECT MIN(due_date) FROM tasks
+                 WHERE is_completed = 0 AND due_date IS NOT NULL AND deleted_at IS NULL AND all_day = ?1
+                 AND NOT EXISTS (
+                    SELECT 1 FROM task_notifications n
+                    WHERE n.task_id = tasks.id AND n.due_date = tasks.due_date
+                 )",
+                [all_day],
+                |row| row.get(0),
+            )?;
+            Ok(next_due.and_then(|ts| Utc.timestamp_opt(ts, 0).single()))
+        };
         
-        let now_str = now.to_rfc3339();
-        let future_str = future.to_rfc3339();
+        // 全天任务的到期时间是截止日期在用户时区的零点
+        let zone = self.user_time_zone()?;
+        let next_all_day = next_due(true)?.map(|due| zone.day_start(due.date_naive()));
         
-        let mut stmt = self.connection.prepare(
-            "SELECT id, title, description, due_date, priority, is_completed, created_at, COALESCE(sort_order, 0) as sort_order
-             FROM tasks 
-             WHERE is_completed = 0 AND due_date IS NOT NULL 
-             AND due_date > ?1 AND due_date <= ?2
-             ORDER BY due_date ASC"
+        Ok([next_due(false)?, next_all_day]
+            .into_iter()
+            .flatten()
+            .min()
+            .map(|due| due - chrono::Duration::minutes(minutes_before as i64)))
+    }
+    
+    /// 记录任务在当前截止时间已经发送过提醒
+    pub fn mark_task_notified(&self, task_id: i64, due_date: chrono::DateTime<Utc>) -> Result<(), AppError> {
+        self.connection.execute(
+            "INSERT OR IGNORE INTO task_notifications (task_id, due_date, notified_at) VALUES (?1, ?2, ?3)",
+            params![task_id, due_date.timestamp(), Utc::now().timestamp()],
         )?;
+        Ok(())
+    }
+    
+    /// 获取截止时间在指定窗口内的未完成任务，按截止时间排序
+    pub fn get_tasks_due(&self, window: DueWindow) -> Result<Vec<Task>, AppError> {
+        self.get_tasks_due_at(window, Utc::now())
+    }
+    
+    /// 以 `now` 作为当前时间查询截止时间窗口，可以在固定的时间点检查窗口边界
+    pub fn get_tasks_due_at(&self, window: DueWindow, now: DateTime<Utc>) -> Result<Vec<Task>, AppError> {
+        window.validate()?;
+        
+        let (condition, values) = DueBounds::for_window(window, now, &self.user_time_zone()?).condition();
+        self.load_tasks(
+            &format!("is_completed = 0 AND deleted_at IS NULL AND {} ORDER BY due_date ASC", condition),
+            rusqlite::params_from_iter(values.iter()),
+        )
+    }
+    
+    /// 获取过期的任务
+    pub fn get_overdue_tasks(&self) -> Result<Vec<Task>, AppError> {
+        self.get_tasks_due(DueWindow::Overdue)
+    }
+    
+    /// 获取即将到期的任务（在指定分钟数内）
+    pub fn get_upcoming_tasks(&self, minutes_ahead: i32) -> Result<Vec<Task>, AppError> {
+        self.get_tasks_due(DueWindow::DueWithin { minutes: minutes_ahead })
+    }
+}
+
+/// 读取 `id, name, color, created_at, parent_id` 五列组成的标签
+fn tag_from_row(row: &rusqlite::Row) -> SqliteResult<Tag> {
+    let created_at_timestamp: i64 = row.get(3)?;
+    
+    Ok(Tag {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        color: row.get(2)?,
+        created_at: Utc.timestamp_opt(created_at_timestamp, 0).unwrap(),
+        parent_id: row.get(4)?,
+    })
+}
+
+/// 返回满足条件的标签及其所有子孙标签ID的子查询
+fn tag_subtree_sql(condition: &str) -> String {
+    format!(
+        "WITH RECURSIVE subtree(id) AS (
+            SELECT id FROM tags WHERE {}
+            UNION
+            SELECT tags.id FROM tags JOIN subtree ON tags.parent_id = subtree.id
+        ) SELECT id FROM subtree",
+        condition
+    )
+}
+
+/// 读取任务的列，与 `task_from_row` 中的列序号一一对应
+const TASK_COLUMNS: &str = "id, title, description, due_date, priority, is_completed, created_at, COALESCE(sort_order, 0) as sort_order, recurrence, parent_id,
+    (SELECT COUNT(*) FROM tasks c WHERE c.parent_id = tasks.id AND c.deleted_at IS NULL) as subtask_count,
+    (SELECT COUNT(*) FROM tasks c WHERE c.parent_id = tasks.id AND c.is_completed = 1 AND c.deleted_at IS NULL) as completed_subtask_count,
+    project_id, deleted_at, all_day";
+
+/// 读取 `TASK_COLUMNS` 组成的任务，标签由 `attach_tags` 批量填充
+fn task_from_row(row: &rusqlite::Row) -> SqliteResult<Task> {
+    let due_date_timestamp: Option<i64> = row.get(3)?;
+    let created_at_timestamp: i64 = row.get(6)?;
+    
+    Ok(Task {
+        id: Some(row.get(0)?),
+        title: row.get(1)?,
+        description: row.get(2)?,
+        due_date: due_date_timestamp.map(|ts| Utc.timestamp_opt(ts, 0).unwrap()),
+        priority: TaskPriority::from(row.get::<_, i32>(4)?),
+        is_completed: row.get(5)?,
+        created_at: Utc.timestamp_opt(created_at_timestamp, 0).unwrap(),
+        tags: Vec::new(),
+        sort_order: row.get(7)?,
+        recurrence: parse_recurrence(row.get(8)?),
+        parent_id: row.get(9)?,
+        subtask_count: row.get(10)?,
+        completed_subtask_count: row.get(11)?,
+        project_id: row.get(12)?,
+        deleted_at: row.get::<_, Option<i64>>(13)?.map(|ts| Utc.timestamp_opt(ts, 0).unwrap()),
+        is_all_day: row.get(14)?,
+    })
+}
+
+/// 解析数据库中 JSON 格式的重复规则，格式损坏时视为不重复
+fn parse_recurrence(value: Option<String>) -> Option<RecurrenceRule> {
+    value.and_then(|json| serde_json::from_str(&json).ok())
+}
+
+/// 将筛选条件编译为参数化的 WHERE 子句，返回子句和按顺序绑定的参数
+fn build_filter_clause(filter: &TaskFilter, zone: &UserTimeZone) -> (String, Vec<Value>) {
+    // 回收站中的任务不参与查询
+    let mut conditions: Vec<String> = vec!["deleted_at IS NULL".to_string()];
+    let mut values: Vec<Value> = Vec::new();
+    
+    fn placeholders(count: usize) -> String {
+        vec!["?"; count].join(", ")
+    }
+    
+    if !filter.tags_any.is_empty() {
+        conditions.push(format!(
+            "id IN (SELECT task_id FROM task_tags WHERE tag_id IN ({}))",
+            tag_subtree_sql(&format!("name IN ({})", placeholders(filter.tags_any.len())))
+        ));
+        values.extend(filter.tags_any.iter().cloned().map(Value::Text));
+    }
+    
+    for tag in &filter.tags_all {
+        conditions.push(format!(
+            "id IN (SELECT task_id FROM task_tags WHERE tag_id IN ({}))",
+            tag_subtree_sql("name = ?")
+        ));
+        values.push(Value::Text(tag.clone()));
+    }
+    
+    if !filter.tags_none.is_empty() {
+        conditions.push(format!(
+            "id NOT IN (SELECT task_id FROM task_tags WHERE tag_id IN ({}))",
+            tag_subtree_sql(&format!("name IN ({})", placeholders(filter.tags_none.len())))
+        ));
+        values.extend(filter.tags_none.iter().cloned().map(Value::Text));
+    }
+    
+    if !filter.priorities.is_empty() {
+        conditions.push(format!("priority IN ({})", placeholders(filter.priorities.len())));
+        values.extend(filter.priorities.iter().map(|&priority| {
+            let priority_value: i32 = priority.into();
+            Value::Integer(priority_value as i64)
+        }));
+    }
+    
+    if !filter.project_ids.is_empty() {
+        conditions.push(format!("project_id IN ({})", placeholders(filter.project_ids.len())));
+        values.extend(filter.project_ids.iter().map(|&id| Value::Integer(id)));
+    }
+    
+    let mut due_bounds = Vec::new();
+    if filter.due_before.is_some() || filter.due_after.is_some() {
+        due_bounds.push(DueBounds::between(filter.due_after, filter.due_before, zone));
+    }
+    if let Some(due_range) = filter.due_range {
+        due_bounds.push(relative_due_bounds(due_range, zone));
+    }
+    for bounds in due_bounds {
+        let (condition, bound_values) = bounds.condition();
+        conditions.push(condition);
+        values.extend(bound_values);
+    }
+    
+    if let Some(is_completed) = filter.is_completed {
+        conditions.push("is_completed = ?".to_string());
+        values.push(Value::Integer(is_completed as i64));
+    }
+    
+    if let Some(match_query) = filter.text.as_deref().and_then(search::build_match_query) {
+        conditions.push("id IN (SELECT rowid FROM tasks_fts WHERE tasks_fts MATCH ?)".to_string());
+        values.push(Value::Text(match_query));
+    }
+    
+    if let Some(created_before) = filter.created_before {
+        conditions.push("created_at < ?".to_string());
+        values.push(Value::Integer(created_before.timestamp()));
+    }
+    
+    if let Some(created_after) = filter.created_after {
+        conditions.push("created_at >= ?".to_string());
+        values.push(Value::Integer(created_after.timestamp()));
+    }
+    
+    (conditions.join(" AND "), values)
+}
+
+/// 截止时间的时间戳区间 [start, end)
+///
+/// 定时任务直接比较截止时刻；全天任务保存的是日期（UTC 零点），需要换算为日期区间再比较。
+struct DueBounds {
+    timed: (i64, i64),   // 定时任务的截止时刻
+    all_day: (i64, i64), // 全天任务的截止日期（UTC 零点）
+}
+
+impl DueBounds {
+    /// 到期时刻在 [start, end) 内，边界为空表示不限；全天任务的到期时刻是截止日期在用户时区的零点
+    fn between(start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>, zone: &UserTimeZone) -> Self {
+        // 零点不早于 instant 的第一个日期
+        let first_day_from = |instant: DateTime<Utc>| {
+            let date = zone.date_of(instant);
+            let date = if zone.day_start(date) >= instant { date } else { date + chrono::Duration::days(1) };
+            timezone::all_day_due_date(date).timestamp()
+        };
         
-        let task_iter = stmt.query_map([now_str, future_str], |row| {
-            let due_date_timestamp: Option<i64> = row.get(3)?;
-            let due_date = due_date_timestamp.map(|ts| Utc.timestamp_opt(ts, 0).unwrap());
-            
-            let created_at_timestamp: i64 = row.get(6)?;
-            let created_at = Utc.timestamp_opt(created_at_timestamp, 0).unwrap();
-            
-            // 获取任务标签
-            let task_id: i64 = row.get(0)?;
-            let mut tag_stmt = self.connection.prepare(
-                "SELECT tag_name FROM task_tags WHERE task_id = ?1"
-            ).unwrap();
-            
-            let tag_iter = tag_stmt.query_map([task_id], |row| {
-                Ok(row.get::<_, String>(0)?)
-            }).unwrap();
-            
-            let mut tags = Vec::new();
-            for tag in tag_iter {
-                tags.push(tag.unwrap());
-            }
-            
-            Ok(Task {
-                id: Some(row.get(0)?),
-                title: row.get(1)?,
-                description: row.get(2)?,
-                due_date,
-                priority: TaskPriority::from(row.get::<_, i32>(4)?),
-                is_completed: row.get(5)?,
-                created_at,
-                tags,
-                sort_order: row.get(7)?,
-            })
-        })?;
-        
-        let mut tasks = Vec::new();
-        for task in task_iter {
-            tasks.push(task?);
+        DueBounds {
+            timed: (
+                start.map_or(i64::MIN, |start| start.timestamp()),
+                end.map_or(i64::MAX, |end| end.timestamp()),
+            ),
+            all_day: (
+                start.map_or(i64::MIN, first_day_from),
+                end.map_or(i64::MAX, first_day_from),
+            ),
+        }
+    }
+    
+    /// 截止时间早于 `now`；全天任务在截止日期（用户时区）结束后才算过期
+    fn overdue(now: DateTime<Utc>, zone: &UserTimeZone) -> Self {
+        DueBounds {
+            timed: (i64::MIN, now.timestamp()),
+            all_day: (i64::MIN, timezone::all_day_due_date(zone.date_of(now)).timestamp()),
+        }
+    }
+    
+    /// 截止时间窗口对应的区间，`now` 为当前时间
+    fn for_window(window: DueWindow, now: DateTime<Utc>, zone: &UserTimeZone) -> Self {
+        // 截止时间精确到秒，(now, now + N 分钟] 即 [now + 1 秒, now + N 分钟 + 1 秒)
+        let second = chrono::Duration::seconds(1);
+        match window {
+            DueWindow::Overdue => DueBounds::overdue(now, zone),
+            DueWindow::DueWithin { minutes } => DueBounds::between(
+                Some(now + second),
+                Some(now + chrono::Duration::minutes(minutes as i64) + second),
+                zone,
+            ),
+            DueWindow::Between { start, end } => DueBounds::between(Some(start), Some(end), zone),
+        }
+    }
+    
+    /// 参数化的 SQL 条件和按顺序绑定的参数
+    fn condition(&self) -> (String, Vec<Value>) {
+        (
+            "due_date IS NOT NULL AND ((all_day = 0 AND due_date >= ? AND due_date < ?) OR (all_day = 1 AND due_date >= ? AND due_date < ?))"
+                .to_string(),
+            [self.timed.0, self.timed.1, self.all_day.0, self.all_day.1]
+                .map(Value::Integer)
+                .to_vec(),
+        )
+    }
+}
+
+/// 计算相对时间范围对应的区间，日期按用户时区划分
+fn relative_due_bounds(range: RelativeDueRange, zone: &UserTimeZone) -> DueBounds {
+    let now = Utc::now();
+    let today = zone.today();
+    
+    // 日期区间 [start, end) 对应的两种区间
+    let days = |start: NaiveDate, end: NaiveDate| DueBounds {
+        timed: (zone.day_start(start).timestamp(), zone.day_start(end).timestamp()),
+        all_day: (
+            timezone::all_day_due_date(start).timestamp(),
+            timezone::all_day_due_date(end).timestamp(),
+        ),
+    };
+    
+    match range {
+        RelativeDueRange::Overdue => DueBounds::overdue(now, zone),
+        RelativeDueRange::Today => days(today, today + chrono::Duration::days(1)),
+        RelativeDueRange::Tomorrow => days(today + chrono::Duration::days(1), today + chrono::Duration::days(2)),
+        RelativeDueRange::ThisWeek => {
+            let week_start = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+            days(week_start, week_start + chrono::Duration::days(7))
+        }
+        RelativeDueRange::Next7Days => DueBounds {
+            timed: (now.timestamp(), (now + chrono::Duration::days(7)).timestamp()),
+            all_day: (
+                timezone::all_day_due_date(today).timestamp(),
+                timezone::all_day_due_date(today + chrono::Duration::days(7)).timestamp(),
+            ),
+        },
+        RelativeDueRange::ThisMonth => {
+            let month_start = today.with_day(1).unwrap();
+            let next_month_start = if month_start.month() == 12 {
+                NaiveDate::from_ymd_opt(month_start.year() + 1, 1, 1).unwrap()
+            } else {
+                NaiveDate::from_ymd_opt(month_start.year(), month_start.month() + 1, 1).unwrap()
+            };
+            days(month_start, next_month_start)
         }
-        
-        Ok(tasks)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,173 @@
+//! 数据库加密
+//!
+//! 数据库文件由 SQLCipher 以 AES-256 整体加密，密钥有两种来源：
+//! - 自动生成的随机密钥，保存在系统钥匙串中；钥匙串不可用时（如没有桌面环境的 Linux）
+//!   保存到配置目录下仅当前用户可读的密钥文件
+//! - 用户设置的密码，由 SQLCipher 通过 PBKDF2 派生密钥，不在本地保存，每次启动后需要输入密码解锁
+//!
+//! 每个配置的数据库使用各自的随机密钥，按密钥名称分别保存。
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use crate::models::KeyStorage;
+use crate::error::AppError;
+
+const KEYRING_SERVICE: &str = "MyTodo";
+/// 默认数据库的密钥名称，也是钥匙串中的用户名
+pub const DEFAULT_KEY_NAME: &str = "database-key";
+/// 默认数据库的密钥文件名，其他密钥的文件名为 `<密钥名称>.key`
+const DEFAULT_KEY_FILE_NAME: &str = "database.key";
+
+/// 未加密的 SQLite 文件以该字符串开头
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// 数据库密钥
+pub enum DatabaseKey {
+    /// 自动生成的 256 位随机密钥（十六进制）
+    Generated(String),
+    /// 用户设置的密码
+    Passphrase(String),
+}
+
+impl DatabaseKey {
+    /// 生成新的随机密钥
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        DatabaseKey::Generated(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// `PRAGMA key` / `PRAGMA rekey` 使用的值
+    ///
+    /// 随机密钥使用 SQLCipher 的原始密钥格式 `x'...'`，跳过密钥派生；密码交由 SQLCipher 派生密钥。
+    pub fn pragma_value(&self) -> String {
+        match self {
+            DatabaseKey::Generated(hex) => format!("x'{}'", hex),
+            DatabaseKey::Passphrase(passphrase) => passphrase.clone(),
+        }
+    }
+}
+
+/// 判断数据库文件是否已加密（文件不存在或为空时视为未加密）
+pub fn is_encrypted_file(path: &Path) -> std::io::Result<bool> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+
+    let mut header = Vec::with_capacity(SQLITE_HEADER.len());
+    file.by_ref().take(SQLITE_HEADER.len() as u64).read_to_end(&mut header)?;
+
+    Ok(!header.is_empty() && header != SQLITE_HEADER)
+}
+
+/// 读取保存的随机密钥，优先使用系统钥匙串
+pub fn load_stored_key(key_name: &str) -> Result<Option<(DatabaseKey, KeyStorage)>, AppError> {
+    if let Ok(entry) = keyring::Entry::new(KEYRING_SERVICE, key_name) {
+        if let Ok(hex) = entry.get_password() {
+            return Ok(Some((DatabaseKey::Generated(hex), KeyStorage::Keyring)));
+        }
+    }
+
+    let key_file = key_file_path(key_name)?;
+    if key_file.exists() {
+        let hex = fs::read_to_string(&key_file)?.trim().to_string();
+        return Ok(Some((DatabaseKey::Generated(hex), KeyStorage::File)));
+    }
+
+    Ok(None)
+}
+
+/// 保存随机密钥，系统钥匙串不可用时写入密钥文件
+pub fn store_key(key_name: &str, key: &DatabaseKey) -> Result<KeyStorage, AppError> {
+    let hex = match key {
+        DatabaseKey::Generated(hex) => hex,
+        DatabaseKey::Passphrase(_) => return Err(AppError::validation(None, "用户密码不会保存在本地")),
+    };
+
+    // 部分钥匙串实现写入失败时不会报错，读回校验后才认为保存成功
+    let saved_to_keyring = keyring::Entry::new(KEYRING_SERVICE, key_name)
+        .and_then(|entry| {
+            entry.set_password(hex)?;
+            entry.get_password()
+        })
+        .map(|saved| &saved == hex)
+        .unwrap_or(false);
+
+    if saved_to_keyring {
+        // 之前可能回退到了密钥文件，保存成功后删除旧文件
+        let key_file = key_file_path(key_name)?;
+        if key_file.exists() {
+            fs::remove_file(key_file)?;
+        }
+        return Ok(KeyStorage::Keyring);
+    }
+
+    write_key_file(key_name, hex)?;
+    Ok(KeyStorage::File)
+}
+
+/// 删除保存的随机密钥（改用用户密码后调用）
+pub fn delete_stored_key(key_name: &str) -> Result<(), AppError> {
+    if let Ok(entry) = keyring::Entry::new(KEYRING_SERVICE, key_name) {
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            // 钥匙串不可用时密钥只可能保存在文件中
+            Err(keyring::Error::NoStorageAccess(_)) | Err(keyring::Error::PlatformFailure(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let key_file = key_file_path(key_name)?;
+    if key_file.exists() {
+        fs::remove_file(key_file)?;
+    }
+
+    Ok(())
+}
+
+/// 密钥文件路径：配置目录/MyTodo/database.key
+///
+/// 与数据库文件分开存放，避免同步或备份数据目录时连同密钥一起带走。
+fn key_file_path(key_name: &str) -> Result<PathBuf, AppError> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| AppError::io("无法获取用户配置目录"))?;
+
+    let file_name = if key_name == DEFAULT_KEY_NAME {
+        DEFAULT_KEY_FILE_NAME.to_string()
+    } else {
+        format!("{}.key", key_name)
+    };
+    Ok(config_dir.join("MyTodo").join(file_name))
+}
+
+/// 写入密钥文件，Unix 系统上限制为仅当前用户可读写
+fn write_key_file(key_name: &str, hex: &str) -> Result<(), AppError> {
+    let key_file = key_file_path(key_name)?;
+    if let Some(parent_dir) = key_file.parent() {
+        fs::create_dir_all(parent_dir)?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&key_file)?;
+        file.write_all(hex.as_bytes())?;
+    }
+
+    #[cfg(not(unix))]
+    fs::write(&key_file, hex)?;
+
+    Ok(())
+}
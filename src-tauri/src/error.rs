@@ -0,0 +1,195 @@
+//! 统一错误类型
+//!
+//! 命令返回给前端的错误序列化为 `{ code, message, details }`：
+//! - `code`：稳定的机器可读错误码，前端据此区分错误类型并显示本地化提示
+//! - `message`：中文说明，可直接显示
+//! - `details`：出错的对象（`entity`、`id`）或字段（`field`），没有时为空对象
+
+use std::fmt;
+use serde::{Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+/// 错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ErrorCode {
+    #[serde(rename = "not_found")]
+    NotFound,   // 对象不存在
+    #[serde(rename = "validation")]
+    Validation, // 输入不合法
+    #[serde(rename = "conflict")]
+    Conflict,   // 与现有数据或状态冲突
+    #[serde(rename = "locked")]
+    Locked,     // 应用或数据库已锁定
+    #[serde(rename = "storage")]
+    Storage,    // 数据库读写失败
+    #[serde(rename = "io")]
+    Io,         // 文件系统错误（磁盘已满、没有权限等）
+}
+
+#[derive(Debug)]
+pub enum AppError {
+    NotFound { entity: &'static str, id: Option<i64>, message: String },
+    Validation { field: Option<String>, message: String },
+    Conflict { message: String },
+    Locked { message: String },
+    Storage { message: String },
+    Io { message: String },
+}
+
+impl AppError {
+    /// 指定对象不存在，`entity` 为对象类型（task、project、smart_list、tag、profile）
+    pub fn not_found(entity: &'static str, id: i64) -> Self {
+        AppError::NotFound {
+            entity,
+            id: Some(id),
+            message: format!("{}不存在: {}", entity_name(entity), id),
+        }
+    }
+
+    /// 输入不合法，`field` 为出错的字段名
+    pub fn validation(field: Option<&str>, message: impl Into<String>) -> Self {
+        AppError::Validation { field: field.map(str::to_string), message: message.into() }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        AppError::Conflict { message: message.into() }
+    }
+
+    pub fn locked(message: impl Into<String>) -> Self {
+        AppError::Locked { message: message.into() }
+    }
+
+    pub fn storage(message: impl Into<String>) -> Self {
+        AppError::Storage { message: message.into() }
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        AppError::Io { message: message.into() }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::NotFound { .. } => ErrorCode::NotFound,
+            AppError::Validation { .. } => ErrorCode::Validation,
+            AppError::Conflict { .. } => ErrorCode::Conflict,
+            AppError::Locked { .. } => ErrorCode::Locked,
+            AppError::Storage { .. } => ErrorCode::Storage,
+            AppError::Io { .. } => ErrorCode::Io,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::NotFound { message, .. }
+            | AppError::Validation { message, .. }
+            | AppError::Conflict { message }
+            | AppError::Locked { message }
+            | AppError::Storage { message }
+            | AppError::Io { message } => message,
+        }
+    }
+
+    /// 在说明前加上正在执行的操作，如 `创建任务失败: 项目不存在: 3`
+    pub fn context(mut self, action: &str) -> Self {
+        match &mut self {
+            AppError::NotFound { message, .. }
+            | AppError::Validation { message, .. }
+            | AppError::Conflict { message }
+            | AppError::Locked { message }
+            | AppError::Storage { message }
+            | AppError::Io { message } => *message = format!("{}: {}", action, message),
+        }
+        self
+    }
+}
+
+/// 对象类型的中文名称
+fn entity_name(entity: &str) -> &str {
+    match entity {
+        "task" => "任务",
+        "project" => "项目",
+        "smart_list" => "智能列表",
+        "tag" => "标签",
+        "profile" => "配置",
+        _ => "记录",
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Details<'a> {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            entity: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            id: Option<i64>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            field: Option<&'a str>,
+        }
+
+        let details = match self {
+            AppError::NotFound { entity, id, .. } => Details { entity: Some(entity), id: *id, field: None },
+            AppError::Validation { field, .. } => Details { entity: None, id: None, field: field.as_deref() },
+            _ => Details { entity: None, id: None, field: None },
+        };
+
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", &self.code())?;
+        state.serialize_field("message", self.message())?;
+        state.serialize_field("details", &details)?;
+        state.end()
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(error: rusqlite::Error) -> Self {
+        use rusqlite::ErrorCode as SqliteCode;
+
+        let message = error.to_string();
+        match &error {
+            rusqlite::Error::QueryReturnedNoRows => {
+                AppError::NotFound { entity: "record", id: None, message: "记录不存在".to_string() }
+            }
+            rusqlite::Error::SqliteFailure(failure, _) => match failure.code {
+                SqliteCode::ConstraintViolation => AppError::Conflict { message },
+                SqliteCode::DiskFull
+                | SqliteCode::SystemIoFailure
+                | SqliteCode::CannotOpen
+                | SqliteCode::PermissionDenied
+                | SqliteCode::ReadOnly => AppError::Io { message },
+                _ => AppError::Storage { message },
+            },
+            _ => AppError::Storage { message },
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        AppError::Io { message: error.to_string() }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(error: serde_json::Error) -> Self {
+        // 数据格式错误来自导入文件或前端传入的内容
+        AppError::Validation { field: None, message: format!("数据格式错误: {}", error) }
+    }
+}
+
+impl From<keyring::Error> for AppError {
+    fn from(error: keyring::Error) -> Self {
+        AppError::Storage { message: format!("访问系统钥匙串失败: {}", error) }
+    }
+}
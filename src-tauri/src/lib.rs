@@ -1,22 +1,92 @@
 pub mod models;
 pub mod database;
 pub mod commands;
+pub mod recurrence;
+pub mod scheduler;
+pub mod search;
+pub mod encryption;
+pub mod app_lock;
+pub mod migrations;
+pub mod error;
+pub mod validation;
+pub mod timezone;
+pub mod data_dir;
+pub mod profiles;
 
 use database::Database;
+use app_lock::AppLock;
+use data_dir::DataDir;
+use profiles::ProfileStore;
 use std::sync::Mutex;
-use tauri::{tray::TrayIconBuilder, Manager, Emitter, menu::{MenuBuilder, MenuItem, PredefinedMenuItem}};
+use tauri::{tray::TrayIconBuilder, AppHandle, Manager, Emitter, Runtime, menu::{Menu, MenuBuilder, MenuItem, PredefinedMenuItem}};
+
+/// 系统托盘的ID，切换配置后据此更新托盘菜单
+const TRAY_ID: &str = "main";
 
 // 全局数据库状态
 pub struct AppState {
     pub db: Mutex<Database>,
+    pub app_lock: Mutex<AppLock>,
+    pub data_dir: Mutex<DataDir>,
+    pub profiles: Mutex<ProfileStore>,
+}
+
+/// 创建托盘菜单，顶部显示当前使用的配置
+fn build_tray_menu<R: Runtime, M: Manager<R>>(app: &M, profile_name: &str) -> tauri::Result<Menu<R>> {
+  let profile_item = MenuItem::with_id(app, "profile", format!("当前配置：{}", profile_name), false, None::<&str>)?;
+  let separator0 = PredefinedMenuItem::separator(app)?;
+  let show_item = MenuItem::with_id(app, "show", "显示主界面", true, None::<&str>)?;
+  let add_task_item = MenuItem::with_id(app, "add_task", "快速添加任务", true, None::<&str>)?;
+  let separator1 = PredefinedMenuItem::separator(app)?;
+  let settings_item = MenuItem::with_id(app, "settings", "设置", true, None::<&str>)?;
+  let separator2 = PredefinedMenuItem::separator(app)?;
+  let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
+  
+  MenuBuilder::new(app)
+    .items(&[&profile_item, &separator0, &show_item, &add_task_item, &separator1, &settings_item, &separator2, &quit_item])
+    .build()
+}
+
+/// 托盘提示文字
+fn tray_tooltip(profile_name: &str) -> String {
+  format!("MyTodo - 个人待办事项（{}）", profile_name)
+}
+
+/// 切换或重命名当前配置后更新托盘菜单和提示文字
+pub fn refresh_tray<R: Runtime>(app: &AppHandle<R>, profile_name: &str) {
+  if let Some(tray) = app.tray_by_id(TRAY_ID) {
+    match build_tray_menu(app, profile_name) {
+      Ok(menu) => {
+        let _ = tray.set_menu(Some(menu));
+      }
+      Err(e) => log::error!("更新托盘菜单失败: {}", e),
+    }
+    let _ = tray.set_tooltip(Some(tray_tooltip(profile_name)));
+  }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-  // 初始化数据库
-  let database = Database::new().expect("Failed to initialize database");
+  // 确定数据目录，打开当前配置的数据库
+  let data_dir = data_dir::resolve().expect("Failed to resolve data directory");
+  let profiles = ProfileStore::load(&data_dir.path).expect("Failed to load profiles");
+  let active_profile = profiles.active();
+  let database = Database::open(&profiles.database_path(active_profile.id), &ProfileStore::key_name(active_profile.id))
+    .expect("Failed to initialize database");
+  
+  // 设置了应用锁密码时以锁定状态启动（使用密码加密的数据库在解锁后再读取设置）
+  let app_lock = if database.is_unlocked() {
+    let settings = database.get_app_settings().expect("Failed to load settings");
+    AppLock::new(settings.app_lock_enabled, settings.auto_lock_minutes)
+  } else {
+    AppLock::new(false, 0)
+  };
+  
   let app_state = AppState {
     db: Mutex::new(database),
+    app_lock: Mutex::new(app_lock),
+    data_dir: Mutex::new(data_dir),
+    profiles: Mutex::new(profiles),
   };
 
   tauri::Builder::default()
@@ -30,13 +100,55 @@ pub fn run() {
       commands::update_task,
       commands::delete_task,
       commands::toggle_task_completion,
+      commands::get_task_history,
       commands::export_tasks_to_json,
       commands::import_tasks_from_json,
       commands::clear_all_tasks,
+      commands::query_tasks,
+      commands::search_tasks,
+      commands::create_project,
+      commands::get_projects,
+      commands::update_project,
+      commands::delete_project,
+      commands::get_project_tasks,
+      commands::move_tasks_to_project,
+      commands::create_smart_list,
+      commands::get_smart_lists,
+      commands::update_smart_list,
+      commands::delete_smart_list,
+      commands::get_smart_list_tasks,
+      commands::get_trashed_tasks,
+      commands::restore_task,
+      commands::empty_trash,
+      commands::create_subtask,
+      commands::get_subtasks,
+      commands::move_subtask,
       commands::create_tag,
       commands::get_all_tags,
       commands::update_tag,
       commands::delete_tag,
+      commands::get_tag_stats,
+      commands::merge_tags,
+      commands::delete_unused_tags,
+      commands::undo,
+      commands::redo,
+      commands::get_undo_status,
+      commands::check_database_consistency,
+      commands::get_encryption_status,
+      commands::unlock_database,
+      commands::encrypt_database,
+      commands::change_database_key,
+      commands::get_data_directory,
+      commands::move_data_directory,
+      commands::get_profiles,
+      commands::create_profile,
+      commands::rename_profile,
+      commands::delete_profile,
+      commands::switch_profile,
+      commands::get_app_lock_status,
+      commands::unlock_app,
+      commands::lock_app,
+      commands::set_app_lock_passphrase,
       commands::get_app_settings,
       commands::update_app_settings,
       commands::handle_close_request,
@@ -45,9 +157,10 @@ pub fn run() {
       commands::send_notification,
       commands::check_overdue_tasks,
       commands::get_upcoming_tasks,
+      commands::get_tasks_due,
       commands::update_tasks_sort_order,
     ])
-    .setup(|app| {
+    .setup(move |app| {
       if cfg!(debug_assertions) {
         app.handle().plugin(
           tauri_plugin_log::Builder::default()
@@ -57,23 +170,15 @@ pub fn run() {
       }
       
       // 创建托盘菜单
-      let show_item = MenuItem::with_id(app, "show", "显示主界面", true, None::<&str>)?;
-      let add_task_item = MenuItem::with_id(app, "add_task", "快速添加任务", true, None::<&str>)?;
-      let separator1 = PredefinedMenuItem::separator(app)?;
-      let settings_item = MenuItem::with_id(app, "settings", "设置", true, None::<&str>)?;
-      let separator2 = PredefinedMenuItem::separator(app)?;
-      let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
-      
-      let menu = MenuBuilder::new(app)
-        .items(&[&show_item, &add_task_item, &separator1, &settings_item, &separator2, &quit_item])
-        .build()?;
+      let profile_name = active_profile.name.clone();
+      let menu = build_tray_menu(app, &profile_name)?;
       
       // 创建系统托盘
-      let _tray = TrayIconBuilder::new()
+      let _tray = TrayIconBuilder::with_id(TRAY_ID)
         .icon(app.default_window_icon().unwrap().clone())
         .menu(&menu)
         .show_menu_on_left_click(false)
-        .tooltip("MyTodo - 个人待办事项")
+        .tooltip(tray_tooltip(&profile_name))
         .on_tray_icon_event(|tray, event| {
           match event {
             tauri::tray::TrayIconEvent::Click { button, rect: _, .. } => {
@@ -153,6 +258,12 @@ pub fn run() {
         })
         .build(app)?;
       
+      // 启动后台提醒调度器
+      scheduler::start(app.handle().clone());
+      
+      // 启动空闲自动锁定
+      scheduler::start_auto_lock(app.handle().clone());
+      
       // 处理窗口关闭事件
       if let Some(window) = app.get_webview_window("main") {
         let window_clone = window.clone();
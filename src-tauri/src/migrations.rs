@@ -0,0 +1,423 @@
+//! 数据库结构迁移
+//!
+//! 数据库的结构版本保存在 `PRAGMA user_version` 中。打开数据库时按版本号依次执行尚未应用的迁移，
+//! 每个迁移和版本号的更新在同一个事务中完成，失败时整体回滚，不会留下只迁移了一半的数据库。
+//!
+//! 修改表结构时在 `MIGRATIONS` 末尾追加新的迁移，不要修改已经发布的迁移。
+//! 引入版本号之前的数据库版本为 0，前几个迁移需要兼容表和字段已经存在的情况。
+
+use std::path::{Path, PathBuf};
+use rusqlite::{Connection, Result as SqliteResult};
+use crate::error::AppError;
+
+/// 一次结构迁移
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub up: fn(&Connection) -> SqliteResult<()>,
+}
+
+/// 所有迁移，按版本号升序排列
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "创建任务、标签和设置表", up: create_base_tables },
+    Migration { version: 2, description: "任务排序字段", up: add_sort_order },
+    Migration { version: 3, description: "重复任务和提醒记录", up: add_recurrence },
+    Migration { version: 4, description: "子任务", up: add_subtasks },
+    Migration { version: 5, description: "全文搜索索引", up: add_search_index },
+    Migration { version: 6, description: "智能列表", up: add_smart_lists },
+    Migration { version: 7, description: "项目", up: add_projects },
+    Migration { version: 8, description: "回收站", up: add_trash },
+    Migration { version: 9, description: "撤销/重做日志", up: add_undo_journal },
+    Migration { version: 10, description: "任务变更历史", up: add_task_history },
+    Migration { version: 11, description: "应用锁", up: add_app_lock },
+    Migration { version: 12, description: "任务标签关联改为引用标签ID", up: reference_tags_by_id },
+    Migration { version: 13, description: "标记自动创建的标签", up: add_tag_auto_created },
+    Migration { version: 14, description: "层级标签", up: add_tag_hierarchy },
+    Migration { version: 15, description: "时区设置和全天任务", up: add_timezone_and_all_day },
+    Migration { version: 16, description: "任务时间统一为时间戳", up: normalize_task_timestamps },
+    Migration { version: 17, description: "已过期任务视为已提醒", up: mark_overdue_tasks_notified },
+];
+
+/// 当前应用支持的数据库版本
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|migration| migration.version).unwrap_or(0)
+}
+
+/// 将数据库迁移到最新版本
+///
+/// 数据库版本高于当前应用时拒绝打开，避免旧版本应用破坏新版本写入的数据。
+/// 迁移已有数据之前先在同一目录下备份数据库文件（如 `tasks.db.v3.bak`）。
+pub fn run(connection: &Connection, db_path: &Path) -> Result<(), AppError> {
+    let current_version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let latest = latest_version();
+
+    if current_version > latest {
+        return Err(AppError::conflict(format!(
+            "数据库版本（{}）高于当前应用支持的版本（{}），请升级应用后再打开",
+            current_version, latest
+        )));
+    }
+
+    if current_version == latest {
+        return Ok(());
+    }
+
+    if has_existing_data(connection)? {
+        backup(db_path, current_version)?;
+    }
+
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > current_version) {
+        let transaction = connection.unchecked_transaction()?;
+        (migration.up)(&transaction).map_err(|e| {
+            AppError::storage(format!("数据库迁移到版本 {}（{}）失败: {}", migration.version, migration.description, e))
+        })?;
+        transaction.pragma_update(None, "user_version", migration.version)?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+/// 新建的空数据库不需要备份
+fn has_existing_data(connection: &Connection) -> SqliteResult<bool> {
+    connection.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'tasks')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// 复制数据库文件作为迁移前的备份（加密的数据库备份后仍然是加密的）
+fn backup(db_path: &Path, version: i64) -> Result<PathBuf, AppError> {
+    let file_name = db_path
+        .file_name()
+        .ok_or_else(|| AppError::io("无效的数据库文件路径"))?
+        .to_string_lossy();
+    let backup_path = db_path.with_file_name(format!("{}.v{}.bak", file_name, version));
+
+    std::fs::copy(db_path, &backup_path)?;
+    Ok(backup_path)
+}
+
+/// 字段不存在时添加字段，返回是否新增
+fn add_column_if_missing(connection: &Connection, table: &str, column: &str, definition: &str) -> SqliteResult<bool> {
+    let mut stmt = connection.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<SqliteResult<_>>()?;
+
+    if columns.iter().any(|name| name == column) {
+        return Ok(false);
+    }
+
+    connection.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    Ok(true)
+}
+
+// === 迁移 ===
+
+fn create_base_tables(connection: &Connection) -> SqliteResult<()> {
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS tasks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            title TEXT NOT NULL,
+            description TEXT DEFAULT '',
+            due_date DATETIME,
+            priority INTEGER NOT NULL DEFAULT 1 CHECK(priority IN (0,1,2)),
+            is_completed BOOLEAN NOT NULL DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            color TEXT NOT NULL DEFAULT '#3b82f6',
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- 任务-标签关联表（多对多关系）
+        CREATE TABLE IF NOT EXISTS task_tags (
+            task_id INTEGER NOT NULL,
+            tag_name TEXT NOT NULL,
+            PRIMARY KEY (task_id, tag_name),
+            FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_name) REFERENCES tags(name) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_tasks_priority ON tasks(priority);
+        CREATE INDEX IF NOT EXISTS idx_tasks_due_date ON tasks(due_date);
+        CREATE INDEX IF NOT EXISTS idx_tasks_is_completed ON tasks(is_completed);
+        CREATE INDEX IF NOT EXISTS idx_task_tags_task_id ON task_tags(task_id);
+        CREATE INDEX IF NOT EXISTS idx_task_tags_tag_name ON task_tags(tag_name);
+
+        CREATE TABLE IF NOT EXISTS settings (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            close_behavior TEXT NOT NULL DEFAULT 'ask' CHECK(close_behavior IN ('exit','minimize','ask')),
+            notifications_enabled BOOLEAN NOT NULL DEFAULT 1,
+            notification_time_before INTEGER NOT NULL DEFAULT 15,
+            startup_behavior TEXT NOT NULL DEFAULT 'normal' CHECK(startup_behavior IN ('normal','minimized')),
+            theme TEXT NOT NULL DEFAULT 'auto'
+        );
+
+        INSERT OR IGNORE INTO settings (id, close_behavior, notifications_enabled, notification_time_before, startup_behavior, theme)
+        VALUES (1, 'ask', 1, 15, 'normal', 'auto');"
+    )
+}
+
+fn add_sort_order(connection: &Connection) -> SqliteResult<()> {
+    if add_column_if_missing(connection, "tasks", "sort_order", "INTEGER DEFAULT 0")? {
+        // 为现有任务设置sort_order值（基于created_at排序）
+        connection.execute(
+            "UPDATE tasks SET sort_order = (
+                SELECT COUNT(*) FROM tasks t2 WHERE t2.created_at <= tasks.created_at
+            )",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+fn add_recurrence(connection: &Connection) -> SqliteResult<()> {
+    // recurrence: JSON 格式的重复规则
+    add_column_if_missing(connection, "tasks", "recurrence", "TEXT")?;
+    // next_occurrence_id: 完成后生成的下一次任务，避免反复切换状态时重复生成
+    add_column_if_missing(connection, "tasks", "next_occurrence_id", "INTEGER")?;
+
+    // 提醒记录表（记录已发送过提醒的任务及其截止时间，保证每个截止时间只提醒一次）
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS task_notifications (
+            task_id INTEGER NOT NULL,
+            due_date INTEGER NOT NULL,
+            notified_at INTEGER NOT NULL,
+            PRIMARY KEY (task_id, due_date),
+            FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn add_subtasks(connection: &Connection) -> SqliteResult<()> {
+    add_column_if_missing(connection, "tasks", "parent_id", "INTEGER REFERENCES tasks(id) ON DELETE CASCADE")?;
+    connection.execute("CREATE INDEX IF NOT EXISTS idx_tasks_parent_id ON tasks(parent_id)", [])?;
+    Ok(())
+}
+
+fn add_search_index(connection: &Connection) -> SqliteResult<()> {
+    // 独立存储分词后的文本，rowid 与 tasks.id 对应；索引内容在打开数据库时检查并重建
+    connection.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS tasks_fts USING fts5(
+            title, description, tags,
+            tokenize = 'unicode61 remove_diacritics 2'
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn add_smart_lists(connection: &Connection) -> SqliteResult<()> {
+    // 保存的任务查询，query 为 JSON 格式的 TaskQuery
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS smart_lists (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            icon TEXT NOT NULL DEFAULT '',
+            query TEXT NOT NULL,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn add_projects(connection: &Connection) -> SqliteResult<()> {
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS projects (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            color TEXT NOT NULL DEFAULT '#3b82f6',
+            icon TEXT NOT NULL DEFAULT '',
+            is_archived BOOLEAN NOT NULL DEFAULT 0,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    add_column_if_missing(connection, "tasks", "project_id", "INTEGER REFERENCES projects(id) ON DELETE SET NULL")?;
+    connection.execute("CREATE INDEX IF NOT EXISTS idx_tasks_project_id ON tasks(project_id)", [])?;
+    Ok(())
+}
+
+fn add_trash(connection: &Connection) -> SqliteResult<()> {
+    add_column_if_missing(connection, "tasks", "deleted_at", "INTEGER")?;
+    connection.execute("CREATE INDEX IF NOT EXISTS idx_tasks_deleted_at ON tasks(deleted_at)", [])?;
+    // 回收站保留天数，0 表示不自动清理
+    add_column_if_missing(connection, "settings", "trash_retention_days", "INTEGER NOT NULL DEFAULT 30")?;
+    Ok(())
+}
+
+fn add_undo_journal(connection: &Connection) -> SqliteResult<()> {
+    // 触发器依赖各表的字段，在打开数据库时重新生成
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS undo_log (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            table_name TEXT NOT NULL,
+            row_key INTEGER,
+            sql TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS undo_steps (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL CHECK(kind IN ('undo','redo')),
+            description TEXT NOT NULL,
+            first_seq INTEGER NOT NULL,
+            last_seq INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS undo_state (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            recording BOOLEAN NOT NULL DEFAULT 0
+        );"
+    )
+}
+
+fn add_task_history(connection: &Connection) -> SqliteResult<()> {
+    // 只追加，不参与撤销/重做
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS task_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_id INTEGER NOT NULL,
+            field TEXT NOT NULL,
+            old_value TEXT,
+            new_value TEXT,
+            changed_at INTEGER NOT NULL,
+            FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_task_history_task_id ON task_history(task_id, changed_at);"
+    )
+}
+
+fn add_app_lock(connection: &Connection) -> SqliteResult<()> {
+    add_column_if_missing(connection, "settings", "app_lock_hash", "TEXT")?;
+    // 空闲多少分钟后自动锁定，0 表示不自动锁定
+    add_column_if_missing(connection, "settings", "auto_lock_minutes", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+fn reference_tags_by_id(connection: &Connection) -> SqliteResult<()> {
+    // 重命名标签后旧名称的关联不再对应任何标签，先按旧名称补建标签，保留任务上原有的标签
+    connection.execute_batch(
+        "CREATE TABLE task_tags_by_id (
+            task_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (task_id, tag_id),
+            FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+        );
+
+        INSERT OR IGNORE INTO tags (name, color, created_at)
+            SELECT DISTINCT tag_name, '#3b82f6', CAST(strftime('%s', 'now') AS INTEGER) FROM task_tags
+            WHERE task_id IN (SELECT id FROM tasks);
+
+        INSERT OR IGNORE INTO task_tags_by_id (task_id, tag_id)
+            SELECT task_tags.task_id, tags.id FROM task_tags
+            JOIN tags ON tags.name = task_tags.tag_name
+            WHERE task_tags.task_id IN (SELECT id FROM tasks);
+
+        DROP TABLE task_tags;
+        ALTER TABLE task_tags_by_id RENAME TO task_tags;
+
+        CREATE INDEX IF NOT EXISTS idx_task_tags_task_id ON task_tags(task_id);
+        CREATE INDEX IF NOT EXISTS idx_task_tags_tag_id ON task_tags(tag_id);
+
+        -- 撤销日志中的逆操作按旧的表结构生成，无法再执行
+        DELETE FROM undo_log;
+        DELETE FROM undo_steps;"
+    )
+}
+
+fn add_tag_auto_created(connection: &Connection) -> SqliteResult<()> {
+    add_column_if_missing(connection, "tags", "auto_created", "INTEGER NOT NULL DEFAULT 0")?;
+    // 之前编辑任务时自动创建的标签没有写入 created_at，取的是默认的文本时间，据此识别并统一为时间戳
+    connection.execute(
+        "UPDATE tags SET auto_created = 1, created_at = CAST(strftime('%s', created_at) AS INTEGER)
+         WHERE typeof(created_at) = 'text'",
+        [],
+    )?;
+    Ok(())
+}
+
+fn add_tag_hierarchy(connection: &Connection) -> SqliteResult<()> {
+    // 标签名称保存完整路径（如 work/clientA），parent_id 指向上级标签，删除上级标签时子标签一并删除
+    add_column_if_missing(connection, "tags", "parent_id", "INTEGER REFERENCES tags(id) ON DELETE CASCADE")?;
+    connection.execute("CREATE INDEX IF NOT EXISTS idx_tags_parent_id ON tags(parent_id)", [])?;
+
+    // 已有的带斜杠的标签按路径从顶层开始逐级补建上级标签，并设置上下级关系
+    let names: Vec<String> = {
+        let mut stmt = connection.prepare("SELECT name FROM tags WHERE name LIKE '%/%'")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<SqliteResult<_>>()?
+    };
+
+    for name in names {
+        let segments: Vec<&str> = name.split('/').collect();
+        for depth in 1..segments.len() {
+            let parent_path = segments[..depth].join("/");
+            let child_path = segments[..=depth].join("/");
+            for path in [&parent_path, &child_path] {
+                connection.execute(
+                    "INSERT OR IGNORE INTO tags (name, color, created_at, auto_created) VALUES (?1, '#3b82f6', CAST(strftime('%s', 'now') AS INTEGER), 1)",
+                    [path],
+                )?;
+            }
+            connection.execute(
+                "UPDATE tags SET parent_id = (SELECT id FROM tags WHERE name = ?1) WHERE name = ?2",
+                [&parent_path, &child_path],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn add_timezone_and_all_day(connection: &Connection) -> SqliteResult<()> {
+    // 时区为空时使用系统时区
+    add_column_if_missing(connection, "settings", "timezone", "TEXT")?;
+    // 全天任务的截止时间只有日期，按该日期的 UTC 零点保存
+    add_column_if_missing(connection, "tasks", "all_day", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+fn normalize_task_timestamps(connection: &Connection) -> SqliteResult<()> {
+    // 早期版本的任务可能以文本保存时间（默认的 CURRENT_TIMESTAMP 或 RFC 3339 字符串），
+    // 按文本比较截止时间会得到错误的结果，统一转换为 Unix 时间戳；无法解析的创建时间取迁移时间
+    connection.execute_batch(
+        "UPDATE tasks SET due_date = CAST(strftime('%s', due_date) AS INTEGER)
+         WHERE typeof(due_date) = 'text';
+
+        UPDATE tasks SET deleted_at = CAST(strftime('%s', deleted_at) AS INTEGER)
+         WHERE typeof(deleted_at) = 'text';
+
+        UPDATE tasks SET created_at = COALESCE(CAST(strftime('%s', created_at) AS INTEGER), CAST(strftime('%s', 'now') AS INTEGER))
+         WHERE typeof(created_at) = 'text' OR created_at IS NULL;"
+    )
+}
+
+fn mark_overdue_tasks_notified(connection: &Connection) -> SqliteResult<()> {
+    // 启用后台提醒之前已经过期的任务没有提醒记录，第一次检查时会被当作新的过期任务一起提醒。
+    // 升级时把这些任务记为已提醒；全天任务在截止日期结束后才算过期
+    connection.execute(
+        "INSERT OR IGNORE INTO task_notifications (task_id, due_date, notified_at)
+         SELECT id, due_date, CAST(strftime('%s', 'now') AS INTEGER) FROM tasks
+         WHERE is_completed = 0 AND deleted_at IS NULL AND due_date IS NOT NULL
+         AND due_date + CASE WHEN all_day = 1 THEN 86400 ELSE 0 END < CAST(strftime('%s', 'now') AS INTEGER)",
+        [],
+    )?;
+    Ok(())
+}
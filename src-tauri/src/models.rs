@@ -12,14 +12,87 @@ pub struct Task {
     pub created_at: DateTime<Utc>,
     pub tags: Vec<String>, // 任务标签列表
     pub sort_order: i64,   // 新增：排序顺序
+    #[serde(default)]
+    pub recurrence: Option<RecurrenceRule>, // 重复规则（为空表示不重复）
+    #[serde(default)]
+    pub parent_id: Option<i64>, // 父任务ID（为空表示顶层任务）
+    #[serde(default)]
+    pub subtask_count: i64, // 直接子任务数量
+    #[serde(default)]
+    pub completed_subtask_count: i64, // 已完成的直接子任务数量
+    #[serde(default)]
+    pub project_id: Option<i64>, // 所属项目ID（为空表示收集箱）
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>, // 移入回收站的时间（为空表示未删除）
+    #[serde(default)]
+    pub is_all_day: bool, // 全天任务：截止时间只有日期，保存为该日期的 UTC 零点
 }
 
+/// 项目（任务清单）
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Tag {
+pub struct Project {
     pub id: Option<i64>,
     pub name: String,
+    pub color: String, // 项目颜色（十六进制）
+    pub icon: String,
+    pub is_archived: bool,
+    pub sort_order: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 重复任务规则（参考 iCalendar RRULE 的子集）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurrenceRule {
+    pub frequency: RecurrenceFrequency,
+    #[serde(default = "default_recurrence_interval")]
+    pub interval: u32, // 间隔，如每 2 周
+    #[serde(default)]
+    pub by_weekday: Vec<u8>, // 指定星期几（0 = 周日 ... 6 = 周六），用于每日/每周规则
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>, // 截止日期，之后不再生成
+    #[serde(default)]
+    pub count: Option<u32>, // 剩余次数（含当前这一次）
+}
+
+fn default_recurrence_interval() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RecurrenceFrequency {
+    #[serde(rename = "daily")]
+    Daily,
+    #[serde(rename = "weekly")]
+    Weekly,
+    #[serde(rename = "monthly")]
+    Monthly,
+    #[serde(rename = "yearly")]
+    Yearly,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: Option<i64>,
+    pub name: String,  // 完整路径，如 "work/clientA/billing"
     pub color: String, // 标签颜色（十六进制）
     pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub parent_id: Option<i64>, // 上级标签ID（为空表示顶层标签）
+}
+
+/// 标签使用情况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagStats {
+    pub tag: Tag,
+    pub auto_created: bool,   // 是否为编辑任务时自动创建的标签
+    pub open_count: i64,      // 关联的未完成任务数
+    pub completed_count: i64, // 关联的已完成任务数
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergeTagsRequest {
+    pub source_ids: Vec<i64>, // 被合并的标签，合并后删除
+    pub target_id: i64,       // 保留的标签
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -46,21 +119,31 @@ impl<'de> serde::Deserialize<'de> for TaskPriority {
         D: serde::Deserializer<'de>,
     {
         let value = i32::deserialize(deserializer)?;
-        Ok(TaskPriority::from(value))
+        TaskPriority::from_value(value).ok_or_else(|| {
+            serde::de::Error::custom(format!("无效的优先级: {}，应为 0（低）、1（中）或 2（高）", value))
+        })
     }
 }
 
-impl From<i32> for TaskPriority {
-    fn from(value: i32) -> Self {
+impl TaskPriority {
+    /// 从整数值转换，超出范围时返回 None
+    pub fn from_value(value: i32) -> Option<Self> {
         match value {
-            0 => TaskPriority::Low,
-            1 => TaskPriority::Medium,
-            2 => TaskPriority::High,
-            _ => TaskPriority::Medium, // 默认为中等优先级
+            0 => Some(TaskPriority::Low),
+            1 => Some(TaskPriority::Medium),
+            2 => Some(TaskPriority::High),
+            _ => None,
         }
     }
 }
 
+// 读取数据库中的值时使用，超出范围的旧数据按中等优先级处理
+impl From<i32> for TaskPriority {
+    fn from(value: i32) -> Self {
+        TaskPriority::from_value(value).unwrap_or(TaskPriority::Medium)
+    }
+}
+
 impl Into<i32> for TaskPriority {
     fn into(self) -> i32 {
         self as i32
@@ -74,22 +157,298 @@ pub struct CreateTaskRequest {
     pub due_date: Option<DateTime<Utc>>,
     pub priority: TaskPriority,
     pub tags: Option<Vec<String>>, // 新增：创建任务时的标签
+    #[serde(default)]
+    pub recurrence: Option<RecurrenceRule>, // 重复规则
+    #[serde(default)]
+    pub parent_id: Option<i64>, // 父任务ID，创建子任务时使用
+    #[serde(default)]
+    pub project_id: Option<i64>, // 所属项目ID，子任务始终跟随父任务所在的项目
+    #[serde(default)]
+    pub is_all_day: bool, // 全天任务，截止时间只取日期部分（按 UTC 读取）
+}
+
+/// 可清除字段的更新方式
+///
+/// 请求中没有该字段时保持不变，为 null 时清除，有值时设置为新值。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Patch<T> {
+    #[default]
+    Unchanged,
+    Clear,
+    Set(T),
+}
+
+impl<T> Patch<T> {
+    pub fn is_unchanged(&self) -> bool {
+        matches!(self, Patch::Unchanged)
+    }
+
+    /// 要设置的新值，保持不变或清除时为 None
+    pub fn as_set(&self) -> Option<&T> {
+        match self {
+            Patch::Set(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// 应用到当前值，返回更新后的值
+    pub fn apply(self, current: Option<T>) -> Option<T> {
+        match self {
+            Patch::Unchanged => current,
+            Patch::Clear => None,
+            Patch::Set(value) => Some(value),
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Patch<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        // 保持不变的字段通过 skip_serializing_if 省略，这里按 null 处理
+        match self {
+            Patch::Set(value) => value.serialize(serializer),
+            Patch::Unchanged | Patch::Clear => serializer.serialize_none(),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Patch<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // 字段缺失时由 #[serde(default)] 得到 Unchanged，这里只会遇到 null 或具体值
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            Some(value) => Patch::Set(value),
+            None => Patch::Clear,
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateTaskRequest {
     pub id: i64,
     pub title: Option<String>,
-    pub description: Option<String>,
-    pub due_date: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub description: Patch<String>, // 清除时描述置为空
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub due_date: Patch<DateTime<Utc>>,
     pub priority: Option<TaskPriority>,
     pub is_completed: Option<bool>,
-    pub tags: Option<Vec<String>>, // 更新任务时的标签
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub tags: Patch<Vec<String>>, // 清除或设置为空列表时移除所有标签
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub recurrence: Patch<RecurrenceRule>, // 清除时取消重复
+    #[serde(default)]
+    pub is_all_day: Option<bool>, // 切换全天任务时未提供截止时间，则按用户时区换算现有的截止时间
+}
+
+/// 任务筛选条件，所有条件之间为“且”的关系，未设置的条件不参与筛选
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskFilter {
+    // 标签条件包含子标签，如筛选 "work" 时带有 "work/clientA" 的任务同样匹配
+    pub tags_any: Vec<String>,  // 包含任意一个标签
+    pub tags_all: Vec<String>,  // 包含全部标签
+    pub tags_none: Vec<String>, // 不包含这些标签
+    pub priorities: Vec<TaskPriority>,
+    pub project_ids: Vec<i64>, // 属于任意一个项目
+    pub due_before: Option<DateTime<Utc>>,
+    pub due_after: Option<DateTime<Utc>>,
+    pub due_range: Option<RelativeDueRange>, // 相对时间范围，查询时按当前时间计算
+    pub is_completed: Option<bool>,
+    pub text: Option<String>, // 全文搜索关键词（语法同 search_tasks）
+    pub created_before: Option<DateTime<Utc>>,
+    pub created_after: Option<DateTime<Utc>>,
+}
+
+/// 相对截止时间范围，适合保存在智能列表中（如“本周到期”）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum RelativeDueRange {
+    #[serde(rename = "overdue")]
+    Overdue,   // 已过期
+    #[serde(rename = "today")]
+    Today,     // 今天
+    #[serde(rename = "tomorrow")]
+    Tomorrow,  // 明天
+    #[serde(rename = "this_week")]
+    ThisWeek,  // 本周（周一至周日）
+    #[serde(rename = "next_7_days")]
+    Next7Days, // 从现在起 7 天内
+    #[serde(rename = "this_month")]
+    ThisMonth, // 本月
+}
+
+/// 截止时间窗口，用于查询过期、即将到期和指定时间段内到期的未完成任务
+///
+/// 全天任务以截止日期在用户时区的零点作为到期时刻，在截止日期结束后才算过期。
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum DueWindow {
+    #[serde(rename = "overdue")]
+    Overdue, // 已过期
+    #[serde(rename = "due_within")]
+    DueWithin { minutes: i32 }, // 从现在起 N 分钟内到期（不含现在，含第 N 分钟）
+    #[serde(rename = "between")]
+    Between { start: DateTime<Utc>, end: DateTime<Utc> }, // 截止时间在 [start, end) 内
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum TaskSortField {
+    #[serde(rename = "sort_order")]
+    SortOrder,
+    #[serde(rename = "due_date")]
+    DueDate,
+    #[serde(rename = "priority")]
+    Priority,
+    #[serde(rename = "created_at")]
+    CreatedAt,
+    #[serde(rename = "title")]
+    Title,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TaskSortKey {
+    pub field: TaskSortField,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// 任务查询请求：筛选 + 排序 + 分页
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskQuery {
+    pub filter: TaskFilter,
+    pub sort: Vec<TaskSortKey>, // 为空时按 sort_order 排序
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaskQueryResult {
+    pub tasks: Vec<Task>,
+    pub total: i64, // 符合条件的任务总数（不受分页影响）
+}
+
+/// 智能列表：保存的任务查询
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartList {
+    pub id: Option<i64>,
+    pub name: String,
+    pub icon: String,
+    pub query: TaskQuery,
+    pub sort_order: i64,
+    pub created_at: DateTime<Utc>,
+    pub task_count: i64, // 当前符合条件的任务数量（每次读取时重新计算）
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSmartListRequest {
+    pub name: String,
+    pub icon: Option<String>,
+    pub query: TaskQuery,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateSmartListRequest {
+    pub id: i64,
+    pub name: Option<String>,
+    pub icon: Option<String>,
+    pub query: Option<TaskQuery>,
+    pub sort_order: Option<i64>,
+}
+
+/// 撤销/重做状态
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UndoStatus {
+    pub can_undo: bool,
+    pub can_redo: bool,
+    pub undo_description: Option<String>, // 下一次撤销的操作描述
+    pub redo_description: Option<String>, // 下一次重做的操作描述
+}
+
+/// 任务变更历史记录
+///
+/// `old_value` / `new_value` 为字段值的 JSON 表示，字段不存在时为空。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskHistoryEntry {
+    pub id: i64,
+    pub task_id: i64,
+    pub field: String, // 变更的字段：title、description、due_date、is_all_day、priority、is_completed、recurrence、tags
+    pub old_value: Option<serde_json::Value>,
+    pub new_value: Option<serde_json::Value>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// 数据一致性检查发现的一类问题
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsistencyIssue {
+    pub kind: String,        // 问题类型，如 "orphaned_task_tags"
+    pub description: String, // 问题说明
+    pub count: i64,          // 涉及的记录数
+}
+
+/// 数据一致性检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsistencyReport {
+    pub issues: Vec<ConsistencyIssue>, // 只包含发现问题的检查项，为空表示数据一致
+    pub repaired: bool,                // 是否已修复上述问题
+}
+
+/// 全文搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub task: Task,
+    pub title_highlight: String, // 高亮后的标题（命中部分以 <mark> 包裹）
+    pub snippet: String,         // 描述中命中位置附近的片段
+    pub rank: f64,               // 相关度（越小越相关）
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoveSubtaskRequest {
+    pub id: i64,
+    pub parent_id: Option<i64>, // 新的父任务ID，为空表示移动到顶层
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateTaskSortOrderRequest {
     pub task_orders: Vec<TaskOrderItem>,
+    #[serde(default)]
+    pub project_id: Option<i64>, // 指定时只调整该项目内任务的顺序
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateProjectRequest {
+    pub name: String,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateProjectRequest {
+    pub id: i64,
+    pub name: Option<String>,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub is_archived: Option<bool>,
+    pub sort_order: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoveTasksToProjectRequest {
+    pub task_ids: Vec<i64>,
+    pub project_id: Option<i64>, // 为空表示移回收集箱
+}
+
+/// 导出文件格式
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportData {
+    pub version: u32,
+    #[serde(default)]
+    pub projects: Vec<Project>,
+    pub tasks: Vec<Task>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -119,6 +478,12 @@ pub struct AppSettings {
     pub notification_time_before: i32, // 提前多少分钟通知（截止时间提醒）
     pub startup_behavior: StartupBehavior,
     pub theme: String, // 主题设置 ("light", "dark", "auto")
+    pub trash_retention_days: i32, // 回收站保留天数，超过后在启动时永久删除（0 表示不自动清理）
+    #[serde(default)]
+    pub app_lock_enabled: bool, // 是否设置了应用锁密码（只读，通过 set_app_lock_passphrase 修改）
+    pub auto_lock_minutes: i32, // 空闲多少分钟后自动锁定（0 表示不自动锁定）
+    #[serde(default)]
+    pub timezone: Option<String>, // IANA 时区名称（如 Asia/Shanghai），为空时跟随系统时区
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -147,6 +512,10 @@ impl Default for AppSettings {
             notification_time_before: 15, // 默认提前15分钟通知
             startup_behavior: StartupBehavior::Normal,
             theme: "auto".to_string(),
+            trash_retention_days: 30, // 默认保留30天
+            app_lock_enabled: false,
+            auto_lock_minutes: 0,
+            timezone: None,
         }
     }
 }
@@ -158,4 +527,89 @@ pub struct UpdateSettingsRequest {
     pub notification_time_before: Option<i32>,
     pub startup_behavior: Option<StartupBehavior>,
     pub theme: Option<String>,
-}
\ No newline at end of file
+    pub trash_retention_days: Option<i32>,
+    pub auto_lock_minutes: Option<i32>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub timezone: Patch<String>, // 清除时恢复跟随系统时区
+}
+
+/// 数据库加密方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EncryptionMode {
+    #[serde(rename = "none")]
+    None,       // 未加密
+    #[serde(rename = "stored_key")]
+    StoredKey,  // 自动生成的密钥，保存在本地
+    #[serde(rename = "passphrase")]
+    Passphrase, // 用户密码，启动后需要输入解锁
+}
+
+/// 自动生成的密钥的保存位置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum KeyStorage {
+    #[serde(rename = "keyring")]
+    Keyring, // 系统钥匙串
+    #[serde(rename = "file")]
+    File,    // 配置目录下的密钥文件（钥匙串不可用时使用）
+}
+
+/// 数据库加密状态
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EncryptionStatus {
+    pub mode: EncryptionMode,
+    pub is_unlocked: bool, // 数据库是否已解锁可用
+    pub key_storage: Option<KeyStorage>, // 仅在使用自动生成的密钥时有值
+}
+
+/// 应用锁状态
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppLockStatus {
+    pub is_enabled: bool, // 是否设置了应用锁密码
+    pub is_locked: bool,
+    pub auto_lock_minutes: i32,
+}
+
+/// 数据目录的来源
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DataDirSource {
+    #[serde(rename = "command_line")]
+    CommandLine, // 命令行参数 --data-dir
+    #[serde(rename = "environment")]
+    Environment, // 环境变量 MYTODO_DATA_DIR
+    #[serde(rename = "portable")]
+    Portable,    // 可执行文件旁有 portable 标记文件
+    #[serde(rename = "moved")]
+    Moved,       // 移动数据目录后记录的位置
+    #[serde(rename = "legacy")]
+    Legacy,      // 旧版本使用的 文档/TodoAppData
+    #[serde(rename = "platform")]
+    Platform,    // 平台数据目录
+}
+
+/// 当前使用的数据目录
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataDirectoryInfo {
+    pub path: String,
+    pub source: DataDirSource,
+    pub is_movable: bool, // 由命令行参数、环境变量或便携模式指定时不能移动
+}
+
+/// 配置：每个配置使用单独的数据库文件，例如分开保存个人和工作的任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: i64,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub is_active: bool, // 是否为当前使用的配置
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateProfileRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenameProfileRequest {
+    pub id: i64,
+    pub name: String,
+}
@@ -0,0 +1,222 @@
+//! 配置
+//!
+//! 每个配置使用数据目录下单独的数据库文件和单独的随机密钥：默认配置沿用原来的 `tasks.db`，
+//! 其他配置保存为 `profile-<ID>.db`。配置列表和当前使用的配置记录在数据目录下的 `profiles.json`，
+//! 没有该文件时只有一个默认配置。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::data_dir::DATABASE_FILE_NAME;
+use crate::encryption;
+use crate::error::AppError;
+use crate::models::{Profile, CreateProfileRequest, RenameProfileRequest};
+
+/// 配置列表文件名
+pub const PROFILES_FILE_NAME: &str = "profiles.json";
+/// 默认配置的ID，使用原来的数据库文件和密钥
+pub const DEFAULT_PROFILE_ID: i64 = 1;
+const DEFAULT_PROFILE_NAME: &str = "默认";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProfileEntry {
+    id: i64,
+    name: String,
+    created_at: DateTime<Utc>,
+}
+
+/// `profiles.json` 的内容
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfilesFile {
+    active_id: i64,
+    next_id: i64, // 删除的配置ID不再使用，避免新配置用到旧配置残留的文件或密钥
+    profiles: Vec<ProfileEntry>,
+}
+
+/// 数据目录下的配置列表
+#[derive(Debug)]
+pub struct ProfileStore {
+    dir: PathBuf,
+    file: ProfilesFile,
+}
+
+impl ProfileStore {
+    /// 读取数据目录下的配置列表，没有配置列表文件时只有默认配置
+    pub fn load(dir: &Path) -> Result<Self, AppError> {
+        let mut file = match fs::read_to_string(dir.join(PROFILES_FILE_NAME)) {
+            Ok(content) => serde_json::from_str::<ProfilesFile>(&content)
+                .map_err(|e| AppError::storage(format!("配置列表文件已损坏: {}", e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ProfilesFile {
+                active_id: DEFAULT_PROFILE_ID,
+                next_id: DEFAULT_PROFILE_ID + 1,
+                profiles: vec![ProfileEntry {
+                    id: DEFAULT_PROFILE_ID,
+                    name: DEFAULT_PROFILE_NAME.to_string(),
+                    created_at: Utc::now(),
+                }],
+            },
+            Err(e) => return Err(e.into()),
+        };
+
+        // 记录的当前配置已不存在时改用第一个配置
+        if !file.profiles.iter().any(|profile| profile.id == file.active_id) {
+            file.active_id = file.profiles.first()
+                .map(|profile| profile.id)
+                .ok_or_else(|| AppError::storage("配置列表文件已损坏: 没有任何配置"))?;
+        }
+
+        Ok(ProfileStore { dir: dir.to_path_buf(), file })
+    }
+
+    /// 所有配置，按创建顺序排列
+    pub fn list(&self) -> Vec<Profile> {
+        self.file.profiles.iter().map(|entry| self.to_profile(entry)).collect()
+    }
+
+    /// 当前使用的配置
+    pub fn active(&self) -> Profile {
+        let entry = self.file.profiles.iter()
+            .find(|profile| profile.id == self.file.active_id)
+            .expect("当前配置在加载时已确认存在");
+        self.to_profile(entry)
+    }
+
+    /// 配置的数据库文件路径
+    pub fn database_path(&self, id: i64) -> PathBuf {
+        self.dir.join(Self::database_file_name(id))
+    }
+
+    /// 配置的随机密钥名称
+    pub fn key_name(id: i64) -> String {
+        if id == DEFAULT_PROFILE_ID {
+            encryption::DEFAULT_KEY_NAME.to_string()
+        } else {
+            format!("{}-{}", encryption::DEFAULT_KEY_NAME, id)
+        }
+    }
+
+    /// 数据目录中属于配置的所有文件名（数据库文件和配置列表），移动数据目录时一并移动
+    pub fn file_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.file.profiles.iter()
+            .map(|profile| Self::database_file_name(profile.id))
+            .collect();
+        names.push(PROFILES_FILE_NAME.to_string());
+        names
+    }
+
+    /// 数据目录移动后改用新目录
+    pub fn set_dir(&mut self, dir: &Path) {
+        self.dir = dir.to_path_buf();
+    }
+
+    /// 新建配置，数据库文件在第一次切换到该配置时创建
+    pub fn create(&mut self, request: CreateProfileRequest) -> Result<Profile, AppError> {
+        request.validate()?;
+        let name = request.name.trim().to_string();
+        self.ensure_unique_name(&name, None)?;
+
+        let entry = ProfileEntry { id: self.file.next_id, name, created_at: Utc::now() };
+        self.file.next_id += 1;
+        self.file.profiles.push(entry.clone());
+        self.save()?;
+
+        Ok(self.to_profile(&entry))
+    }
+
+    /// 重命名配置
+    pub fn rename(&mut self, request: RenameProfileRequest) -> Result<Profile, AppError> {
+        request.validate()?;
+        let name = request.name.trim().to_string();
+        self.ensure_unique_name(&name, Some(request.id))?;
+
+        let entry = self.file.profiles.iter_mut()
+            .find(|profile| profile.id == request.id)
+            .ok_or_else(|| AppError::not_found("profile", request.id))?;
+        entry.name = name;
+        let entry = entry.clone();
+        self.save()?;
+
+        Ok(self.to_profile(&entry))
+    }
+
+    /// 删除配置及其数据库文件、迁移备份和保存的密钥，不能删除当前使用的配置
+    ///
+    /// 调用方应先通过 `Database::verify_removal` 确认该配置的密码。
+    pub fn delete(&mut self, id: i64) -> Result<(), AppError> {
+        self.find(id)?;
+        if id == self.file.active_id {
+            return Err(AppError::conflict("不能删除当前使用的配置，请先切换到其他配置"));
+        }
+
+        self.file.profiles.retain(|profile| profile.id != id);
+        self.save()?;
+
+        // 删除数据库文件以及同名开头的迁移备份（如 profile-2.db.v3.bak）
+        let file_name = Self::database_file_name(id);
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with(&file_name) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        encryption::delete_stored_key(&Self::key_name(id))?;
+
+        Ok(())
+    }
+
+    /// 记录当前使用的配置，调用方应先成功打开该配置的数据库
+    pub fn set_active(&mut self, id: i64) -> Result<Profile, AppError> {
+        self.find(id)?;
+        self.file.active_id = id;
+        self.save()?;
+
+        Ok(self.active())
+    }
+
+    /// 获取指定的配置
+    pub fn find(&self, id: i64) -> Result<Profile, AppError> {
+        self.file.profiles.iter()
+            .find(|profile| profile.id == id)
+            .map(|entry| self.to_profile(entry))
+            .ok_or_else(|| AppError::not_found("profile", id))
+    }
+
+    fn database_file_name(id: i64) -> String {
+        if id == DEFAULT_PROFILE_ID {
+            DATABASE_FILE_NAME.to_string()
+        } else {
+            format!("profile-{}.db", id)
+        }
+    }
+
+    fn to_profile(&self, entry: &ProfileEntry) -> Profile {
+        Profile {
+            id: entry.id,
+            name: entry.name.clone(),
+            created_at: entry.created_at,
+            is_active: entry.id == self.file.active_id,
+        }
+    }
+
+    /// 配置名称不区分大小写不能重复
+    fn ensure_unique_name(&self, name: &str, except_id: Option<i64>) -> Result<(), AppError> {
+        let duplicate = self.file.profiles.iter()
+            .any(|profile| Some(profile.id) != except_id && profile.name.to_lowercase() == name.to_lowercase());
+        if duplicate {
+            return Err(AppError::conflict(format!("配置名称已存在: {}", name)));
+        }
+        Ok(())
+    }
+
+    /// 先写入临时文件再替换，避免写到一半时留下损坏的配置列表
+    fn save(&self) -> Result<(), AppError> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(PROFILES_FILE_NAME);
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(&self.file)?)?;
+        fs::rename(&temp_path, &path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use crate::models::{RecurrenceFrequency, RecurrenceRule};
+use crate::timezone::UserTimeZone;
+
+/// 计算重复任务的下一次截止时间
+///
+/// `from` 为当前这一次的截止时间（没有截止时间时传入完成时间）。
+/// 日期推算在用户时区 `zone` 中进行，以保证“每周一 9:00”这类规则不会因 UTC 换日而错位；
+/// 全天任务的截止时间是 UTC 零点，传入 `UserTimeZone::utc()`。
+/// 返回 `None` 表示重复已经结束（超过 `until` 或次数用尽）。
+pub fn next_occurrence(rule: &RecurrenceRule, from: DateTime<Utc>, zone: &UserTimeZone) -> Option<DateTime<Utc>> {
+    // count 表示剩余次数（含当前这一次），只剩一次时不再生成
+    if let Some(count) = rule.count {
+        if count <= 1 {
+            return None;
+        }
+    }
+
+    let interval = rule.interval.max(1);
+    let local = zone.to_local(from);
+
+    let next_local = match rule.frequency {
+        RecurrenceFrequency::Daily => {
+            if rule.by_weekday.is_empty() {
+                local + Duration::days(interval as i64)
+            } else {
+                next_matching_day(local, interval as i64, &rule.by_weekday)?
+            }
+        }
+        RecurrenceFrequency::Weekly => {
+            if rule.by_weekday.is_empty() {
+                local + Duration::weeks(interval as i64)
+            } else {
+                next_weekly_by_weekday(local, interval as i64, &rule.by_weekday)?
+            }
+        }
+        RecurrenceFrequency::Monthly => add_months(local, interval)?,
+        RecurrenceFrequency::Yearly => add_months(local, interval.checked_mul(12)?)?,
+    };
+
+    let next = zone.to_utc(next_local)?;
+
+    if let Some(until) = rule.until {
+        if next > until {
+            return None;
+        }
+    }
+
+    Some(next)
+}
+
+/// 生成下一次任务使用的重复规则（剩余次数减一）
+pub fn advance_rule(rule: &RecurrenceRule) -> RecurrenceRule {
+    let mut next_rule = rule.clone();
+    next_rule.count = rule.count.map(|count| count.saturating_sub(1));
+    next_rule
+}
+
+/// 按天步进，找到第一个星期几匹配的日期
+fn next_matching_day(from: NaiveDateTime, step_days: i64, weekdays: &[u8]) -> Option<NaiveDateTime> {
+    // 步进 7 次必然覆盖一周中的每一天
+    for step in 1..=7 {
+        let candidate = from + Duration::days(step * step_days);
+        if weekdays.contains(&(candidate.weekday().num_days_from_sunday() as u8)) {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
+/// 每 N 周的指定星期几：先在本周剩余日期中查找，再跳到下一个有效周
+fn next_weekly_by_weekday(from: NaiveDateTime, interval_weeks: i64, weekdays: &[u8]) -> Option<NaiveDateTime> {
+    let current = from.weekday().num_days_from_sunday() as i64;
+
+    // 本周内后续的日期（周日为一周的第一天）
+    for offset in 1..(7 - current) {
+        let candidate = from + Duration::days(offset);
+        if weekdays.contains(&(candidate.weekday().num_days_from_sunday() as u8)) {
+            return Some(candidate);
+        }
+    }
+
+    // 下一个有效周的周日，再向后找第一个匹配的日期
+    let next_week_start = from - Duration::days(current) + Duration::weeks(interval_weeks);
+    (0..7)
+        .map(|offset| next_week_start + Duration::days(offset))
+        .find(|candidate| weekdays.contains(&(candidate.weekday().num_days_from_sunday() as u8)))
+}
+
+/// 增加若干个月，目标月份没有对应日期时取该月最后一天（如 1 月 31 日 -> 2 月 28 日）
+fn add_months(from: NaiveDateTime, months: u32) -> Option<NaiveDateTime> {
+    let total_months = from.year() as i64 * 12 + from.month0() as i64 + months as i64;
+    let year = (total_months / 12) as i32;
+    let month = (total_months % 12) as u32 + 1;
+
+    let mut day = from.day();
+    loop {
+        if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
+            return Some(date.and_time(from.time()));
+        }
+        if day <= 28 {
+            return None;
+        }
+        day -= 1;
+    }
+}
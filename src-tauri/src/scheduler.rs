@@ -0,0 +1,143 @@
+use std::time::Duration;
+use chrono::Utc;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+use crate::AppState;
+use crate::models::Task;
+
+/// 两次检查之间的最长等待时间，用于感知任务和设置的变更
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+/// 最短等待时间，避免异常情况下空转
+const MIN_SLEEP: Duration = Duration::from_secs(1);
+/// 一次需要提醒的任务超过该数量时合并为一条通知
+const MAX_INDIVIDUAL_NOTIFICATIONS: usize = 3;
+/// 检查是否空闲超时的间隔
+const AUTO_LOCK_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// 启动后台提醒调度器
+///
+/// 调度器运行在后端，窗口隐藏到托盘时依然能够按时提醒。
+/// 每个任务的每个截止时间只提醒一次，提醒记录保存在数据库中，重启后不会重复提醒。
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let wait = match check_and_notify(&app) {
+                Ok(wait) => wait,
+                Err(e) => {
+                    log::error!("提醒调度失败: {}", e);
+                    MAX_SLEEP
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    });
+}
+
+/// 启动空闲自动锁定检查
+///
+/// 命令在空闲超时后会直接返回锁定错误，这里负责及时通知前端切换到锁定界面。
+pub fn start_auto_lock(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut was_locked = true;
+        loop {
+            let is_locked = {
+                let state = app.state::<AppState>();
+                let locked = state.app_lock.lock().map(|mut app_lock| app_lock.is_locked());
+                locked.unwrap_or(true)
+            };
+
+            if is_locked && !was_locked {
+                if let Err(e) = app.emit("app-locked", ()) {
+                    log::error!("发送锁定事件失败: {}", e);
+                }
+            }
+            was_locked = is_locked;
+
+            tokio::time::sleep(AUTO_LOCK_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+/// 发送到期提醒，并返回距离下一次检查的等待时间
+fn check_and_notify(app: &AppHandle) -> Result<Duration, Box<dyn std::error::Error>> {
+    let state = app.state::<AppState>();
+
+    // 发送系统通知可能阻塞较长时间，只在读取待提醒任务时持有数据库锁
+    let (db_path, minutes_before, tasks) = {
+        let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
+
+        // 使用密码加密的数据库在解锁前无法读取
+        if !db.is_unlocked() {
+            return Ok(MAX_SLEEP);
+        }
+
+        let settings = db.get_app_settings()?;
+        if !settings.notifications_enabled {
+            return Ok(MAX_SLEEP);
+        }
+
+        let minutes_before = settings.notification_time_before;
+        (db.path().to_path_buf(), minutes_before, db.get_pending_notifications(minutes_before)?)
+    };
+
+    // 应用锁定时通知中不显示任务标题
+    let is_locked = state.app_lock.lock().map(|mut app_lock| app_lock.is_locked()).unwrap_or(true);
+
+    if !tasks.is_empty() && (is_locked || tasks.len() > MAX_INDIVIDUAL_NOTIFICATIONS) {
+        show_notification(app, "任务提醒", &format!("有 {} 个任务即将到期或已过期，请及时处理。", tasks.len()));
+    } else {
+        for task in &tasks {
+            let (title, body) = notification_text(task);
+            show_notification(app, title, &body);
+        }
+    }
+
+    let db = state.db.lock().map_err(|e| format!("数据库锁定失败: {}", e))?;
+
+    // 发送通知期间可能切换了配置，提醒记录只写回读取任务的数据库
+    if db.path() != db_path.as_path() || !db.is_unlocked() {
+        return Ok(MIN_SLEEP);
+    }
+
+    for task in &tasks {
+        if let (Some(id), Some(due_date)) = (task.id, task.due_date) {
+            db.mark_task_notified(id, due_date)?;
+        }
+    }
+
+    // 睡眠到下一个提醒时间，但不超过 MAX_SLEEP，以便及时感知新建或修改的任务
+    let wait = match db.get_next_notification_time(minutes_before)? {
+        Some(next_time) => (next_time - Utc::now())
+            .to_std()
+            .unwrap_or(MIN_SLEEP)
+            .clamp(MIN_SLEEP, MAX_SLEEP),
+        None => MAX_SLEEP,
+    };
+
+    Ok(wait)
+}
+
+/// 根据任务是否已过期生成通知内容
+fn notification_text(task: &Task) -> (&'static str, String) {
+    let now = Utc::now();
+    match task.due_date {
+        Some(due_date) if due_date > now => {
+            let minutes_left = (due_date - now).num_minutes();
+            ("任务即将到期！", format!("任务\"{}\"将在 {} 分钟后到期。", task.title, minutes_left))
+        }
+        _ => ("任务已过期！", format!("任务\"{}\"已过期，请及时处理。", task.title)),
+    }
+}
+
+fn show_notification(app: &AppHandle, title: &str, body: &str) {
+    let result = app.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .icon("icons/icon.ico")
+        .show();
+
+    if let Err(e) = result {
+        log::error!("发送通知失败: {}", e);
+    }
+}
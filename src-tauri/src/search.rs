@@ -0,0 +1,128 @@
+//! 全文检索辅助函数
+//!
+//! FTS5 自带的 unicode61 分词器按空白和标点切分，连续的中文会被当成一个词，
+//! 无法搜索其中的片段。这里在写入索引和构造查询时，在每个中日韩字符两侧插入
+//! 不可见分隔符（U+2063），使每个汉字成为独立的词；中文关键词则作为相邻汉字组成的短语匹配。
+//! 读取高亮片段时再去掉分隔符，还原原始文本。
+//!
+//! 高亮结果以 HTML 返回给前端，任务文本中的 HTML 特殊字符会被转义，只有 `<mark>` 是标记。
+//! FTS5 的高亮函数先用私用区字符标记命中位置，转义文本之后再替换为 `<mark>`。
+
+/// 中文分词使用的分隔符，unicode61 分词器会把它当作词边界
+const CJK_SEPARATOR: char = '\u{2063}';
+
+/// FTS5 高亮函数使用的命中标记，写入索引的文本中不会出现
+pub const MATCH_START: char = '\u{E000}';
+pub const MATCH_END: char = '\u{E001}';
+
+/// 高亮标记
+const HIGHLIGHT_START: &str = "<mark>";
+const HIGHLIGHT_END: &str = "</mark>";
+
+/// 判断是否为中日韩字符
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF     // 平假名、片假名
+        | 0x3400..=0x4DBF   // 扩展 A
+        | 0x4E00..=0x9FFF   // 基本汉字
+        | 0xAC00..=0xD7AF   // 韩文音节
+        | 0xF900..=0xFAFF   // 兼容汉字
+        | 0x20000..=0x2EBEF // 扩展 B-F
+    )
+}
+
+/// 为写入索引的文本分词：在每个中日韩字符两侧插入分隔符，并去掉与命中标记相同的字符
+pub fn segment(text: &str) -> String {
+    let mut result = String::with_capacity(text.len() * 2);
+    for c in text.chars() {
+        if c == MATCH_START || c == MATCH_END {
+            continue;
+        }
+        if is_cjk(c) {
+            result.push(CJK_SEPARATOR);
+            result.push(c);
+            result.push(CJK_SEPARATOR);
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// 去掉分词时插入的分隔符，把高亮片段转换为 HTML
+///
+/// 文本中的 HTML 特殊字符会被转义，命中标记替换为 `<mark>`。
+/// 中文短语的每个字会被分别高亮，还原后把相邻的高亮标记合并在一起。
+pub fn unsegment(text: &str) -> String {
+    let mut html = String::with_capacity(text.len() + 16);
+    for c in text.chars() {
+        match c {
+            CJK_SEPARATOR => {}
+            MATCH_START => html.push_str(HIGHLIGHT_START),
+            MATCH_END => html.push_str(HIGHLIGHT_END),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+    html.replace(&format!("{}{}", HIGHLIGHT_END, HIGHLIGHT_START), "")
+}
+
+/// 将用户输入转换为 FTS5 查询语句
+///
+/// 支持的语法：
+/// - 多个关键词用空格分隔，需要全部匹配
+/// - `"双引号"` 包裹的内容作为短语匹配
+/// - 以 `*` 结尾的关键词作为前缀匹配，如 `rep*`
+///
+/// 所有关键词都会被转义为 FTS5 字符串，用户输入中的特殊字符不会破坏查询语法。
+/// 输入中没有有效关键词时返回 `None`。
+pub fn build_match_query(input: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let (text, is_prefix) = if c == '"' {
+            // 短语：读取到下一个双引号为止
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            let is_prefix = chars.peek() == Some(&'*');
+            if is_prefix {
+                chars.next();
+            }
+            (phrase, is_prefix)
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            let is_prefix = word.ends_with('*');
+            (word.trim_end_matches('*').to_string(), is_prefix)
+        };
+
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        let quoted = format!("\"{}\"", segment(text.trim()).replace('"', "\"\""));
+        terms.push(if is_prefix { format!("{}*", quoted) } else { quoted });
+    }
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
@@ -0,0 +1,79 @@
+//! 用户时区
+//!
+//! “今天”、过期、即将到期和重复任务等按日期计算的逻辑使用设置中的时区，未设置时跟随系统时区。
+//! 全天任务的截止时间只有日期，保存为该日期的 UTC 零点，读取时按日期解释，不随时区变化。
+
+use chrono::{DateTime, Duration, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// 计算日期边界使用的时区
+#[derive(Debug, Clone, Copy)]
+pub enum UserTimeZone {
+    System,    // 跟随系统时区
+    Named(Tz), // 设置中指定的 IANA 时区，如 Asia/Shanghai
+}
+
+impl UserTimeZone {
+    /// 根据设置中的时区名称创建，未设置或无法识别时使用系统时区
+    pub fn from_setting(name: Option<&str>) -> Self {
+        name.and_then(parse).map(UserTimeZone::Named).unwrap_or(UserTimeZone::System)
+    }
+
+    /// 全天任务的日期按 UTC 计算
+    pub fn utc() -> Self {
+        UserTimeZone::Named(Tz::UTC)
+    }
+
+    /// 当前的本地日期
+    pub fn today(&self) -> NaiveDate {
+        self.date_of(Utc::now())
+    }
+
+    /// 某一时刻在该时区的日期
+    pub fn date_of(&self, instant: DateTime<Utc>) -> NaiveDate {
+        self.to_local(instant).date()
+    }
+
+    /// 某一时刻在该时区的本地时间
+    pub fn to_local(&self, instant: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            UserTimeZone::System => instant.with_timezone(&Local).naive_local(),
+            UserTimeZone::Named(tz) => instant.with_timezone(tz).naive_local(),
+        }
+    }
+
+    /// 本地时间转换回 UTC，夏令时造成的重复时刻取较早的一个，跳过的时刻顺延一小时
+    pub fn to_utc(&self, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            UserTimeZone::System => local_to_utc(&Local, local),
+            UserTimeZone::Named(tz) => local_to_utc(tz, local),
+        }
+    }
+
+    /// 一天开始的时刻（本地零点）
+    pub fn day_start(&self, date: NaiveDate) -> DateTime<Utc> {
+        let midnight = date.and_time(NaiveTime::MIN);
+        self.to_utc(midnight).unwrap_or_else(|| midnight.and_utc())
+    }
+}
+
+/// 解析 IANA 时区名称
+pub fn parse(name: &str) -> Option<Tz> {
+    name.parse().ok()
+}
+
+/// 全天任务保存的截止时间：该日期的 UTC 零点
+pub fn all_day_due_date(date: NaiveDate) -> DateTime<Utc> {
+    date.and_time(NaiveTime::MIN).and_utc()
+}
+
+fn local_to_utc<Z: TimeZone>(tz: &Z, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(dt) => Some(dt.with_timezone(&Utc)),
+        LocalResult::Ambiguous(earliest, _) => Some(earliest.with_timezone(&Utc)),
+        LocalResult::None => tz
+            .from_local_datetime(&(local + Duration::hours(1)))
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc)),
+    }
+}
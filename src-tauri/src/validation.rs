@@ -0,0 +1,248 @@
+//! 请求参数校验
+//!
+//! 在写入数据库之前检查前端传入的请求，不合法时返回带字段名的 `AppError::Validation`，
+//! 避免把空标题、错误的颜色值等无效数据保存下来。
+
+use crate::error::AppError;
+use crate::timezone;
+use crate::models::{CreateTaskRequest, UpdateTaskRequest, CreateTagRequest, UpdateTagRequest, UpdateSettingsRequest, RecurrenceRule, RecurrenceFrequency, DueWindow, CreateProfileRequest, RenameProfileRequest};
+
+/// 任务标题最大长度（字符数）
+pub const MAX_TITLE_LENGTH: usize = 200;
+/// 任务描述最大长度（字符数）
+pub const MAX_DESCRIPTION_LENGTH: usize = 10_000;
+/// 标签名称每一级的最大长度（字符数）
+pub const MAX_TAG_NAME_LENGTH: usize = 50;
+/// 配置名称最大长度（字符数）
+pub const MAX_PROFILE_NAME_LENGTH: usize = 50;
+/// 提前通知时间范围（分钟），最多提前一天
+pub const NOTIFICATION_MINUTES_RANGE: std::ops::RangeInclusive<i32> = 1..=1440;
+/// 回收站保留天数范围，0 表示永久保留
+pub const TRASH_RETENTION_DAYS_RANGE: std::ops::RangeInclusive<i32> = 0..=3650;
+/// 自动锁定时间范围（分钟），0 表示不自动锁定
+pub const AUTO_LOCK_MINUTES_RANGE: std::ops::RangeInclusive<i32> = 0..=1440;
+/// 支持的主题
+pub const THEMES: &[&str] = &["light", "dark", "auto"];
+
+impl CreateTaskRequest {
+    pub fn validate(&self) -> Result<(), AppError> {
+        validate_title(&self.title)?;
+        if let Some(ref description) = self.description {
+            validate_description(description)?;
+        }
+        if let Some(ref tags) = self.tags {
+            validate_tag_names(tags)?;
+        }
+        if let Some(ref recurrence) = self.recurrence {
+            validate_recurrence(recurrence)?;
+        }
+        Ok(())
+    }
+}
+
+impl UpdateTaskRequest {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(ref title) = self.title {
+            validate_title(title)?;
+        }
+        if let Some(description) = self.description.as_set() {
+            validate_description(description)?;
+        }
+        if let Some(tags) = self.tags.as_set() {
+            validate_tag_names(tags)?;
+        }
+        if let Some(recurrence) = self.recurrence.as_set() {
+            validate_recurrence(recurrence)?;
+        }
+        Ok(())
+    }
+}
+
+impl CreateTagRequest {
+    pub fn validate(&self) -> Result<(), AppError> {
+        validate_tag_name("name", &self.name)?;
+        validate_color("color", &self.color)
+    }
+}
+
+impl UpdateTagRequest {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(ref name) = self.name {
+            validate_tag_name("name", name)?;
+        }
+        if let Some(ref color) = self.color {
+            validate_color("color", color)?;
+        }
+        Ok(())
+    }
+}
+
+impl CreateProfileRequest {
+    pub fn validate(&self) -> Result<(), AppError> {
+        validate_profile_name(&self.name)
+    }
+}
+
+impl RenameProfileRequest {
+    pub fn validate(&self) -> Result<(), AppError> {
+        validate_profile_name(&self.name)
+    }
+}
+
+impl UpdateSettingsRequest {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(minutes) = self.notification_time_before {
+            validate_range("notification_time_before", minutes, NOTIFICATION_MINUTES_RANGE, "提前通知时间")?;
+        }
+        if let Some(days) = self.trash_retention_days {
+            validate_range("trash_retention_days", days, TRASH_RETENTION_DAYS_RANGE, "回收站保留天数")?;
+        }
+        if let Some(minutes) = self.auto_lock_minutes {
+            validate_range("auto_lock_minutes", minutes, AUTO_LOCK_MINUTES_RANGE, "自动锁定时间")?;
+        }
+        if let Some(ref theme) = self.theme {
+            if !THEMES.contains(&theme.as_str()) {
+                return Err(AppError::validation(
+                    Some("theme"),
+                    format!("未知的主题: {}，可选值为 {}", theme, THEMES.join("、")),
+                ));
+            }
+        }
+        if let Some(name) = self.timezone.as_set() {
+            if timezone::parse(name).is_none() {
+                return Err(AppError::validation(
+                    Some("timezone"),
+                    format!("未知的时区: {}，应为 IANA 时区名称，如 Asia/Shanghai", name),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DueWindow {
+    pub fn validate(&self) -> Result<(), AppError> {
+        match *self {
+            DueWindow::DueWithin { minutes } if minutes < 0 => {
+                Err(AppError::validation(Some("minutes"), "到期时间范围不能为负数"))
+            }
+            DueWindow::Between { start, end } if end < start => {
+                Err(AppError::validation(Some("end"), "结束时间不能早于开始时间"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn validate_title(title: &str) -> Result<(), AppError> {
+    if title.trim().is_empty() {
+        return Err(AppError::validation(Some("title"), "任务标题不能为空"));
+    }
+    if title.chars().count() > MAX_TITLE_LENGTH {
+        return Err(AppError::validation(
+            Some("title"),
+            format!("任务标题不能超过 {} 个字符", MAX_TITLE_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_description(description: &str) -> Result<(), AppError> {
+    if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+        return Err(AppError::validation(
+            Some("description"),
+            format!("任务描述不能超过 {} 个字符", MAX_DESCRIPTION_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_tag_names(tags: &[String]) -> Result<(), AppError> {
+    tags.iter().try_for_each(|tag| validate_tag_name("tags", tag))
+}
+
+/// 标签名称可以是 `work/clientA/billing` 形式的路径，每一级都不能为空
+fn validate_tag_name(field: &str, name: &str) -> Result<(), AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::validation(Some(field), "标签名称不能为空"));
+    }
+    for segment in name.split('/') {
+        if segment.trim().is_empty() {
+            return Err(AppError::validation(
+                Some(field),
+                format!("标签路径中的每一级名称都不能为空: {}", name),
+            ));
+        }
+        if segment.chars().count() > MAX_TAG_NAME_LENGTH {
+            return Err(AppError::validation(
+                Some(field),
+                format!("标签名称不能超过 {} 个字符: {}", MAX_TAG_NAME_LENGTH, segment),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_profile_name(name: &str) -> Result<(), AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::validation(Some("name"), "配置名称不能为空"));
+    }
+    if name.chars().count() > MAX_PROFILE_NAME_LENGTH {
+        return Err(AppError::validation(
+            Some("name"),
+            format!("配置名称不能超过 {} 个字符", MAX_PROFILE_NAME_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+/// 颜色必须是 `#RGB` 或 `#RRGGBB` 格式的十六进制颜色
+fn validate_color(field: &str, color: &str) -> Result<(), AppError> {
+    let is_valid = color
+        .strip_prefix('#')
+        .map(|hex| (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .unwrap_or(false);
+
+    if !is_valid {
+        return Err(AppError::validation(
+            Some(field),
+            format!("颜色格式不正确: {}，应为 #RRGGBB 格式", color),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_recurrence(rule: &RecurrenceRule) -> Result<(), AppError> {
+    if rule.interval == 0 {
+        return Err(AppError::validation(Some("recurrence.interval"), "重复间隔必须大于 0"));
+    }
+    if rule.by_weekday.iter().any(|&weekday| weekday > 6) {
+        return Err(AppError::validation(Some("recurrence.by_weekday"), "星期取值应为 0（周日）到 6（周六）"));
+    }
+    // 间隔为 7 的倍数时每次都落在同一个星期几，指定的其他星期几永远不会出现
+    if rule.frequency == RecurrenceFrequency::Daily && !rule.by_weekday.is_empty() && rule.interval % 7 == 0 {
+        return Err(AppError::validation(
+            Some("recurrence.interval"),
+            "按天重复并指定星期几时，间隔不能是 7 的倍数，请改用按周重复",
+        ));
+    }
+    if rule.count == Some(0) {
+        return Err(AppError::validation(Some("recurrence.count"), "重复次数必须大于 0"));
+    }
+    Ok(())
+}
+
+fn validate_range(
+    field: &str,
+    value: i32,
+    range: std::ops::RangeInclusive<i32>,
+    label: &str,
+) -> Result<(), AppError> {
+    if !range.contains(&value) {
+        return Err(AppError::validation(
+            Some(field),
+            format!("{}应在 {} 到 {} 之间", label, range.start(), range.end()),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+//! 集成测试共用的辅助函数
+//!
+//! 测试使用内存数据库或临时目录中的未加密数据库，不读写用户文档目录和系统钥匙串。
+
+// 每个测试文件只用到其中一部分
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use app_lib::database::Database;
+use app_lib::error::{AppError, ErrorCode};
+use app_lib::models::{CreateTaskRequest, Patch, Task, TaskPriority, UpdateSettingsRequest, UpdateTaskRequest};
+use chrono::{DateTime, Utc};
+
+/// 打开一个空的内存数据库
+pub fn open_db() -> Database {
+    Database::open_in_memory().expect("无法打开内存数据库")
+}
+
+/// 只有标题的创建任务请求
+pub fn task_request(title: &str) -> CreateTaskRequest {
+    CreateTaskRequest {
+        title: title.to_string(),
+        description: None,
+        due_date: None,
+        priority: TaskPriority::Medium,
+        tags: None,
+        recurrence: None,
+        parent_id: None,
+        project_id: None,
+        is_all_day: false,
+    }
+}
+
+/// 创建任务并返回任务ID
+pub fn add_task(db: &Database, request: CreateTaskRequest) -> i64 {
+    db.create_task(request).expect("创建任务失败").id.expect("新任务没有ID")
+}
+
+/// 不修改任何字段的更新任务请求
+pub fn update_request(id: i64) -> UpdateTaskRequest {
+    UpdateTaskRequest {
+        id,
+        title: None,
+        description: Patch::Unchanged,
+        due_date: Patch::Unchanged,
+        priority: None,
+        is_completed: None,
+        tags: Patch::Unchanged,
+        recurrence: Patch::Unchanged,
+        is_all_day: None,
+    }
+}
+
+/// 不修改任何设置的更新设置请求
+pub fn settings_request() -> UpdateSettingsRequest {
+    UpdateSettingsRequest {
+        close_behavior: None,
+        notifications_enabled: None,
+        notification_time_before: None,
+        startup_behavior: None,
+        theme: None,
+        trash_retention_days: None,
+        auto_lock_minutes: None,
+        timezone: Patch::Unchanged,
+    }
+}
+
+/// 设置用户时区，使日期相关的结果不受运行测试的机器影响
+pub fn set_timezone(db: &Database, name: &str) {
+    db.update_app_settings(UpdateSettingsRequest { timezone: Patch::Set(name.to_string()), ..settings_request() })
+        .expect("设置时区失败");
+}
+
+/// 任务标题列表，保持原有顺序
+pub fn titles(tasks: &[Task]) -> Vec<&str> {
+    tasks.iter().map(|task| task.title.as_str()).collect()
+}
+
+/// 解析 RFC 3339 格式的时间
+pub fn at(rfc3339: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(rfc3339).expect("时间格式错误").with_timezone(&Utc)
+}
+
+/// 断言操作失败且错误码符合预期
+pub fn assert_error<T: std::fmt::Debug>(result: Result<T, AppError>, code: ErrorCode) {
+    match result {
+        Ok(value) => panic!("预期错误 {:?}，实际成功: {:?}", code, value),
+        Err(e) => assert_eq!(e.code(), code, "错误信息: {}", e),
+    }
+}
+
+/// 测试结束时自动删除的临时目录
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("mytodo-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("无法创建临时目录");
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 目录中的数据库文件路径
+    pub fn db_path(&self) -> PathBuf {
+        self.path.join("tasks.db")
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,59 @@
+//! 移动数据库文件
+
+mod common;
+
+use app_lib::database::Database;
+use app_lib::error::ErrorCode;
+use common::*;
+
+#[test]
+fn relocate_copies_database_and_switches_to_new_file() {
+    let dir = TempDir::new("relocate");
+    let mut db = Database::open_unencrypted(&dir.db_path()).unwrap();
+    add_task(&db, task_request("搬家前"));
+
+    let new_path = dir.path().join("moved").join("tasks.db");
+    db.relocate(&new_path, None).unwrap();
+    assert_eq!(db.path(), new_path);
+    assert!(!dir.path().join("moved").join("tasks.db.moving").exists());
+
+    // 之后的修改写入新文件，原文件保持原样由调用方删除
+    add_task(&db, task_request("搬家后"));
+    drop(db);
+    let moved = Database::open_unencrypted(&new_path).unwrap();
+    assert_eq!(titles(&moved.get_all_tasks().unwrap()), ["搬家前", "搬家后"]);
+    assert_eq!(moved.search_tasks("搬家后", 10).unwrap().len(), 1);
+    let original = Database::open_unencrypted(&dir.db_path()).unwrap();
+    assert_eq!(titles(&original.get_all_tasks().unwrap()), ["搬家前"]);
+}
+
+#[test]
+fn relocate_rejects_existing_target_and_in_memory_database() {
+    let dir = TempDir::new("relocate-conflict");
+    let mut db = Database::open_unencrypted(&dir.db_path()).unwrap();
+    let other = dir.path().join("other.db");
+    std::fs::write(&other, b"").unwrap();
+
+    assert_error(db.relocate(&other, None), ErrorCode::Conflict);
+    assert_eq!(db.path(), dir.db_path());
+
+    let mut memory = open_db();
+    assert_error(memory.relocate(&dir.path().join("memory.db"), None), ErrorCode::Conflict);
+}
+
+#[test]
+fn relocating_passphrase_database_needs_correct_passphrase() {
+    let dir = TempDir::new("relocate-passphrase");
+    let mut db = Database::open_unencrypted(&dir.db_path()).unwrap();
+    add_task(&db, task_request("机密"));
+    db.encrypt(Some("secret".to_string())).unwrap();
+    let new_path = dir.path().join("moved.db");
+
+    assert_error(db.relocate(&new_path, None), ErrorCode::Validation);
+    assert_error(db.relocate(&new_path, Some("wrong")), ErrorCode::Validation);
+    assert!(!new_path.exists());
+    assert_eq!(db.path(), dir.db_path());
+
+    db.relocate(&new_path, Some("secret")).unwrap();
+    assert_eq!(titles(&db.get_all_tasks().unwrap()), ["机密"]);
+}
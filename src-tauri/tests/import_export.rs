@@ -0,0 +1,176 @@
+//! 导出和导入
+
+mod common;
+
+use app_lib::database::Database;
+use app_lib::error::ErrorCode;
+use app_lib::models::{
+    CreateProjectRequest, CreateTaskRequest, ExportData, RecurrenceFrequency, RecurrenceRule, Task, TaskPriority,
+    UpdateProjectRequest,
+};
+use common::*;
+
+/// 生成包含项目、子任务、标签、重复规则、全天和已完成任务的数据
+fn populate(db: &Database) {
+    let project = db.create_project(CreateProjectRequest {
+        name: "工作".to_string(),
+        color: Some("#ef4444".to_string()),
+        icon: Some("💼".to_string()),
+    }).unwrap().id.unwrap();
+    let archived = db.create_project(CreateProjectRequest { name: "旧项目".to_string(), color: None, icon: None })
+        .unwrap().id.unwrap();
+    db.update_project(UpdateProjectRequest { id: archived, name: None, color: None, icon: None, is_archived: Some(true), sort_order: None })
+        .unwrap();
+
+    let parent = add_task(db, CreateTaskRequest {
+        description: Some("第一季度".to_string()),
+        due_date: Some(at("2024-05-01T09:30:00Z")),
+        priority: TaskPriority::High,
+        tags: Some(vec!["work/report".to_string()]),
+        project_id: Some(project),
+        ..task_request("季度报告")
+    });
+    add_task(db, CreateTaskRequest { parent_id: Some(parent), ..task_request("收集数据") });
+    add_task(db, CreateTaskRequest {
+        recurrence: Some(RecurrenceRule {
+            frequency: RecurrenceFrequency::Weekly,
+            interval: 2,
+            by_weekday: vec![1, 3],
+            until: None,
+            count: Some(5),
+        }),
+        ..task_request("健身")
+    });
+    add_task(db, CreateTaskRequest { due_date: Some(at("2024-06-01T00:00:00Z")), is_all_day: true, ..task_request("生日") });
+    let done = add_task(db, task_request("已完成"));
+    db.toggle_task_completion(done).unwrap();
+}
+
+/// 导入后会变化的字段（ID、创建时间）之外的任务内容，按标题排序
+fn task_summaries(db: &Database) -> Vec<String> {
+    let tasks = db.get_all_tasks().unwrap();
+    let title_of = |id: Option<i64>| -> Option<String> {
+        id.map(|id| tasks.iter().find(|task| task.id == Some(id)).unwrap().title.clone())
+    };
+    let project_of = |task: &Task| -> Option<String> {
+        task.project_id.map(|id| db.get_project_by_id(id).unwrap().name)
+    };
+
+    let mut summaries: Vec<String> = tasks.iter().map(|task| {
+        format!(
+            "{}|{}|{:?}|{}|{}|{:?}|{}|{:?}|{:?}|{}",
+            task.title,
+            task.description,
+            task.due_date,
+            task.priority as i32,
+            task.is_completed,
+            task.tags,
+            serde_json::to_string(&task.recurrence).unwrap(),
+            title_of(task.parent_id),
+            project_of(task),
+            task.is_all_day,
+        )
+    }).collect();
+    summaries.sort();
+    summaries
+}
+
+#[test]
+fn export_and_import_round_trip() {
+    let source = open_db();
+    populate(&source);
+    let json = source.export_tasks_to_json().unwrap();
+
+    let export: ExportData = serde_json::from_str(&json).unwrap();
+    assert_eq!(export.version, 2);
+    assert_eq!(export.projects.len(), 2);
+    assert_eq!(export.tasks.len(), 5);
+
+    let target = open_db();
+    assert_eq!(target.import_tasks_from_json(&json).unwrap(), 5);
+    assert_eq!(task_summaries(&target), task_summaries(&source));
+
+    let projects = target.get_projects(true).unwrap();
+    let work = projects.iter().find(|project| project.name == "工作").unwrap();
+    assert_eq!((work.color.as_str(), work.icon.as_str()), ("#ef4444", "💼"));
+    assert!(projects.iter().find(|project| project.name == "旧项目").unwrap().is_archived);
+
+    // 导入后的任务可以搜索
+    assert_eq!(target.search_tasks("季度", 10).unwrap().len(), 1);
+}
+
+#[test]
+fn importing_twice_merges_projects_by_name() {
+    let source = open_db();
+    populate(&source);
+    let json = source.export_tasks_to_json().unwrap();
+
+    let target = open_db();
+    target.import_tasks_from_json(&json).unwrap();
+    target.import_tasks_from_json(&json).unwrap();
+
+    assert_eq!(target.get_projects(true).unwrap().len(), 2);
+    assert_eq!(target.get_all_tasks().unwrap().len(), 10);
+}
+
+#[test]
+fn import_accepts_legacy_task_array() {
+    let source = open_db();
+    add_task(&source, CreateTaskRequest { tags: Some(vec!["legacy".to_string()]), ..task_request("旧格式") });
+    let tasks = serde_json::to_string(&source.get_all_tasks().unwrap()).unwrap();
+
+    let target = open_db();
+    assert_eq!(target.import_tasks_from_json(&tasks).unwrap(), 1);
+    let imported = target.get_all_tasks().unwrap();
+    assert_eq!(titles(&imported), ["旧格式"]);
+    assert_eq!(imported[0].tags, ["legacy"]);
+}
+
+#[test]
+fn import_keeps_completion_without_spawning_occurrences() {
+    let source = open_db();
+    add_task(&source, task_request("已完成"));
+    add_task(&source, CreateTaskRequest {
+        due_date: Some(at("2024-05-01T09:00:00Z")),
+        recurrence: Some(RecurrenceRule { frequency: RecurrenceFrequency::Daily, interval: 1, by_weekday: vec![], until: None, count: None }),
+        ..task_request("每天")
+    });
+    let mut tasks = source.get_all_tasks().unwrap();
+    for task in &mut tasks {
+        task.is_completed = true;
+    }
+
+    let target = open_db();
+    assert_eq!(target.import_tasks_from_json(&serde_json::to_string(&tasks).unwrap()).unwrap(), 2);
+    // 已完成的重复任务不会在导入时生成下一次任务
+    let imported = target.get_all_tasks().unwrap();
+    assert_eq!(imported.len(), 2);
+    assert!(imported.iter().all(|task| task.is_completed));
+}
+
+#[test]
+fn import_skips_invalid_tasks_and_rejects_malformed_json() {
+    let source = open_db();
+    add_task(&source, task_request("有效"));
+    let mut tasks = source.get_all_tasks().unwrap();
+    let mut invalid = tasks[0].clone();
+    invalid.title = String::new();
+    tasks.push(invalid);
+
+    let target = open_db();
+    assert_eq!(target.import_tasks_from_json(&serde_json::to_string(&tasks).unwrap()).unwrap(), 1);
+
+    assert_error(target.import_tasks_from_json("{ not json"), ErrorCode::Validation);
+    assert_eq!(target.get_all_tasks().unwrap().len(), 1);
+}
+
+#[test]
+fn export_excludes_trashed_tasks() {
+    let db = open_db();
+    add_task(&db, task_request("保留"));
+    let trashed = add_task(&db, task_request("删除"));
+    db.delete_task(trashed).unwrap();
+
+    let export: ExportData = serde_json::from_str(&db.export_tasks_to_json().unwrap()).unwrap();
+    assert_eq!(titles(&export.tasks), ["保留"]);
+}
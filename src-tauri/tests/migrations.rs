@@ -0,0 +1,152 @@
+//! 数据库结构迁移
+
+mod common;
+
+use std::path::Path;
+
+use app_lib::database::Database;
+use app_lib::error::ErrorCode;
+use app_lib::migrations::{self, MIGRATIONS};
+use chrono::{Duration, Utc};
+use rusqlite::Connection;
+use common::*;
+
+/// 按历史版本的表结构创建数据库：依次执行前 `version` 个迁移
+fn create_at_version(path: &Path, version: i64) -> Connection {
+    let connection = Connection::open(path).unwrap();
+    for migration in MIGRATIONS.iter().take_while(|migration| migration.version <= version) {
+        (migration.up)(&connection).unwrap();
+    }
+    connection.pragma_update(None, "user_version", version).unwrap();
+    connection
+}
+
+fn user_version(path: &Path) -> i64 {
+    Connection::open(path).unwrap().query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+}
+
+#[test]
+fn migrations_are_numbered_consecutively() {
+    for (index, migration) in MIGRATIONS.iter().enumerate() {
+        assert_eq!(migration.version, index as i64 + 1, "迁移 {} 的版本号不连续", migration.description);
+    }
+    assert_eq!(migrations::latest_version(), MIGRATIONS.len() as i64);
+}
+
+#[test]
+fn new_database_is_created_at_latest_version() {
+    let dir = TempDir::new("migrate-new");
+    Database::open_unencrypted(&dir.db_path()).unwrap();
+
+    assert_eq!(user_version(&dir.db_path()), migrations::latest_version());
+    // 新建的空数据库不需要备份
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn upgrades_from_every_earlier_version() {
+    for version in 1..migrations::latest_version() {
+        let dir = TempDir::new(&format!("migrate-v{}", version));
+        let connection = create_at_version(&dir.db_path(), version);
+        connection.execute(
+            "INSERT INTO tasks (title, due_date, priority, is_completed, created_at) VALUES ('旧任务', 1714555800, 2, 0, 1714550000)",
+            [],
+        ).unwrap();
+        drop(connection);
+
+        let db = Database::open_unencrypted(&dir.db_path())
+            .unwrap_or_else(|e| panic!("从版本 {} 迁移失败: {}", version, e));
+        let tasks = db.get_all_tasks().unwrap();
+        assert_eq!(titles(&tasks), ["旧任务"], "版本 {}", version);
+        assert_eq!(tasks[0].due_date, Some(at("2024-05-01T09:30:00Z")));
+        assert_eq!(db.search_tasks("旧任务", 10).unwrap().len(), 1, "版本 {} 的搜索索引", version);
+        drop(db);
+
+        assert_eq!(user_version(&dir.db_path()), migrations::latest_version());
+        assert!(dir.path().join(format!("tasks.db.v{}.bak", version)).exists(), "版本 {} 没有备份", version);
+    }
+}
+
+#[test]
+fn unversioned_database_keeps_tasks_and_tags() {
+    // 引入版本号之前的数据库：表已经存在，但 user_version 为 0，标签按名称关联
+    let dir = TempDir::new("migrate-v0");
+    let connection = create_at_version(&dir.db_path(), 1);
+    connection.pragma_update(None, "user_version", 0).unwrap();
+    // 旧版本没有启用外键约束，关联里可能有不存在的标签名
+    connection.execute_batch(
+        "PRAGMA foreign_keys = OFF;
+         INSERT INTO tasks (id, title, priority, is_completed, created_at) VALUES (1, '旧任务', 1, 1, 1714550000);
+         INSERT INTO tags (name, color, created_at) VALUES ('work', '#ff0000', 1714550000);
+         INSERT INTO task_tags (task_id, tag_name) VALUES (1, 'work');
+         INSERT INTO task_tags (task_id, tag_name) VALUES (1, 'renamed');"
+    ).unwrap();
+    drop(connection);
+
+    let db = Database::open_unencrypted(&dir.db_path()).unwrap();
+    let task = db.get_task_by_id(1).unwrap();
+    assert!(task.is_completed);
+    assert_eq!(task.sort_order, 1);
+    // 标签关联改为引用ID时，找不到标签的旧名称会补建标签
+    assert_eq!(task.tags, ["renamed", "work"]);
+    assert!(dir.path().join("tasks.db.v0.bak").exists());
+}
+
+#[test]
+fn text_timestamps_are_migrated_to_integers() {
+    let dir = TempDir::new("migrate-text-timestamps");
+    let connection = create_at_version(&dir.db_path(), 15);
+    // 早期版本以 RFC 3339 文本保存截止时间，创建时间取默认的 CURRENT_TIMESTAMP 文本
+    connection.execute(
+        "INSERT INTO tasks (title, due_date, priority, is_completed, created_at)
+         VALUES ('旧任务', '2024-03-10T08:00:00+08:00', 1, 0, '2024-03-01 12:00:00')",
+        [],
+    ).unwrap();
+    drop(connection);
+
+    let db = Database::open_unencrypted(&dir.db_path()).unwrap();
+    let task = db.get_all_tasks().unwrap().pop().unwrap();
+    assert_eq!(task.due_date, Some(at("2024-03-10T00:00:00Z")));
+    assert_eq!(task.created_at, at("2024-03-01T12:00:00Z"));
+}
+
+#[test]
+fn overdue_tasks_are_marked_notified_on_upgrade() {
+    let dir = TempDir::new("migrate-overdue-notified");
+    let connection = create_at_version(&dir.db_path(), 16);
+    let soon = (Utc::now() + Duration::minutes(5)).timestamp();
+    connection.execute_batch(&format!(
+        "INSERT INTO tasks (title, due_date, priority, is_completed, created_at) VALUES ('多年前', 1420070400, 1, 0, 1420000000);
+         INSERT INTO tasks (title, due_date, priority, is_completed, created_at) VALUES ('即将到期', {}, 1, 0, 1420000000);",
+        soon
+    )).unwrap();
+    drop(connection);
+
+    // 早已过期的任务不再提醒，即将到期的任务照常提醒
+    let db = Database::open_unencrypted(&dir.db_path()).unwrap();
+    assert_eq!(titles(&db.get_pending_notifications(15).unwrap()), ["即将到期"]);
+}
+
+#[test]
+fn reopening_current_database_does_not_migrate_again() {
+    let dir = TempDir::new("migrate-reopen");
+    let db = Database::open_unencrypted(&dir.db_path()).unwrap();
+    add_task(&db, task_request("任务"));
+    drop(db);
+
+    let db = Database::open_unencrypted(&dir.db_path()).unwrap();
+    assert_eq!(titles(&db.get_all_tasks().unwrap()), ["任务"]);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn newer_database_version_is_rejected() {
+    let dir = TempDir::new("migrate-newer");
+    drop(Database::open_unencrypted(&dir.db_path()).unwrap());
+    Connection::open(dir.db_path())
+        .unwrap()
+        .pragma_update(None, "user_version", migrations::latest_version() + 1)
+        .unwrap();
+
+    assert_error(Database::open_unencrypted(&dir.db_path()).map(|_| ()), ErrorCode::Conflict);
+}
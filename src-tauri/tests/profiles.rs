@@ -0,0 +1,124 @@
+//! 配置列表
+
+mod common;
+
+use app_lib::app_lock;
+use app_lib::database::Database;
+use app_lib::encryption;
+use app_lib::error::ErrorCode;
+use app_lib::models::{CreateProfileRequest, RenameProfileRequest};
+use app_lib::profiles::{ProfileStore, DEFAULT_PROFILE_ID, PROFILES_FILE_NAME};
+use common::*;
+
+fn create(store: &mut ProfileStore, name: &str) -> i64 {
+    store.create(CreateProfileRequest { name: name.to_string() }).unwrap().id
+}
+
+#[test]
+fn missing_profiles_file_means_single_default_profile() {
+    let dir = TempDir::new("profiles-default");
+    let store = ProfileStore::load(dir.path()).unwrap();
+
+    let profiles = store.list();
+    assert_eq!(profiles.len(), 1);
+    assert_eq!(profiles[0].id, DEFAULT_PROFILE_ID);
+    assert!(profiles[0].is_active);
+    // 默认配置沿用原来的数据库文件和密钥
+    assert_eq!(store.database_path(DEFAULT_PROFILE_ID), dir.db_path());
+    assert_eq!(ProfileStore::key_name(DEFAULT_PROFILE_ID), encryption::DEFAULT_KEY_NAME);
+    assert!(!dir.path().join(PROFILES_FILE_NAME).exists());
+}
+
+#[test]
+fn create_rename_and_switch_are_persisted() {
+    let dir = TempDir::new("profiles-persist");
+    let mut store = ProfileStore::load(dir.path()).unwrap();
+    let work = create(&mut store, " 工作 ");
+
+    assert_eq!(store.find(work).unwrap().name, "工作");
+    assert_ne!(store.database_path(work), dir.db_path());
+    assert_ne!(ProfileStore::key_name(work), encryption::DEFAULT_KEY_NAME);
+
+    store.rename(RenameProfileRequest { id: work, name: "公司".to_string() }).unwrap();
+    let active = store.set_active(work).unwrap();
+    assert_eq!((active.id, active.name.as_str()), (work, "公司"));
+
+    let store = ProfileStore::load(dir.path()).unwrap();
+    let names: Vec<String> = store.list().into_iter().map(|profile| profile.name).collect();
+    assert_eq!(names, ["默认", "公司"]);
+    assert_eq!(store.active().id, work);
+}
+
+#[test]
+fn profile_names_are_validated_and_unique() {
+    let dir = TempDir::new("profiles-names");
+    let mut store = ProfileStore::load(dir.path()).unwrap();
+    let work = create(&mut store, "Work");
+
+    assert_error(store.create(CreateProfileRequest { name: "  ".to_string() }), ErrorCode::Validation);
+    assert_error(store.create(CreateProfileRequest { name: "长".repeat(51) }), ErrorCode::Validation);
+    assert_error(store.create(CreateProfileRequest { name: "work".to_string() }), ErrorCode::Conflict);
+    assert_error(store.rename(RenameProfileRequest { id: DEFAULT_PROFILE_ID, name: "WORK".to_string() }), ErrorCode::Conflict);
+    assert_error(store.rename(RenameProfileRequest { id: 42, name: "其他".to_string() }), ErrorCode::NotFound);
+    assert_error(store.set_active(42), ErrorCode::NotFound);
+
+    // 改名为自己原来的名称（仅大小写不同）是允许的
+    store.rename(RenameProfileRequest { id: work, name: "WORK".to_string() }).unwrap();
+    assert_eq!(store.list().len(), 2);
+}
+
+#[test]
+fn delete_removes_database_files_and_never_reuses_id() {
+    let dir = TempDir::new("profiles-delete");
+    let mut store = ProfileStore::load(dir.path()).unwrap();
+    let work = create(&mut store, "工作");
+    let database_path = store.database_path(work);
+    let backup_path = database_path.with_file_name(format!("{}.v3.bak", database_path.file_name().unwrap().to_string_lossy()));
+    std::fs::write(&database_path, b"").unwrap();
+    std::fs::write(&backup_path, b"").unwrap();
+    std::fs::write(dir.db_path(), b"").unwrap();
+
+    assert_error(store.delete(DEFAULT_PROFILE_ID), ErrorCode::Conflict);
+    store.delete(work).unwrap();
+    assert!(!database_path.exists() && !backup_path.exists());
+    assert!(dir.db_path().exists());
+    assert_error(store.delete(work), ErrorCode::NotFound);
+
+    let next = create(&mut store, "工作");
+    assert!(next > work);
+}
+
+#[test]
+fn deleting_protected_profile_requires_its_passphrase() {
+    let dir = TempDir::new("profiles-protected");
+    let mut store = ProfileStore::load(dir.path()).unwrap();
+    let work = create(&mut store, "工作");
+    let mut db = Database::open_unencrypted(&store.database_path(work)).unwrap();
+
+    // 没有设置密码的配置可以直接删除
+    db.verify_removal(None).unwrap();
+
+    db.set_app_lock_hash(Some(&app_lock::hash_passphrase("secret").unwrap())).unwrap();
+    assert_error(db.verify_removal(None), ErrorCode::Locked);
+    assert_error(db.verify_removal(Some("wrong")), ErrorCode::Validation);
+    db.verify_removal(Some("secret")).unwrap();
+    drop(db);
+
+    store.delete(work).unwrap();
+    assert!(!store.database_path(work).exists());
+}
+
+#[test]
+fn missing_active_profile_falls_back_to_first() {
+    let dir = TempDir::new("profiles-fallback");
+    std::fs::write(
+        dir.path().join(PROFILES_FILE_NAME),
+        r#"{"active_id": 7, "next_id": 8, "profiles": [{"id": 3, "name": "个人", "created_at": "2024-05-01T00:00:00Z"}]}"#,
+    ).unwrap();
+
+    let store = ProfileStore::load(dir.path()).unwrap();
+    assert_eq!(store.active().id, 3);
+
+    std::fs::write(dir.path().join(PROFILES_FILE_NAME), "{").unwrap();
+    assert_error(ProfileStore::load(dir.path()), ErrorCode::Storage);
+}
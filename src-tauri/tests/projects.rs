@@ -0,0 +1,182 @@
+//! 项目、任务排序和智能列表
+
+mod common;
+
+use app_lib::database::Database;
+use app_lib::error::ErrorCode;
+use app_lib::models::{
+    CreateProjectRequest, CreateSmartListRequest, CreateTaskRequest, TaskFilter, TaskPriority, TaskQuery, TaskSortField,
+    TaskSortKey, UpdateProjectRequest, UpdateSmartListRequest,
+};
+use common::*;
+
+fn add_project(db: &Database, name: &str) -> i64 {
+    db.create_project(CreateProjectRequest { name: name.to_string(), color: None, icon: None })
+        .unwrap()
+        .id
+        .unwrap()
+}
+
+fn project_update(id: i64) -> UpdateProjectRequest {
+    UpdateProjectRequest { id, name: None, color: None, icon: None, is_archived: None, sort_order: None }
+}
+
+#[test]
+fn create_update_and_archive_projects() {
+    let db = open_db();
+    let work = add_project(&db, "工作");
+    let home = add_project(&db, "家庭");
+
+    let project = db.get_project_by_id(work).unwrap();
+    assert_eq!(project.name, "工作");
+    assert_eq!(project.color, "#3b82f6");
+    assert!(!project.is_archived);
+
+    let updated = db.update_project(UpdateProjectRequest {
+        name: Some("公司".to_string()),
+        color: Some("#10b981".to_string()),
+        is_archived: Some(true),
+        ..project_update(work)
+    }).unwrap();
+    assert_eq!(updated.name, "公司");
+    assert_eq!(updated.sort_order, project.sort_order);
+
+    let names = |include_archived| -> Vec<String> {
+        db.get_projects(include_archived).unwrap().into_iter().map(|project| project.name).collect()
+    };
+    assert_eq!(names(false), ["家庭"]);
+    assert_eq!(names(true), ["公司", "家庭"]);
+
+    db.update_project(UpdateProjectRequest { sort_order: Some(0), ..project_update(home) }).unwrap();
+    assert_eq!(names(true), ["家庭", "公司"]);
+
+    assert_error(db.get_project_by_id(999), ErrorCode::NotFound);
+    assert_error(db.update_project(project_update(999)), ErrorCode::NotFound);
+}
+
+#[test]
+fn deleting_project_moves_tasks_to_inbox() {
+    let db = open_db();
+    let project = add_project(&db, "项目");
+    let task = add_task(&db, CreateTaskRequest { project_id: Some(project), ..task_request("任务") });
+    add_task(&db, task_request("收集箱"));
+
+    assert_eq!(titles(&db.get_project_tasks(Some(project)).unwrap()), ["任务"]);
+    assert_eq!(titles(&db.get_project_tasks(None).unwrap()), ["收集箱"]);
+
+    db.delete_project(project).unwrap();
+    assert_error(db.get_project_by_id(project), ErrorCode::NotFound);
+    assert_eq!(db.get_task_by_id(task).unwrap().project_id, None);
+    assert_eq!(db.get_project_tasks(None).unwrap().len(), 2);
+}
+
+#[test]
+fn subtasks_follow_parent_project() {
+    let db = open_db();
+    let project = add_project(&db, "项目");
+    let other = add_project(&db, "其他");
+    let parent = add_task(&db, CreateTaskRequest { project_id: Some(project), ..task_request("父任务") });
+    let child = add_task(&db, CreateTaskRequest { parent_id: Some(parent), project_id: Some(other), ..task_request("子任务") });
+
+    // 子任务忽略请求中的项目，跟随父任务
+    assert_eq!(db.get_task_by_id(child).unwrap().project_id, Some(project));
+
+    db.move_tasks_to_project(&[parent], Some(other)).unwrap();
+    assert_eq!(db.get_task_by_id(parent).unwrap().project_id, Some(other));
+    assert_eq!(db.get_task_by_id(child).unwrap().project_id, Some(other));
+
+    // 单独移动子任务时先提升为顶层任务
+    db.move_tasks_to_project(&[child], None).unwrap();
+    let child_task = db.get_task_by_id(child).unwrap();
+    assert_eq!(child_task.project_id, None);
+    assert_eq!(child_task.parent_id, None);
+
+    assert_error(db.move_tasks_to_project(&[parent], Some(999)), ErrorCode::NotFound);
+}
+
+#[test]
+fn moving_parent_with_subtasks_keeps_tree() {
+    let db = open_db();
+    let project = add_project(&db, "项目");
+    let parent = add_task(&db, task_request("父任务"));
+    let child = add_task(&db, CreateTaskRequest { parent_id: Some(parent), ..task_request("子任务") });
+    let grandchild = add_task(&db, CreateTaskRequest { parent_id: Some(child), ..task_request("孙任务") });
+
+    // 多选时父任务和子孙任务同时在列表中，顺序不影响结果
+    db.move_tasks_to_project(&[grandchild, parent, child], Some(project)).unwrap();
+
+    let child_task = db.get_task_by_id(child).unwrap();
+    assert_eq!(child_task.parent_id, Some(parent));
+    assert_eq!(child_task.project_id, Some(project));
+    assert_eq!(db.get_task_by_id(grandchild).unwrap().parent_id, Some(child));
+    assert_eq!(db.get_task_by_id(parent).unwrap().project_id, Some(project));
+}
+
+#[test]
+fn moved_tasks_go_to_end_of_target_project() {
+    let db = open_db();
+    let project = add_project(&db, "项目");
+    add_task(&db, CreateTaskRequest { project_id: Some(project), ..task_request("已有") });
+    let moved = add_task(&db, task_request("移入"));
+
+    db.move_tasks_to_project(&[moved], Some(project)).unwrap();
+    assert_eq!(titles(&db.get_project_tasks(Some(project)).unwrap()), ["已有", "移入"]);
+}
+
+#[test]
+fn sort_order_is_per_project() {
+    let db = open_db();
+    let project = add_project(&db, "项目");
+    let a = add_task(&db, CreateTaskRequest { project_id: Some(project), ..task_request("a") });
+    let b = add_task(&db, CreateTaskRequest { project_id: Some(project), ..task_request("b") });
+    let inbox = add_task(&db, task_request("inbox"));
+
+    // 每个项目从 1 开始编号
+    assert_eq!(db.get_task_by_id(a).unwrap().sort_order, 1);
+    assert_eq!(db.get_task_by_id(inbox).unwrap().sort_order, 1);
+
+    // 指定项目时忽略其他项目中的任务
+    db.update_tasks_sort_order(vec![(b, 1), (a, 2), (inbox, 5)], Some(project)).unwrap();
+    assert_eq!(titles(&db.get_project_tasks(Some(project)).unwrap()), ["b", "a"]);
+    assert_eq!(db.get_task_by_id(inbox).unwrap().sort_order, 1);
+
+    let query = TaskQuery { sort: vec![TaskSortKey { field: TaskSortField::SortOrder, descending: true }], ..Default::default() };
+    let result = db.query_tasks(&TaskQuery { filter: TaskFilter { project_ids: vec![project], ..Default::default() }, ..query }).unwrap();
+    assert_eq!(titles(&result.tasks), ["a", "b"]);
+}
+
+#[test]
+fn smart_lists_store_queries() {
+    let db = open_db();
+    add_task(&db, CreateTaskRequest { priority: TaskPriority::High, ..task_request("重要") });
+    add_task(&db, CreateTaskRequest { priority: TaskPriority::Low, ..task_request("普通") });
+
+    let query = TaskQuery {
+        filter: TaskFilter { priorities: vec![TaskPriority::High], ..Default::default() },
+        ..Default::default()
+    };
+    let list = db.create_smart_list(CreateSmartListRequest { name: "重要任务".to_string(), icon: None, query }).unwrap();
+    let id = list.id.unwrap();
+    assert_eq!(list.task_count, 1);
+    assert_eq!(titles(&db.get_smart_list_tasks(id).unwrap().tasks), ["重要"]);
+
+    // 数量每次读取时重新计算
+    add_task(&db, CreateTaskRequest { priority: TaskPriority::High, ..task_request("又一个") });
+    assert_eq!(db.get_smart_list_by_id(id).unwrap().task_count, 2);
+
+    let updated = db.update_smart_list(UpdateSmartListRequest {
+        id,
+        name: Some("低优先级".to_string()),
+        icon: Some("🐢".to_string()),
+        query: Some(TaskQuery { filter: TaskFilter { priorities: vec![TaskPriority::Low], ..Default::default() }, ..Default::default() }),
+        sort_order: None,
+    }).unwrap();
+    assert_eq!(updated.name, "低优先级");
+    assert_eq!(updated.task_count, 1);
+    assert_eq!(db.get_smart_lists().unwrap().len(), 1);
+
+    db.delete_smart_list(id).unwrap();
+    assert!(db.get_smart_lists().unwrap().is_empty());
+    assert_error(db.get_smart_list_by_id(id), ErrorCode::NotFound);
+    assert_error(db.get_smart_list_tasks(id), ErrorCode::NotFound);
+}
@@ -0,0 +1,126 @@
+//! 应用设置、应用锁密码和加密状态
+
+mod common;
+
+use app_lib::database::Database;
+use app_lib::encryption;
+use app_lib::error::ErrorCode;
+use app_lib::models::{CloseBehavior, EncryptionMode, Patch, StartupBehavior, UpdateSettingsRequest};
+use common::*;
+
+#[test]
+fn new_database_uses_default_settings() {
+    let db = open_db();
+    let settings = db.get_app_settings().unwrap();
+
+    assert!(matches!(settings.close_behavior, CloseBehavior::Ask));
+    assert!(settings.notifications_enabled);
+    assert_eq!(settings.notification_time_before, 15);
+    assert!(matches!(settings.startup_behavior, StartupBehavior::Normal));
+    assert_eq!(settings.theme, "auto");
+    assert_eq!(settings.trash_retention_days, 30);
+    assert!(!settings.app_lock_enabled);
+    assert_eq!(settings.auto_lock_minutes, 0);
+    assert_eq!(settings.timezone, None);
+}
+
+#[test]
+fn update_settings_keeps_unspecified_fields() {
+    let db = open_db();
+
+    let updated = db.update_app_settings(UpdateSettingsRequest {
+        close_behavior: Some(CloseBehavior::Minimize),
+        theme: Some("dark".to_string()),
+        timezone: Patch::Set("Europe/Berlin".to_string()),
+        ..settings_request()
+    }).unwrap();
+    assert!(matches!(updated.close_behavior, CloseBehavior::Minimize));
+
+    db.update_app_settings(UpdateSettingsRequest {
+        notification_time_before: Some(30),
+        startup_behavior: Some(StartupBehavior::Minimized),
+        ..settings_request()
+    }).unwrap();
+
+    let settings = db.get_app_settings().unwrap();
+    assert!(matches!(settings.close_behavior, CloseBehavior::Minimize));
+    assert!(matches!(settings.startup_behavior, StartupBehavior::Minimized));
+    assert_eq!(settings.theme, "dark");
+    assert_eq!(settings.notification_time_before, 30);
+    assert_eq!(settings.timezone.as_deref(), Some("Europe/Berlin"));
+
+    // 清除时区后跟随系统时区
+    db.update_app_settings(UpdateSettingsRequest { timezone: Patch::Clear, ..settings_request() }).unwrap();
+    assert_eq!(db.get_app_settings().unwrap().timezone, None);
+}
+
+#[test]
+fn update_settings_validates_values() {
+    let db = open_db();
+
+    assert_error(db.update_app_settings(UpdateSettingsRequest { theme: Some("pink".to_string()), ..settings_request() }), ErrorCode::Validation);
+    assert_error(db.update_app_settings(UpdateSettingsRequest { notification_time_before: Some(0), ..settings_request() }), ErrorCode::Validation);
+    assert_error(db.update_app_settings(UpdateSettingsRequest { trash_retention_days: Some(-1), ..settings_request() }), ErrorCode::Validation);
+    assert_error(db.update_app_settings(UpdateSettingsRequest { auto_lock_minutes: Some(100_000), ..settings_request() }), ErrorCode::Validation);
+    assert_error(
+        db.update_app_settings(UpdateSettingsRequest { timezone: Patch::Set("Mars/Olympus".to_string()), ..settings_request() }),
+        ErrorCode::Validation,
+    );
+
+    assert_eq!(db.get_app_settings().unwrap().theme, "auto");
+}
+
+#[test]
+fn settings_persist_across_connections() {
+    let dir = TempDir::new("settings");
+    {
+        let db = Database::open_unencrypted(&dir.db_path()).unwrap();
+        db.update_app_settings(UpdateSettingsRequest { trash_retention_days: Some(7), ..settings_request() }).unwrap();
+    }
+
+    let db = Database::open_unencrypted(&dir.db_path()).unwrap();
+    assert_eq!(db.get_app_settings().unwrap().trash_retention_days, 7);
+}
+
+#[test]
+fn app_lock_hash_can_be_set_and_cleared() {
+    let db = open_db();
+    assert_eq!(db.get_app_lock_hash().unwrap(), None);
+
+    db.set_app_lock_hash(Some("hash")).unwrap();
+    assert_eq!(db.get_app_lock_hash().unwrap().as_deref(), Some("hash"));
+    assert!(db.get_app_settings().unwrap().app_lock_enabled);
+
+    db.set_app_lock_hash(None).unwrap();
+    assert_eq!(db.get_app_lock_hash().unwrap(), None);
+    assert!(!db.get_app_settings().unwrap().app_lock_enabled);
+}
+
+#[test]
+fn in_memory_database_cannot_be_encrypted() {
+    let mut db = open_db();
+    assert!(db.is_in_memory());
+    assert!(db.is_unlocked());
+    assert_eq!(db.get_encryption_status().mode, EncryptionMode::None);
+
+    assert_error(db.encrypt(Some("secret".to_string())), ErrorCode::Conflict);
+    assert_error(db.change_key(Some("secret".to_string())), ErrorCode::Conflict);
+}
+
+#[test]
+fn encrypt_with_passphrase_rewrites_file() {
+    let dir = TempDir::new("encrypt");
+    let mut db = Database::open_unencrypted(&dir.db_path()).unwrap();
+    add_task(&db, task_request("机密"));
+    assert!(!encryption::is_encrypted_file(&dir.db_path()).unwrap());
+
+    assert_error(db.encrypt(Some(String::new())), ErrorCode::Validation);
+    db.encrypt(Some("secret".to_string())).unwrap();
+
+    let status = db.get_encryption_status();
+    assert_eq!(status.mode, EncryptionMode::Passphrase);
+    assert_eq!(status.key_storage, None);
+    assert!(encryption::is_encrypted_file(&dir.db_path()).unwrap());
+    assert_eq!(titles(&db.get_all_tasks().unwrap()), ["机密"]);
+    assert_error(db.encrypt(None), ErrorCode::Conflict);
+}
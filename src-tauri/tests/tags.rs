@@ -0,0 +1,199 @@
+//! 标签的增删改查、层级路径、使用统计、合并和清理
+
+mod common;
+
+use app_lib::database::Database;
+use app_lib::error::ErrorCode;
+use app_lib::models::{CreateTagRequest, CreateTaskRequest, Patch, Tag, TaskFilter, UpdateTagRequest, UpdateTaskRequest};
+use common::*;
+
+fn tag_request(name: &str) -> CreateTagRequest {
+    CreateTagRequest { name: name.to_string(), color: "#ff0000".to_string() }
+}
+
+/// 任务标签变更历史中每一条的旧值和新值
+fn tag_history(db: &Database, task_id: i64) -> Vec<(serde_json::Value, serde_json::Value)> {
+    db.get_task_history(task_id).unwrap()
+        .into_iter()
+        .filter(|entry| entry.field == "tags")
+        .map(|entry| (entry.old_value.unwrap(), entry.new_value.unwrap()))
+        .collect()
+}
+
+fn tag_names(db: &Database) -> Vec<String> {
+    db.get_all_tags().unwrap().into_iter().map(|tag| tag.name).collect()
+}
+
+fn find_tag(db: &Database, name: &str) -> Tag {
+    db.get_all_tags().unwrap().into_iter().find(|tag| tag.name == name).unwrap_or_else(|| panic!("标签不存在: {}", name))
+}
+
+fn tagged_task(db: &Database, title: &str, tags: &[&str]) -> i64 {
+    add_task(db, CreateTaskRequest { tags: Some(tags.iter().map(|tag| tag.to_string()).collect()), ..task_request(title) })
+}
+
+#[test]
+fn create_and_get_tag() {
+    let db = open_db();
+
+    let created = db.create_tag(tag_request("work")).unwrap();
+    let tag = db.get_tag_by_id(created.id.unwrap()).unwrap();
+    assert_eq!(tag.name, "work");
+    assert_eq!(tag.color, "#ff0000");
+    assert_eq!(tag.parent_id, None);
+
+    assert_error(db.create_tag(tag_request("work")), ErrorCode::Conflict);
+    assert_error(db.create_tag(CreateTagRequest { color: "red".to_string(), ..tag_request("home") }), ErrorCode::Validation);
+    assert_error(db.create_tag(tag_request("")), ErrorCode::Validation);
+    assert_error(db.get_tag_by_id(999), ErrorCode::NotFound);
+}
+
+#[test]
+fn tag_paths_create_missing_parents() {
+    let db = open_db();
+
+    let billing = db.create_tag(tag_request("work/clientA/billing")).unwrap();
+    assert_eq!(tag_names(&db), ["work", "work/clientA", "work/clientA/billing"]);
+
+    let client = find_tag(&db, "work/clientA");
+    assert_eq!(billing.parent_id, client.id);
+    assert_eq!(client.parent_id, find_tag(&db, "work").id);
+
+    // 任务上的标签路径同样会补建上级标签
+    tagged_task(&db, "任务", &["home/garden"]);
+    assert_eq!(find_tag(&db, "home/garden").parent_id, find_tag(&db, "home").id);
+}
+
+#[test]
+fn rename_tag_moves_children_and_updates_tasks() {
+    let db = open_db();
+    let task = tagged_task(&db, "任务", &["work/clientA"]);
+    let work = find_tag(&db, "work");
+
+    let renamed = db.update_tag(UpdateTagRequest { id: work.id.unwrap(), name: Some("job".to_string()), color: None }).unwrap();
+    assert_eq!(renamed.name, "job");
+    assert_eq!(tag_names(&db), ["job", "job/clientA"]);
+    assert_eq!(db.get_task_by_id(task).unwrap().tags, ["job/clientA"]);
+    assert_eq!(db.search_tasks("job", 10).unwrap().len(), 1);
+    assert_eq!(tag_history(&db, task), [(serde_json::json!(["work/clientA"]), serde_json::json!(["job/clientA"]))]);
+
+    // 修改上级路径时移动到新的上级标签下
+    let client = find_tag(&db, "job/clientA");
+    let moved = db.update_tag(UpdateTagRequest { id: client.id.unwrap(), name: Some("clients/A".to_string()), color: None }).unwrap();
+    assert_eq!(moved.parent_id, find_tag(&db, "clients").id);
+
+    assert_error(
+        db.update_tag(UpdateTagRequest { id: renamed.id.unwrap(), name: None, color: None }),
+        ErrorCode::Validation,
+    );
+    let clients = find_tag(&db, "clients");
+    assert_error(
+        db.update_tag(UpdateTagRequest { id: clients.id.unwrap(), name: Some("clients/A/sub".to_string()), color: None }),
+        ErrorCode::Validation,
+    );
+}
+
+#[test]
+fn delete_tag_removes_children_and_task_links() {
+    let db = open_db();
+    let task = tagged_task(&db, "任务", &["work/clientA", "home"]);
+
+    db.delete_tag(find_tag(&db, "work").id.unwrap()).unwrap();
+    assert_eq!(tag_names(&db), ["home"]);
+    assert_eq!(db.get_task_by_id(task).unwrap().tags, ["home"]);
+    assert!(db.search_tasks("clientA", 10).unwrap().is_empty());
+    assert_eq!(tag_history(&db, task), [(serde_json::json!(["home", "work/clientA"]), serde_json::json!(["home"]))]);
+}
+
+#[test]
+fn task_tags_can_be_replaced_and_cleared() {
+    let db = open_db();
+    let task = tagged_task(&db, "任务", &["a", "b"]);
+
+    db.update_task(UpdateTaskRequest { tags: Patch::Set(vec!["c".to_string()]), ..update_request(task) }).unwrap();
+    assert_eq!(db.get_task_by_id(task).unwrap().tags, ["c"]);
+
+    db.update_task(UpdateTaskRequest { tags: Patch::Clear, ..update_request(task) }).unwrap();
+    assert!(db.get_task_by_id(task).unwrap().tags.is_empty());
+}
+
+#[test]
+fn tag_filters_include_child_tags() {
+    let db = open_db();
+    tagged_task(&db, "parent", &["work"]);
+    tagged_task(&db, "child", &["work/clientA"]);
+    tagged_task(&db, "both", &["work", "urgent"]);
+    tagged_task(&db, "other", &["home"]);
+
+    let count = |filter: TaskFilter| db.count_tasks(&filter).unwrap();
+    assert_eq!(count(TaskFilter { tags_any: vec!["work".to_string()], ..Default::default() }), 3);
+    assert_eq!(count(TaskFilter { tags_any: vec!["work/clientA".to_string()], ..Default::default() }), 1);
+    assert_eq!(count(TaskFilter { tags_all: vec!["work".to_string(), "urgent".to_string()], ..Default::default() }), 1);
+    assert_eq!(count(TaskFilter { tags_none: vec!["work".to_string()], ..Default::default() }), 1);
+    assert_eq!(count(TaskFilter { tags_any: vec!["missing".to_string()], ..Default::default() }), 0);
+}
+
+#[test]
+fn tag_stats_roll_up_child_tags() {
+    let db = open_db();
+    tagged_task(&db, "open", &["work"]);
+    let done = tagged_task(&db, "done", &["work/clientA"]);
+    tagged_task(&db, "both", &["work", "work/clientA"]);
+    let trashed = tagged_task(&db, "trashed", &["work"]);
+    db.toggle_task_completion(done).unwrap();
+    db.delete_task(trashed).unwrap();
+    db.create_tag(tag_request("empty")).unwrap();
+
+    let stats = db.get_tag_stats().unwrap();
+    let stat = |name: &str| stats.iter().find(|stat| stat.tag.name == name).unwrap();
+
+    // 同时带有上级和子标签的任务只计算一次，回收站中的任务不计入
+    assert_eq!((stat("work").open_count, stat("work").completed_count), (2, 1));
+    assert_eq!((stat("work/clientA").open_count, stat("work/clientA").completed_count), (1, 1));
+    assert_eq!((stat("empty").open_count, stat("empty").completed_count), (0, 0));
+    assert!(stat("work").auto_created);
+    assert!(!stat("empty").auto_created);
+}
+
+#[test]
+fn merge_tags_moves_tasks_and_children() {
+    let db = open_db();
+    let first = tagged_task(&db, "first", &["todo/urgent"]);
+    let second = tagged_task(&db, "second", &["task", "todo"]);
+    tagged_task(&db, "third", &["task/urgent"]);
+
+    let target = find_tag(&db, "task");
+    let merged = db.merge_tags(&[find_tag(&db, "todo").id.unwrap()], target.id.unwrap()).unwrap();
+    assert_eq!(merged.name, "task");
+
+    // 同名子标签继续合并
+    assert_eq!(tag_names(&db), ["task", "task/urgent"]);
+    assert_eq!(db.get_task_by_id(first).unwrap().tags, ["task/urgent"]);
+    assert_eq!(tag_history(&db, first), [(serde_json::json!(["todo/urgent"]), serde_json::json!(["task/urgent"]))]);
+    assert_eq!(db.get_task_by_id(second).unwrap().tags, ["task"]);
+    assert_eq!(
+        db.count_tasks(&TaskFilter { tags_any: vec!["task/urgent".to_string()], ..Default::default() }).unwrap(),
+        2,
+    );
+
+    let target_id = target.id.unwrap();
+    assert_error(db.merge_tags(&[], target_id), ErrorCode::Validation);
+    assert_error(db.merge_tags(&[target_id], target_id), ErrorCode::Validation);
+    assert_error(db.merge_tags(&[target_id], find_tag(&db, "task/urgent").id.unwrap()), ErrorCode::Validation);
+}
+
+#[test]
+fn delete_unused_tags_keeps_manual_and_used_tags() {
+    let db = open_db();
+    let task = tagged_task(&db, "任务", &["old/unused", "keep/used"]);
+    db.create_tag(tag_request("manual")).unwrap();
+    let trashed = tagged_task(&db, "回收站", &["trash"]);
+    db.delete_task(trashed).unwrap();
+
+    db.update_task(UpdateTaskRequest { tags: Patch::Set(vec!["keep/used".to_string()]), ..update_request(task) }).unwrap();
+
+    // old 和 old/unused 被删除；keep 是仍在使用的子标签的上级；回收站中的任务仍算作在使用
+    assert_eq!(db.delete_unused_tags().unwrap(), 2);
+    assert_eq!(tag_names(&db), ["keep", "keep/used", "manual", "trash"]);
+    assert_eq!(db.delete_unused_tags().unwrap(), 0);
+}
@@ -0,0 +1,557 @@
+//! 任务的增删改查、完成状态、重复任务、子任务、回收站、搜索、查询和提醒
+
+mod common;
+
+use app_lib::database::Database;
+use app_lib::error::ErrorCode;
+use app_lib::models::{
+    CreateTaskRequest, DueWindow, Patch, RecurrenceFrequency, RecurrenceRule, RelativeDueRange, TaskFilter, TaskPriority,
+    TaskQuery, TaskSortField, TaskSortKey, UpdateTaskRequest,
+};
+use app_lib::timezone;
+use chrono::{DateTime, Duration, Utc};
+use common::*;
+
+fn daily() -> RecurrenceRule {
+    RecurrenceRule { frequency: RecurrenceFrequency::Daily, interval: 1, by_weekday: Vec::new(), until: None, count: None }
+}
+
+#[test]
+fn create_and_get_task() {
+    let db = open_db();
+    let due_date = at("2024-05-01T09:30:00Z");
+
+    let created = db.create_task(CreateTaskRequest {
+        description: Some("季度报告".to_string()),
+        due_date: Some(due_date),
+        priority: TaskPriority::High,
+        tags: Some(vec!["work".to_string(), "report".to_string()]),
+        ..task_request("写报告")
+    }).unwrap();
+    let task = db.get_task_by_id(created.id.unwrap()).unwrap();
+
+    assert_eq!(task.title, "写报告");
+    assert_eq!(task.description, "季度报告");
+    assert_eq!(task.due_date, Some(due_date));
+    assert_eq!(task.priority as i32, TaskPriority::High as i32);
+    assert!(!task.is_completed);
+    assert_eq!(task.tags, ["report", "work"]);
+    assert_eq!(task.created_at.timestamp(), created.created_at.timestamp());
+}
+
+#[test]
+fn create_task_validates_request() {
+    let db = open_db();
+
+    assert_error(db.create_task(task_request("   ")), ErrorCode::Validation);
+    assert_error(db.create_task(task_request(&"长".repeat(201))), ErrorCode::Validation);
+    assert_error(
+        db.create_task(CreateTaskRequest { tags: Some(vec!["a//b".to_string()]), ..task_request("任务") }),
+        ErrorCode::Validation,
+    );
+    assert_error(
+        db.create_task(CreateTaskRequest { recurrence: Some(RecurrenceRule { interval: 0, ..daily() }), ..task_request("任务") }),
+        ErrorCode::Validation,
+    );
+    // 每 7 天重复时永远落在同一个星期几，指定的其他星期几不会出现
+    assert_error(
+        db.create_task(CreateTaskRequest {
+            recurrence: Some(RecurrenceRule { interval: 14, by_weekday: vec![1, 3], ..daily() }),
+            ..task_request("任务")
+        }),
+        ErrorCode::Validation,
+    );
+    assert_error(db.create_task(CreateTaskRequest { parent_id: Some(42), ..task_request("子任务") }), ErrorCode::NotFound);
+    assert_error(db.create_task(CreateTaskRequest { project_id: Some(42), ..task_request("任务") }), ErrorCode::NotFound);
+    assert!(db.get_all_tasks().unwrap().is_empty());
+}
+
+#[test]
+fn get_missing_task_is_not_found() {
+    let db = open_db();
+
+    assert_error(db.get_task_by_id(1), ErrorCode::NotFound);
+    assert_error(db.update_task(update_request(1)), ErrorCode::NotFound);
+    assert_error(db.toggle_task_completion(1), ErrorCode::NotFound);
+}
+
+#[test]
+fn update_task_changes_only_given_fields() {
+    let db = open_db();
+    let id = add_task(&db, CreateTaskRequest {
+        description: Some("描述".to_string()),
+        due_date: Some(at("2024-05-01T09:30:00Z")),
+        tags: Some(vec!["home".to_string()]),
+        ..task_request("旧标题")
+    });
+
+    let updated = db.update_task(UpdateTaskRequest { title: Some("新标题".to_string()), ..update_request(id) }).unwrap();
+    assert_eq!(updated.title, "新标题");
+    assert_eq!(updated.description, "描述");
+    assert_eq!(updated.tags, ["home"]);
+
+    db.update_task(UpdateTaskRequest {
+        description: Patch::Clear,
+        due_date: Patch::Clear,
+        tags: Patch::Set(vec!["errand".to_string()]),
+        priority: Some(TaskPriority::Low),
+        ..update_request(id)
+    }).unwrap();
+    let task = db.get_task_by_id(id).unwrap();
+    assert_eq!(task.title, "新标题");
+    assert_eq!(task.description, "");
+    assert_eq!(task.due_date, None);
+    assert_eq!(task.tags, ["errand"]);
+    assert_eq!(task.priority as i32, TaskPriority::Low as i32);
+}
+
+#[test]
+fn all_day_due_dates_keep_only_the_date() {
+    let db = open_db();
+    set_timezone(&db, "Asia/Shanghai");
+    let id = add_task(&db, CreateTaskRequest {
+        due_date: Some(at("2024-05-01T15:45:00Z")),
+        is_all_day: true,
+        ..task_request("全天")
+    });
+    assert_eq!(db.get_task_by_id(id).unwrap().due_date, Some(at("2024-05-01T00:00:00Z")));
+
+    // 取消全天时换算为用户时区的当天零点
+    db.update_task(UpdateTaskRequest { is_all_day: Some(false), ..update_request(id) }).unwrap();
+    let task = db.get_task_by_id(id).unwrap();
+    assert!(!task.is_all_day);
+    assert_eq!(task.due_date, Some(at("2024-04-30T16:00:00Z")));
+
+    // 设为全天时取用户时区的日期
+    db.update_task(UpdateTaskRequest { due_date: Patch::Set(at("2024-05-02T17:00:00Z")), ..update_request(id) }).unwrap();
+    db.update_task(UpdateTaskRequest { is_all_day: Some(true), ..update_request(id) }).unwrap();
+    assert_eq!(db.get_task_by_id(id).unwrap().due_date, Some(at("2024-05-03T00:00:00Z")));
+}
+
+#[test]
+fn get_all_tasks_orders_by_sort_order() {
+    let db = open_db();
+    let first = add_task(&db, task_request("first"));
+    add_task(&db, task_request("second"));
+    let third = add_task(&db, task_request("third"));
+
+    assert_eq!(titles(&db.get_all_tasks().unwrap()), ["first", "second", "third"]);
+
+    db.update_tasks_sort_order(vec![(third, 1), (first, 3)], None).unwrap();
+    assert_eq!(titles(&db.get_all_tasks().unwrap()), ["third", "second", "first"]);
+}
+
+#[test]
+fn toggle_completion_and_completed_list() {
+    let db = open_db();
+    let id = add_task(&db, task_request("任务"));
+    add_task(&db, task_request("其他"));
+
+    assert!(db.toggle_task_completion(id).unwrap().is_completed);
+    assert_eq!(titles(&db.get_completed_tasks().unwrap()), ["任务"]);
+
+    assert!(!db.toggle_task_completion(id).unwrap().is_completed);
+    assert!(db.get_completed_tasks().unwrap().is_empty());
+}
+
+#[test]
+fn completing_recurring_task_creates_next_occurrence_once() {
+    let db = open_db();
+    set_timezone(&db, "UTC");
+    let id = add_task(&db, CreateTaskRequest {
+        due_date: Some(at("2024-05-01T09:00:00Z")),
+        recurrence: Some(RecurrenceRule { count: Some(2), ..daily() }),
+        tags: Some(vec!["habit".to_string()]),
+        ..task_request("跑步")
+    });
+
+    db.toggle_task_completion(id).unwrap();
+    let tasks = db.get_all_tasks().unwrap();
+    assert_eq!(tasks.len(), 2);
+    let next = tasks.iter().find(|task| task.id != Some(id)).unwrap();
+    assert_eq!(next.due_date, Some(at("2024-05-02T09:00:00Z")));
+    assert_eq!(next.tags, ["habit"]);
+    assert_eq!(next.recurrence.as_ref().unwrap().count, Some(1));
+
+    // 重新打开再完成不会再次生成
+    db.toggle_task_completion(id).unwrap();
+    db.toggle_task_completion(id).unwrap();
+    assert_eq!(db.get_all_tasks().unwrap().len(), 2);
+
+    // 最后一次完成后不再生成
+    db.toggle_task_completion(next.id.unwrap()).unwrap();
+    assert_eq!(db.get_all_tasks().unwrap().len(), 2);
+}
+
+#[test]
+fn subtasks_follow_parent_completion() {
+    let db = open_db();
+    let parent = add_task(&db, task_request("父任务"));
+    let child = add_task(&db, CreateTaskRequest { parent_id: Some(parent), ..task_request("子任务") });
+    let grandchild = add_task(&db, CreateTaskRequest { parent_id: Some(child), ..task_request("孙任务") });
+
+    assert_eq!(titles(&db.get_subtasks(parent).unwrap()), ["子任务"]);
+    assert_eq!(db.get_task_by_id(parent).unwrap().subtask_count, 1);
+
+    // 完成父任务时子孙任务一并完成
+    db.toggle_task_completion(parent).unwrap();
+    assert!(db.get_task_by_id(grandchild).unwrap().is_completed);
+    assert_eq!(db.get_task_by_id(parent).unwrap().completed_subtask_count, 1);
+
+    // 重新打开孙任务时祖先任务一并重新打开
+    db.toggle_task_completion(grandchild).unwrap();
+    assert!(!db.get_task_by_id(child).unwrap().is_completed);
+    assert!(!db.get_task_by_id(parent).unwrap().is_completed);
+
+    // 在已完成的任务下新建子任务会重新打开它
+    db.toggle_task_completion(parent).unwrap();
+    add_task(&db, CreateTaskRequest { parent_id: Some(parent), ..task_request("新的子任务") });
+    assert!(!db.get_task_by_id(parent).unwrap().is_completed);
+}
+
+#[test]
+fn move_subtask_rejects_cycles() {
+    let db = open_db();
+    let parent = add_task(&db, task_request("父任务"));
+    let child = add_task(&db, CreateTaskRequest { parent_id: Some(parent), ..task_request("子任务") });
+    let other = add_task(&db, task_request("其他"));
+
+    assert_error(db.move_subtask(parent, Some(child)), ErrorCode::Validation);
+    assert_error(db.move_subtask(parent, Some(parent)), ErrorCode::Validation);
+    assert_error(db.move_subtask(child, Some(999)), ErrorCode::NotFound);
+
+    assert_eq!(db.move_subtask(child, Some(other)).unwrap().parent_id, Some(other));
+    assert_eq!(db.move_subtask(child, None).unwrap().parent_id, None);
+    assert!(db.get_subtasks(parent).unwrap().is_empty());
+}
+
+#[test]
+fn delete_restore_and_empty_trash() {
+    let db = open_db();
+    let parent = add_task(&db, task_request("父任务"));
+    let child = add_task(&db, CreateTaskRequest { parent_id: Some(parent), ..task_request("子任务") });
+    add_task(&db, task_request("保留"));
+
+    db.delete_task(parent).unwrap();
+    assert_eq!(titles(&db.get_all_tasks().unwrap()), ["保留"]);
+    assert_eq!(db.get_trashed_tasks().unwrap().len(), 2);
+    assert_error(db.get_task_by_id(child), ErrorCode::NotFound);
+    assert_error(db.delete_task(parent), ErrorCode::NotFound);
+    assert_error(db.delete_task(child), ErrorCode::NotFound);
+    assert_error(db.delete_task(999), ErrorCode::NotFound);
+
+    // 恢复时同一次删除的子任务一起恢复
+    assert!(db.restore_task(parent).unwrap().deleted_at.is_none());
+    assert!(db.get_task_by_id(child).unwrap().deleted_at.is_none());
+    assert!(db.get_trashed_tasks().unwrap().is_empty());
+
+    // 父任务仍在回收站时，恢复的子任务成为顶层任务
+    db.delete_task(parent).unwrap();
+    assert_eq!(db.restore_task(child).unwrap().parent_id, None);
+
+    assert_eq!(db.empty_trash().unwrap(), 1);
+    assert_error(db.get_task_by_id(parent), ErrorCode::NotFound);
+    assert_eq!(titles(&db.get_all_tasks().unwrap()), ["子任务", "保留"]);
+}
+
+#[test]
+fn trashed_tasks_cannot_be_changed() {
+    let db = open_db();
+    set_timezone(&db, "UTC");
+    let recurring = add_task(&db, CreateTaskRequest {
+        due_date: Some(at("2024-05-01T09:00:00Z")),
+        recurrence: Some(daily()),
+        ..task_request("重复任务")
+    });
+    let parent = add_task(&db, task_request("父任务"));
+    let live = add_task(&db, task_request("正常"));
+    db.delete_task(recurring).unwrap();
+    db.delete_task(parent).unwrap();
+
+    assert_error(db.update_task(UpdateTaskRequest { title: Some("新标题".to_string()), ..update_request(recurring) }), ErrorCode::NotFound);
+    assert_error(db.toggle_task_completion(recurring), ErrorCode::NotFound);
+    assert_error(db.move_subtask(recurring, None), ErrorCode::NotFound);
+    assert_error(db.move_subtask(live, Some(parent)), ErrorCode::NotFound);
+    assert_error(db.create_task(CreateTaskRequest { parent_id: Some(parent), ..task_request("子任务") }), ErrorCode::NotFound);
+
+    // 完成回收站中的重复任务不会生成新的任务
+    assert_eq!(titles(&db.get_all_tasks().unwrap()), ["正常"]);
+    assert_eq!(db.get_trashed_tasks().unwrap().len(), 2);
+    assert!(!db.get_trashed_tasks().unwrap().iter().any(|task| task.is_completed || task.title == "新标题"));
+}
+
+#[test]
+fn clear_all_tasks_moves_everything_to_trash() {
+    let db = open_db();
+    add_task(&db, task_request("一"));
+    add_task(&db, task_request("二"));
+
+    db.clear_all_tasks().unwrap();
+    assert!(db.get_all_tasks().unwrap().is_empty());
+    assert_eq!(db.get_trashed_tasks().unwrap().len(), 2);
+}
+
+#[test]
+fn search_matches_title_description_and_tags() {
+    let db = open_db();
+    add_task(&db, CreateTaskRequest { description: Some("准备季度报告的图表".to_string()), ..task_request("整理资料") });
+    add_task(&db, CreateTaskRequest { tags: Some(vec!["groceries".to_string()]), ..task_request("买牛奶") });
+    let trashed = add_task(&db, task_request("报告草稿"));
+    db.delete_task(trashed).unwrap();
+
+    let results = db.search_tasks("报告", 10).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].task.title, "整理资料");
+    assert!(results[0].snippet.contains("<mark>"));
+
+    let results = db.search_tasks("groceries", 10).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].task.title, "买牛奶");
+
+    assert!(db.search_tasks("  ", 10).unwrap().is_empty());
+}
+
+#[test]
+fn search_highlights_escape_html() {
+    let db = open_db();
+    add_task(&db, CreateTaskRequest {
+        description: Some("报告 <script>alert('x')</script> & \"引用\"".to_string()),
+        ..task_request("<img src=x onerror=alert(1)> 报告")
+    });
+
+    let results = db.search_tasks("报告", 10).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].title_highlight, "&lt;img src=x onerror=alert(1)&gt; <mark>报告</mark>");
+    assert_eq!(results[0].snippet, "<mark>报告</mark> &lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; &amp; &quot;引用&quot;");
+    // 原始标题保持不变
+    assert_eq!(results[0].task.title, "<img src=x onerror=alert(1)> 报告");
+}
+
+#[test]
+fn search_follows_task_updates() {
+    let db = open_db();
+    let id = add_task(&db, task_request("旧标题"));
+
+    db.update_task(UpdateTaskRequest { title: Some("新标题".to_string()), ..update_request(id) }).unwrap();
+    assert!(db.search_tasks("旧标题", 10).unwrap().is_empty());
+    assert_eq!(db.search_tasks("新标题", 10).unwrap().len(), 1);
+}
+
+#[test]
+fn query_tasks_filters_sorts_and_pages() {
+    let db = open_db();
+    for (title, priority, tag) in [
+        ("a", TaskPriority::Low, "work"),
+        ("b", TaskPriority::High, "work/clientA"),
+        ("c", TaskPriority::Medium, "home"),
+        ("d", TaskPriority::High, "work"),
+    ] {
+        add_task(&db, CreateTaskRequest { priority, tags: Some(vec![tag.to_string()]), ..task_request(title) });
+    }
+
+    // 上级标签包含子标签的任务
+    let query = TaskQuery {
+        filter: TaskFilter { tags_any: vec!["work".to_string()], ..Default::default() },
+        sort: vec![
+            TaskSortKey { field: TaskSortField::Priority, descending: true },
+            TaskSortKey { field: TaskSortField::Title, descending: false },
+        ],
+        ..Default::default()
+    };
+    let result = db.query_tasks(&query).unwrap();
+    assert_eq!(titles(&result.tasks), ["b", "d", "a"]);
+    assert_eq!(result.total, 3);
+
+    let page = db.query_tasks(&TaskQuery { limit: Some(1), offset: Some(1), ..query.clone() }).unwrap();
+    assert_eq!(titles(&page.tasks), ["d"]);
+    assert_eq!(page.total, 3);
+
+    let filter = TaskFilter { tags_none: vec!["work".to_string()], ..Default::default() };
+    assert_eq!(db.count_tasks(&filter).unwrap(), 1);
+
+    let filter = TaskFilter { priorities: vec![TaskPriority::High], text: Some("d".to_string()), ..Default::default() };
+    assert_eq!(titles(&db.query_tasks(&TaskQuery { filter, ..Default::default() }).unwrap().tasks), ["d"]);
+}
+
+#[test]
+fn query_tasks_filters_by_due_date() {
+    let db = open_db();
+    set_timezone(&db, "UTC");
+    let now = Utc::now();
+    add_task(&db, CreateTaskRequest { due_date: Some(now - Duration::days(2)), ..task_request("过期") });
+    add_task(&db, CreateTaskRequest { due_date: Some(now + Duration::days(3)), ..task_request("三天后") });
+    add_task(&db, CreateTaskRequest { due_date: Some(now + Duration::days(30)), ..task_request("下个月") });
+    add_task(&db, task_request("没有截止时间"));
+
+    let due = |filter: TaskFilter| {
+        let query = TaskQuery { filter, sort: vec![TaskSortKey { field: TaskSortField::DueDate, descending: false }], ..Default::default() };
+        titles(&db.query_tasks(&query).unwrap().tasks).into_iter().map(str::to_string).collect::<Vec<_>>()
+    };
+
+    assert_eq!(due(TaskFilter { due_range: Some(RelativeDueRange::Overdue), ..Default::default() }), ["过期"]);
+    assert_eq!(due(TaskFilter { due_range: Some(RelativeDueRange::Next7Days), ..Default::default() }), ["三天后"]);
+    assert_eq!(due(TaskFilter { due_after: Some(now), ..Default::default() }), ["三天后", "下个月"]);
+    assert_eq!(due(TaskFilter { due_before: Some(now + Duration::days(4)), ..Default::default() }), ["过期", "三天后"]);
+}
+
+#[test]
+fn today_tasks_use_user_time_zone() {
+    let db = open_db();
+    set_timezone(&db, "UTC");
+    let now = Utc::now();
+    let today = timezone::UserTimeZone::utc().today();
+    add_task(&db, CreateTaskRequest { due_date: Some(now), ..task_request("现在") });
+    add_task(&db, CreateTaskRequest { due_date: Some(timezone::all_day_due_date(today)), is_all_day: true, ..task_request("今天全天") });
+    add_task(&db, CreateTaskRequest { due_date: Some(now + Duration::days(1)), ..task_request("明天") });
+    add_task(&db, task_request("没有截止时间"));
+
+    let today_tasks = db.get_today_tasks().unwrap();
+    let mut today_titles = titles(&today_tasks);
+    today_titles.sort();
+    assert_eq!(today_titles, ["今天全天", "现在"]);
+}
+
+#[test]
+fn tasks_due_windows() {
+    let db = open_db();
+    set_timezone(&db, "UTC");
+    let now = Utc::now();
+    add_task(&db, CreateTaskRequest { due_date: Some(now - Duration::hours(1)), ..task_request("过期") });
+    add_task(&db, CreateTaskRequest { due_date: Some(now + Duration::minutes(10)), ..task_request("十分钟后") });
+    add_task(&db, CreateTaskRequest { due_date: Some(now + Duration::hours(2)), ..task_request("两小时后") });
+    let completed = add_task(&db, CreateTaskRequest { due_date: Some(now - Duration::hours(2)), ..task_request("已完成") });
+    db.toggle_task_completion(completed).unwrap();
+
+    assert_eq!(titles(&db.get_overdue_tasks().unwrap()), ["过期"]);
+    assert_eq!(titles(&db.get_upcoming_tasks(30).unwrap()), ["十分钟后"]);
+    let window = DueWindow::Between { start: now - Duration::days(1), end: now + Duration::days(1) };
+    assert_eq!(titles(&db.get_tasks_due(window).unwrap()), ["过期", "十分钟后", "两小时后"]);
+    assert_error(db.get_tasks_due(DueWindow::DueWithin { minutes: -5 }), ErrorCode::Validation);
+}
+
+fn add_due_task(db: &Database, title: &str, due_date: DateTime<Utc>, is_all_day: bool) {
+    add_task(db, CreateTaskRequest { due_date: Some(due_date), is_all_day, ..task_request(title) });
+}
+
+/// 以 `now` 作为当前时间查询，返回按截止时间排序的任务标题
+fn due_titles(db: &Database, window: DueWindow, now: DateTime<Utc>) -> Vec<String> {
+    db.get_tasks_due_at(window, now).unwrap().into_iter().map(|task| task.title).collect()
+}
+
+#[test]
+fn due_window_between_includes_start_and_excludes_end() {
+    let db = open_db();
+    let start = at("2024-03-10T08:00:00Z");
+    let end = at("2024-03-10T12:00:00Z");
+    add_due_task(&db, "before", start - Duration::seconds(1), false);
+    add_due_task(&db, "start", start, false);
+    add_due_task(&db, "last", end - Duration::seconds(1), false);
+    add_due_task(&db, "end", end, false);
+
+    assert_eq!(due_titles(&db, DueWindow::Between { start, end }, start), ["start", "last"]);
+}
+
+#[test]
+fn due_within_excludes_now_and_includes_last_minute() {
+    let db = open_db();
+    let now = at("2024-03-10T08:00:00Z");
+    add_due_task(&db, "now", now, false);
+    add_due_task(&db, "next", now + Duration::seconds(1), false);
+    add_due_task(&db, "limit", now + Duration::minutes(30), false);
+    add_due_task(&db, "after", now + Duration::minutes(30) + Duration::seconds(1), false);
+
+    assert_eq!(due_titles(&db, DueWindow::DueWithin { minutes: 30 }, now), ["next", "limit"]);
+    assert!(due_titles(&db, DueWindow::DueWithin { minutes: 0 }, now).is_empty());
+}
+
+#[test]
+fn overdue_excludes_tasks_due_now_completed_and_trashed() {
+    let db = open_db();
+    let now = at("2024-03-10T08:00:00Z");
+    add_due_task(&db, "past", now - Duration::seconds(1), false);
+    add_due_task(&db, "now", now, false);
+    let completed = add_task(&db, CreateTaskRequest { due_date: Some(now - Duration::hours(1)), ..task_request("completed") });
+    let trashed = add_task(&db, CreateTaskRequest { due_date: Some(now - Duration::hours(1)), ..task_request("trashed") });
+    db.toggle_task_completion(completed).unwrap();
+    db.delete_task(trashed).unwrap();
+
+    assert_eq!(due_titles(&db, DueWindow::Overdue, now), ["past"]);
+}
+
+#[test]
+fn all_day_due_windows_follow_user_time_zone_days() {
+    let db = open_db();
+    set_timezone(&db, "Asia/Shanghai");
+    // 上海时间 2024-03-10 10:00
+    let now = at("2024-03-10T02:00:00Z");
+    add_due_task(&db, "mar 9", at("2024-03-09T00:00:00Z"), true);
+    add_due_task(&db, "mar 10", at("2024-03-10T00:00:00Z"), true);
+    add_due_task(&db, "mar 11", at("2024-03-11T00:00:00Z"), true);
+    add_due_task(&db, "mar 12", at("2024-03-12T00:00:00Z"), true);
+
+    // 截止日期当天还没有过期
+    assert_eq!(due_titles(&db, DueWindow::Overdue, now), ["mar 9"]);
+    // 上海时间 3 月 11 日零点在 24 小时之内，3 月 10 日零点已经过去
+    assert_eq!(due_titles(&db, DueWindow::DueWithin { minutes: 24 * 60 }, now), ["mar 11"]);
+    // 上海时间的 3 月 11 日
+    let window = DueWindow::Between { start: at("2024-03-10T16:00:00Z"), end: at("2024-03-11T16:00:00Z") };
+    assert_eq!(due_titles(&db, window, now), ["mar 11"]);
+    // 从 3 月 11 日零点后一秒开始的窗口不再包含 3 月 11 日
+    let window = DueWindow::Between { start: at("2024-03-10T16:00:01Z"), end: at("2024-03-12T16:00:00Z") };
+    assert_eq!(due_titles(&db, window, now), ["mar 12"]);
+}
+
+#[test]
+fn invalid_due_windows_are_rejected() {
+    let db = open_db();
+    let now = at("2024-03-10T08:00:00Z");
+
+    assert_error(db.get_tasks_due_at(DueWindow::DueWithin { minutes: -1 }, now), ErrorCode::Validation);
+    let window = DueWindow::Between { start: now, end: now - Duration::seconds(1) };
+    assert_error(db.get_tasks_due_at(window, now), ErrorCode::Validation);
+}
+
+#[test]
+fn notifications_are_sent_once_per_due_date() {
+    let db = open_db();
+    let now = Utc::now();
+    let due_date = now + Duration::minutes(10);
+    let id = add_task(&db, CreateTaskRequest { due_date: Some(due_date), ..task_request("开会") });
+    add_task(&db, CreateTaskRequest { due_date: Some(now + Duration::hours(3)), ..task_request("稍后") });
+
+    assert_eq!(titles(&db.get_pending_notifications(15).unwrap()), ["开会"]);
+    assert!(db.get_pending_notifications(5).unwrap().is_empty());
+    let next = db.get_next_notification_time(15).unwrap().unwrap();
+    assert_eq!(next.timestamp(), (due_date - Duration::minutes(15)).timestamp());
+
+    db.mark_task_notified(id, db.get_task_by_id(id).unwrap().due_date.unwrap()).unwrap();
+    assert!(db.get_pending_notifications(15).unwrap().is_empty());
+
+    // 修改截止时间后会再次提醒
+    db.update_task(UpdateTaskRequest { due_date: Patch::Set(now + Duration::minutes(5)), ..update_request(id) }).unwrap();
+    assert_eq!(titles(&db.get_pending_notifications(15).unwrap()), ["开会"]);
+}
+
+#[test]
+fn task_history_records_changed_fields() {
+    let db = open_db();
+    let id = add_task(&db, task_request("旧标题"));
+
+    db.update_task(UpdateTaskRequest {
+        title: Some("新标题".to_string()),
+        tags: Patch::Set(vec!["work".to_string()]),
+        ..update_request(id)
+    }).unwrap();
+    db.toggle_task_completion(id).unwrap();
+
+    let history = db.get_task_history(id).unwrap();
+    let fields: Vec<&str> = history.iter().map(|entry| entry.field.as_str()).collect();
+    assert_eq!(fields, ["title", "tags", "is_completed"]);
+    assert_eq!(history[0].old_value, Some(serde_json::json!("旧标题")));
+    assert_eq!(history[0].new_value, Some(serde_json::json!("新标题")));
+    assert_eq!(history[1].new_value, Some(serde_json::json!(["work"])));
+
+    // 没有变化的更新不产生记录
+    db.update_task(UpdateTaskRequest { title: Some("新标题".to_string()), ..update_request(id) }).unwrap();
+    assert_eq!(db.get_task_history(id).unwrap().len(), 3);
+}
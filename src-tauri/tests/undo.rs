@@ -0,0 +1,149 @@
+//! 撤销/重做和数据一致性检查
+
+mod common;
+
+use app_lib::database::Database;
+use app_lib::error::{AppError, ErrorCode};
+use app_lib::models::{CreateTaskRequest, UpdateTaskRequest};
+use chrono::{Duration, Utc};
+use rusqlite::Connection;
+use common::*;
+
+#[test]
+fn undo_and_redo_task_changes() {
+    let db = open_db();
+    let status = db.get_undo_status().unwrap();
+    assert!(!status.can_undo && !status.can_redo);
+
+    let id = db.record_undo_step("新建任务", |db| {
+        db.create_task(CreateTaskRequest { tags: Some(vec!["work".to_string()]), ..task_request("任务") })
+    }).unwrap().id.unwrap();
+    db.record_undo_step("修改任务", |db| {
+        db.update_task(UpdateTaskRequest { title: Some("新标题".to_string()), ..update_request(id) })
+    }).unwrap();
+
+    let status = db.get_undo_status().unwrap();
+    assert_eq!(status.undo_description.as_deref(), Some("修改任务"));
+
+    assert_eq!(db.undo().unwrap().as_deref(), Some("修改任务"));
+    assert_eq!(db.get_task_by_id(id).unwrap().title, "任务");
+    assert_eq!(db.search_tasks("任务", 10).unwrap().len(), 1);
+
+    assert_eq!(db.undo().unwrap().as_deref(), Some("新建任务"));
+    assert_error(db.get_task_by_id(id), ErrorCode::NotFound);
+    assert!(db.search_tasks("任务", 10).unwrap().is_empty());
+    assert_eq!(db.undo().unwrap(), None);
+
+    let status = db.get_undo_status().unwrap();
+    assert!(!status.can_undo);
+    assert_eq!(status.redo_description.as_deref(), Some("新建任务"));
+
+    assert_eq!(db.redo().unwrap().as_deref(), Some("新建任务"));
+    let task = db.get_task_by_id(id).unwrap();
+    assert_eq!(task.title, "任务");
+    assert_eq!(task.tags, ["work"]);
+    assert_eq!(db.redo().unwrap().as_deref(), Some("修改任务"));
+    assert_eq!(db.get_task_by_id(id).unwrap().title, "新标题");
+    assert_eq!(db.redo().unwrap(), None);
+}
+
+#[test]
+fn undo_empty_trash_restores_history_and_notifications() {
+    let db = open_db();
+    let id = add_task(&db, CreateTaskRequest { due_date: Some(Utc::now() + Duration::minutes(10)), ..task_request("任务") });
+    db.record_undo_step("修改任务", |db| {
+        db.update_task(UpdateTaskRequest { title: Some("新标题".to_string()), ..update_request(id) })
+    }).unwrap();
+    db.mark_task_notified(id, db.get_task_by_id(id).unwrap().due_date.unwrap()).unwrap();
+    let history = db.get_task_history(id).unwrap();
+    assert_eq!(history.len(), 1);
+
+    db.record_undo_step("删除任务", |db| db.delete_task(id)).unwrap();
+    db.record_undo_step("清空回收站", |db| db.empty_trash()).unwrap();
+    assert!(db.get_task_history(id).unwrap().is_empty());
+
+    assert_eq!(db.undo().unwrap().as_deref(), Some("清空回收站"));
+    assert_eq!(db.undo().unwrap().as_deref(), Some("删除任务"));
+    assert_eq!(db.get_task_history(id).unwrap().len(), history.len());
+    // 提醒记录一起恢复，不会再次提醒
+    assert!(db.get_pending_notifications(15).unwrap().is_empty());
+
+    // 撤销修改时一起删除这次修改的历史记录
+    assert_eq!(db.undo().unwrap().as_deref(), Some("修改任务"));
+    assert!(db.get_task_history(id).unwrap().is_empty());
+    assert_eq!(db.redo().unwrap().as_deref(), Some("修改任务"));
+    assert_eq!(db.get_task_history(id).unwrap().len(), 1);
+}
+
+#[test]
+fn new_step_discards_redo_history() {
+    let db = open_db();
+    db.record_undo_step("第一步", |db| db.create_task(task_request("一"))).unwrap();
+    db.undo().unwrap();
+    assert!(db.get_undo_status().unwrap().can_redo);
+
+    db.record_undo_step("第二步", |db| db.create_task(task_request("二"))).unwrap();
+    let status = db.get_undo_status().unwrap();
+    assert!(!status.can_redo);
+    assert_eq!(status.undo_description.as_deref(), Some("第二步"));
+}
+
+#[test]
+fn failed_step_is_rolled_back_without_history() {
+    let db = open_db();
+
+    let result: Result<(), AppError> = db.record_undo_step("失败的操作", |db| {
+        db.create_task(task_request("半途"))?;
+        Err(AppError::conflict("中途失败"))
+    });
+    assert_error(result, ErrorCode::Conflict);
+    assert!(db.get_all_tasks().unwrap().is_empty());
+    assert!(!db.get_undo_status().unwrap().can_undo);
+}
+
+#[test]
+fn steps_without_changes_are_not_recorded() {
+    let db = open_db();
+    db.record_undo_step("只读", |db| db.get_all_tasks()).unwrap();
+    assert!(!db.get_undo_status().unwrap().can_undo);
+}
+
+#[test]
+fn clear_undo_history() {
+    let db = open_db();
+    db.record_undo_step("新建任务", |db| db.create_task(task_request("任务"))).unwrap();
+
+    db.clear_undo_history().unwrap();
+    assert!(!db.get_undo_status().unwrap().can_undo);
+    assert_eq!(db.undo().unwrap(), None);
+    assert_eq!(db.get_all_tasks().unwrap().len(), 1);
+}
+
+#[test]
+fn consistency_check_reports_and_repairs_orphans() {
+    let dir = TempDir::new("consistency");
+    let db = Database::open_unencrypted(&dir.db_path()).unwrap();
+    let id = add_task(&db, CreateTaskRequest { tags: Some(vec!["work".to_string()]), ..task_request("任务") });
+    let report = db.check_consistency(false).unwrap();
+    assert!(report.issues.is_empty() && !report.repaired);
+
+    // 绕过外键约束写入不一致的数据
+    let connection = Connection::open(dir.db_path()).unwrap();
+    connection.execute_batch(&format!(
+        "PRAGMA foreign_keys = OFF;
+         INSERT INTO task_tags (task_id, tag_id) VALUES (999, 1);
+         DELETE FROM tasks_fts WHERE rowid = {};",
+        id
+    )).unwrap();
+    drop(connection);
+
+    let report = db.check_consistency(false).unwrap();
+    let mut kinds: Vec<&str> = report.issues.iter().map(|issue| issue.kind.as_str()).collect();
+    kinds.sort();
+    assert_eq!(kinds, ["missing_search_index", "orphaned_task_tags"]);
+    assert!(!report.repaired);
+
+    assert!(db.check_consistency(true).unwrap().repaired);
+    assert!(db.check_consistency(false).unwrap().issues.is_empty());
+    assert_eq!(db.search_tasks("任务", 10).unwrap().len(), 1);
+}